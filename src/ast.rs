@@ -1,10 +1,20 @@
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
-    Bold(Vec<Token>),
-    Italic(Vec<Token>),
-    InlineCode(Vec<Token>),
-    Regular(String),
-    Link { label: Vec<Token>, url: String },
+    Bold(Vec<Token>, Span),
+    Italic(Vec<Token>, Span),
+    InlineCode(Vec<Token>, Span),
+    Regular(String, Span),
+    Link {
+        label: Vec<Token>,
+        url: String,
+        span: Span,
+    },
+    Image {
+        label: Vec<Token>,
+        url: String,
+        span: Span,
+    },
+    SoftBreak(Span),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -12,13 +22,75 @@ pub enum Line {
     Header {
         level: HeaderLevel,
         tokens: Vec<Token>,
+        span: Span,
     },
-    Paragraph(Vec<Token>),
+    Paragraph(Vec<Token>, Span),
     Image {
         label: Vec<Token>,
         url: String,
+        span: Span,
     },
-    Blank,
+    CodeBlock {
+        info: String,
+        content: String,
+        span: Span,
+    },
+    List {
+        ordered: bool,
+        start: u64,
+        loose: bool,
+        items: Vec<ListItem>,
+        span: Span,
+    },
+    Blockquote(Vec<Line>, Span),
+    Table {
+        alignments: Vec<Alignment>,
+        header: Vec<Vec<Token>>,
+        rows: Vec<Vec<Vec<Token>>>,
+        span: Span,
+    },
+    Blank(Span),
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct ListItem {
+    pub tokens: Vec<Token>,
+    pub children: Vec<Line>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+}
+
+/// A region of the source text: `start..end` byte offsets, plus the 1-based
+/// line and column (in characters) where it starts.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: self.end.max(other.end),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -32,3 +104,491 @@ pub enum HeaderLevel {
 }
 
 pub type Document = Vec<Line>;
+
+impl HeaderLevel {
+    /// The level as a number, 1 for `H1` to 6 for `H6`.
+    pub fn number(&self) -> u8 {
+        match self {
+            HeaderLevel::H1 => 1,
+            HeaderLevel::H2 => 2,
+            HeaderLevel::H3 => 3,
+            HeaderLevel::H4 => 4,
+            HeaderLevel::H5 => 5,
+            HeaderLevel::H6 => 6,
+        }
+    }
+}
+
+impl Token {
+    pub fn span(&self) -> Span {
+        match self {
+            Token::Bold(_, span)
+            | Token::Italic(_, span)
+            | Token::InlineCode(_, span)
+            | Token::Regular(_, span)
+            | Token::Link { span, .. }
+            | Token::Image { span, .. }
+            | Token::SoftBreak(span) => *span,
+        }
+    }
+
+    pub(crate) fn span_mut(&mut self) -> &mut Span {
+        match self {
+            Token::Bold(_, span)
+            | Token::Italic(_, span)
+            | Token::InlineCode(_, span)
+            | Token::Regular(_, span)
+            | Token::Link { span, .. }
+            | Token::Image { span, .. }
+            | Token::SoftBreak(span) => span,
+        }
+    }
+
+    /// Resets the spans of this token and everything nested in it, so that
+    /// tokens parsed from different sources can be compared structurally.
+    pub fn clear_spans(&mut self) {
+        ClearSpans.visit_token_mut(self);
+    }
+}
+
+impl Line {
+    pub fn span(&self) -> Span {
+        match self {
+            Line::Header { span, .. }
+            | Line::Paragraph(_, span)
+            | Line::Image { span, .. }
+            | Line::CodeBlock { span, .. }
+            | Line::List { span, .. }
+            | Line::Blockquote(_, span)
+            | Line::Table { span, .. }
+            | Line::Blank(span) => *span,
+        }
+    }
+
+    pub(crate) fn span_mut(&mut self) -> &mut Span {
+        match self {
+            Line::Header { span, .. }
+            | Line::Paragraph(_, span)
+            | Line::Image { span, .. }
+            | Line::CodeBlock { span, .. }
+            | Line::List { span, .. }
+            | Line::Blockquote(_, span)
+            | Line::Table { span, .. }
+            | Line::Blank(span) => span,
+        }
+    }
+
+    /// Resets the spans of this line and everything nested in it, so that
+    /// lines parsed from different sources can be compared structurally.
+    pub fn clear_spans(&mut self) {
+        ClearSpans.visit_line_mut(self);
+    }
+}
+
+/// Walks a document without changing it. Each method visits one kind of
+/// node, named as queries name it; by default it walks the nodes inside, so
+/// a visitor overrides only the kinds it is interested in:
+///
+/// ```
+/// use md_parser::{Span, Token, Visit};
+///
+/// #[derive(Default)]
+/// struct Urls(Vec<String>);
+///
+/// impl Visit for Urls {
+///     fn visit_link(&mut self, label: &[Token], url: &str, _: Span) {
+///         self.0.push(url.to_string());
+///         md_parser::walk_tokens(self, label);
+///     }
+/// }
+///
+/// let document = md_parser::parse("> See [a](https://a.com) and **[b](/b)**");
+/// let mut urls = Urls::default();
+/// urls.visit_lines(&document);
+/// assert_eq!(urls.0, ["https://a.com", "/b"]);
+/// ```
+///
+/// An overridden method walks on by calling the `walk_` function or the
+/// `visit_` methods for what is inside, or leaves them out by not doing so.
+#[allow(unused_variables)]
+pub trait Visit {
+    fn visit_lines(&mut self, lines: &[Line]) {
+        walk_lines(self, lines);
+    }
+
+    fn visit_line(&mut self, line: &Line) {
+        walk_line(self, line);
+    }
+
+    fn visit_header(&mut self, level: &HeaderLevel, tokens: &[Token], span: Span) {
+        walk_tokens(self, tokens);
+    }
+
+    fn visit_paragraph(&mut self, tokens: &[Token], span: Span) {
+        walk_tokens(self, tokens);
+    }
+
+    /// Visits an image, whether it is a line of its own or inline.
+    fn visit_image(&mut self, label: &[Token], url: &str, span: Span) {
+        walk_tokens(self, label);
+    }
+
+    fn visit_code_block(&mut self, info: &str, content: &str, span: Span) {}
+
+    fn visit_list(
+        &mut self,
+        ordered: bool,
+        start: u64,
+        loose: bool,
+        items: &[ListItem],
+        span: Span,
+    ) {
+        items.iter().for_each(|item| self.visit_list_item(item));
+    }
+
+    fn visit_list_item(&mut self, item: &ListItem) {
+        walk_list_item(self, item);
+    }
+
+    fn visit_blockquote(&mut self, lines: &[Line], span: Span) {
+        walk_lines(self, lines);
+    }
+
+    fn visit_table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Token>],
+        rows: &[Vec<Vec<Token>>],
+        span: Span,
+    ) {
+        self.visit_table_row(header);
+        rows.iter().for_each(|row| self.visit_table_row(row));
+    }
+
+    fn visit_table_row(&mut self, cells: &[Vec<Token>]) {
+        cells.iter().for_each(|cell| self.visit_table_cell(cell));
+    }
+
+    fn visit_table_cell(&mut self, tokens: &[Token]) {
+        walk_tokens(self, tokens);
+    }
+
+    fn visit_blank(&mut self, span: Span) {}
+
+    fn visit_tokens(&mut self, tokens: &[Token]) {
+        walk_tokens(self, tokens);
+    }
+
+    fn visit_token(&mut self, token: &Token) {
+        walk_token(self, token);
+    }
+
+    fn visit_bold(&mut self, tokens: &[Token], span: Span) {
+        walk_tokens(self, tokens);
+    }
+
+    fn visit_italic(&mut self, tokens: &[Token], span: Span) {
+        walk_tokens(self, tokens);
+    }
+
+    fn visit_inline_code(&mut self, tokens: &[Token], span: Span) {
+        walk_tokens(self, tokens);
+    }
+
+    fn visit_regular(&mut self, text: &str, span: Span) {}
+
+    fn visit_link(&mut self, label: &[Token], url: &str, span: Span) {
+        walk_tokens(self, label);
+    }
+
+    fn visit_soft_break(&mut self, span: Span) {}
+
+    /// Visits the span of every line, list item and token as it is walked,
+    /// before the method for its kind.
+    fn visit_span(&mut self, span: Span) {}
+}
+
+pub fn walk_lines<V: Visit + ?Sized>(visitor: &mut V, lines: &[Line]) {
+    lines.iter().for_each(|line| visitor.visit_line(line));
+}
+
+/// Visits the span of `line`, then the method for its kind.
+pub fn walk_line<V: Visit + ?Sized>(visitor: &mut V, line: &Line) {
+    visitor.visit_span(line.span());
+    match line {
+        Line::Header {
+            level,
+            tokens,
+            span,
+        } => visitor.visit_header(level, tokens, *span),
+        Line::Paragraph(tokens, span) => visitor.visit_paragraph(tokens, *span),
+        Line::Image { label, url, span } => visitor.visit_image(label, url, *span),
+        Line::CodeBlock {
+            info,
+            content,
+            span,
+        } => visitor.visit_code_block(info, content, *span),
+        Line::List {
+            ordered,
+            start,
+            loose,
+            items,
+            span,
+        } => visitor.visit_list(*ordered, *start, *loose, items, *span),
+        Line::Blockquote(lines, span) => visitor.visit_blockquote(lines, *span),
+        Line::Table {
+            alignments,
+            header,
+            rows,
+            span,
+        } => visitor.visit_table(alignments, header, rows, *span),
+        Line::Blank(span) => visitor.visit_blank(*span),
+    }
+}
+
+/// Visits the span of `item`, then its tokens and the lines nested in it.
+pub fn walk_list_item<V: Visit + ?Sized>(visitor: &mut V, item: &ListItem) {
+    visitor.visit_span(item.span);
+    visitor.visit_tokens(&item.tokens);
+    visitor.visit_lines(&item.children);
+}
+
+pub fn walk_tokens<V: Visit + ?Sized>(visitor: &mut V, tokens: &[Token]) {
+    tokens.iter().for_each(|token| visitor.visit_token(token));
+}
+
+/// Visits the span of `token`, then the method for its kind.
+pub fn walk_token<V: Visit + ?Sized>(visitor: &mut V, token: &Token) {
+    visitor.visit_span(token.span());
+    match token {
+        Token::Bold(tokens, span) => visitor.visit_bold(tokens, *span),
+        Token::Italic(tokens, span) => visitor.visit_italic(tokens, *span),
+        Token::InlineCode(tokens, span) => visitor.visit_inline_code(tokens, *span),
+        Token::Regular(text, span) => visitor.visit_regular(text, *span),
+        Token::Link { label, url, span } => visitor.visit_link(label, url, *span),
+        Token::Image { label, url, span } => visitor.visit_image(label, url, *span),
+        Token::SoftBreak(span) => visitor.visit_soft_break(*span),
+    }
+}
+
+/// Walks a document, changing it in place. It has the methods of `Visit`,
+/// with `_mut` after their names, over mutable nodes:
+///
+/// ```
+/// use md_parser::{Span, Token, VisitMut};
+///
+/// struct Https;
+///
+/// impl VisitMut for Https {
+///     fn visit_link_mut(&mut self, label: &mut Vec<Token>, url: &mut String, _: &mut Span) {
+///         *url = url.replace("http://", "https://");
+///         md_parser::walk_tokens_mut(self, label);
+///     }
+/// }
+///
+/// let mut document = md_parser::parse("See [docs](http://a.com/docs).");
+/// Https.visit_lines_mut(&mut document);
+/// assert_eq!(md_parser::to_markdown(&document), "See [docs](https://a.com/docs).\n");
+/// ```
+#[allow(unused_variables)]
+pub trait VisitMut {
+    fn visit_lines_mut(&mut self, lines: &mut Vec<Line>) {
+        walk_lines_mut(self, lines);
+    }
+
+    fn visit_line_mut(&mut self, line: &mut Line) {
+        walk_line_mut(self, line);
+    }
+
+    fn visit_header_mut(
+        &mut self,
+        level: &mut HeaderLevel,
+        tokens: &mut Vec<Token>,
+        span: &mut Span,
+    ) {
+        walk_tokens_mut(self, tokens);
+    }
+
+    fn visit_paragraph_mut(&mut self, tokens: &mut Vec<Token>, span: &mut Span) {
+        walk_tokens_mut(self, tokens);
+    }
+
+    /// Visits an image, whether it is a line of its own or inline.
+    fn visit_image_mut(&mut self, label: &mut Vec<Token>, url: &mut String, span: &mut Span) {
+        walk_tokens_mut(self, label);
+    }
+
+    fn visit_code_block_mut(&mut self, info: &mut String, content: &mut String, span: &mut Span) {}
+
+    fn visit_list_mut(
+        &mut self,
+        ordered: &mut bool,
+        start: &mut u64,
+        loose: &mut bool,
+        items: &mut Vec<ListItem>,
+        span: &mut Span,
+    ) {
+        items
+            .iter_mut()
+            .for_each(|item| self.visit_list_item_mut(item));
+    }
+
+    fn visit_list_item_mut(&mut self, item: &mut ListItem) {
+        walk_list_item_mut(self, item);
+    }
+
+    fn visit_blockquote_mut(&mut self, lines: &mut Vec<Line>, span: &mut Span) {
+        walk_lines_mut(self, lines);
+    }
+
+    fn visit_table_mut(
+        &mut self,
+        alignments: &mut Vec<Alignment>,
+        header: &mut Vec<Vec<Token>>,
+        rows: &mut Vec<Vec<Vec<Token>>>,
+        span: &mut Span,
+    ) {
+        self.visit_table_row_mut(header);
+        rows.iter_mut()
+            .for_each(|row| self.visit_table_row_mut(row));
+    }
+
+    fn visit_table_row_mut(&mut self, cells: &mut Vec<Vec<Token>>) {
+        cells
+            .iter_mut()
+            .for_each(|cell| self.visit_table_cell_mut(cell));
+    }
+
+    fn visit_table_cell_mut(&mut self, tokens: &mut Vec<Token>) {
+        walk_tokens_mut(self, tokens);
+    }
+
+    fn visit_blank_mut(&mut self, span: &mut Span) {}
+
+    fn visit_tokens_mut(&mut self, tokens: &mut Vec<Token>) {
+        walk_tokens_mut(self, tokens);
+    }
+
+    fn visit_token_mut(&mut self, token: &mut Token) {
+        walk_token_mut(self, token);
+    }
+
+    fn visit_bold_mut(&mut self, tokens: &mut Vec<Token>, span: &mut Span) {
+        walk_tokens_mut(self, tokens);
+    }
+
+    fn visit_italic_mut(&mut self, tokens: &mut Vec<Token>, span: &mut Span) {
+        walk_tokens_mut(self, tokens);
+    }
+
+    fn visit_inline_code_mut(&mut self, tokens: &mut Vec<Token>, span: &mut Span) {
+        walk_tokens_mut(self, tokens);
+    }
+
+    fn visit_regular_mut(&mut self, text: &mut String, span: &mut Span) {}
+
+    fn visit_link_mut(&mut self, label: &mut Vec<Token>, url: &mut String, span: &mut Span) {
+        walk_tokens_mut(self, label);
+    }
+
+    fn visit_soft_break_mut(&mut self, span: &mut Span) {}
+
+    /// Visits the span of every line, list item and token as it is walked,
+    /// before the method for its kind.
+    fn visit_span_mut(&mut self, span: &mut Span) {}
+}
+
+pub fn walk_lines_mut<V: VisitMut + ?Sized>(visitor: &mut V, lines: &mut [Line]) {
+    lines
+        .iter_mut()
+        .for_each(|line| visitor.visit_line_mut(line));
+}
+
+/// Visits the span of `line`, then the method for its kind.
+pub fn walk_line_mut<V: VisitMut + ?Sized>(visitor: &mut V, line: &mut Line) {
+    visitor.visit_span_mut(line.span_mut());
+    match line {
+        Line::Header {
+            level,
+            tokens,
+            span,
+        } => visitor.visit_header_mut(level, tokens, span),
+        Line::Paragraph(tokens, span) => visitor.visit_paragraph_mut(tokens, span),
+        Line::Image { label, url, span } => visitor.visit_image_mut(label, url, span),
+        Line::CodeBlock {
+            info,
+            content,
+            span,
+        } => visitor.visit_code_block_mut(info, content, span),
+        Line::List {
+            ordered,
+            start,
+            loose,
+            items,
+            span,
+        } => visitor.visit_list_mut(ordered, start, loose, items, span),
+        Line::Blockquote(lines, span) => visitor.visit_blockquote_mut(lines, span),
+        Line::Table {
+            alignments,
+            header,
+            rows,
+            span,
+        } => visitor.visit_table_mut(alignments, header, rows, span),
+        Line::Blank(span) => visitor.visit_blank_mut(span),
+    }
+}
+
+/// Visits the span of `item`, then its tokens and the lines nested in it.
+pub fn walk_list_item_mut<V: VisitMut + ?Sized>(visitor: &mut V, item: &mut ListItem) {
+    visitor.visit_span_mut(&mut item.span);
+    visitor.visit_tokens_mut(&mut item.tokens);
+    visitor.visit_lines_mut(&mut item.children);
+}
+
+pub fn walk_tokens_mut<V: VisitMut + ?Sized>(visitor: &mut V, tokens: &mut [Token]) {
+    tokens
+        .iter_mut()
+        .for_each(|token| visitor.visit_token_mut(token));
+}
+
+/// Visits the span of `token`, then the method for its kind.
+pub fn walk_token_mut<V: VisitMut + ?Sized>(visitor: &mut V, token: &mut Token) {
+    visitor.visit_span_mut(token.span_mut());
+    match token {
+        Token::Bold(tokens, span) => visitor.visit_bold_mut(tokens, span),
+        Token::Italic(tokens, span) => visitor.visit_italic_mut(tokens, span),
+        Token::InlineCode(tokens, span) => visitor.visit_inline_code_mut(tokens, span),
+        Token::Regular(text, span) => visitor.visit_regular_mut(text, span),
+        Token::Link { label, url, span } => visitor.visit_link_mut(label, url, span),
+        Token::Image { label, url, span } => visitor.visit_image_mut(label, url, span),
+        Token::SoftBreak(span) => visitor.visit_soft_break_mut(span),
+    }
+}
+
+struct ClearSpans;
+
+impl VisitMut for ClearSpans {
+    fn visit_span_mut(&mut self, span: &mut Span) {
+        *span = Span::default();
+    }
+}
+
+/// The text of inline content without its markup, with soft breaks as
+/// spaces.
+pub fn plain_text(tokens: &[Token]) -> String {
+    struct PlainText(String);
+
+    impl Visit for PlainText {
+        fn visit_regular(&mut self, text: &str, _: Span) {
+            self.0.push_str(text);
+        }
+
+        fn visit_soft_break(&mut self, _: Span) {
+            self.0.push(' ');
+        }
+    }
+
+    let mut text = PlainText(String::new());
+    text.visit_tokens(tokens);
+    text.0
+}
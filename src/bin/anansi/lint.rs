@@ -0,0 +1,158 @@
+//! `anansi lint`: problems in documents, one per line as
+//! `file:line:column: message`.
+
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+use md_parser::query::{query, Value};
+use md_parser::{Corpus, HeaderLevel, ParseOptions, Parsed, Span, Token, Visit};
+
+use crate::{read, Error};
+
+/// Queries for nodes that are a problem, and what is wrong with them.
+const RULES: [(&str, &str); 3] = [
+    (
+        "SELECT line, column FROM link WHERE url = ''",
+        "link has no URL",
+    ),
+    (
+        "SELECT line, column FROM link WHERE text = ''",
+        "link has no text",
+    ),
+    (
+        "SELECT line, column FROM image WHERE text = ''",
+        "image has no alt text",
+    ),
+];
+
+/// Lints the files `patterns` match, failing if any has a problem.
+pub fn run(patterns: &[String]) -> Result<ExitCode, Error> {
+    let mut out = io::stdout().lock();
+    let mut problems = 0;
+    for pattern in patterns {
+        let paths = if pattern == "-" {
+            vec![pattern.clone()]
+        } else {
+            Corpus::paths(pattern)
+                .map_err(Error::failed)?
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect()
+        };
+        if paths.is_empty() {
+            return Err(Error::no_match(pattern));
+        }
+        for path in paths {
+            let (name, text) = read(&path)?;
+            for problem in lint(&text) {
+                writeln!(
+                    out,
+                    "{}:{}:{}: {}",
+                    name, problem.line, problem.column, problem.message
+                )
+                .map_err(Error::output)?;
+                problems += 1;
+            }
+        }
+    }
+    Ok(if problems == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct Problem {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+/// The problems in a document, in the order they appear.
+fn lint(text: &str) -> Vec<Problem> {
+    let Parsed { document, errors } = md_parser::parse_with_options(text, &ParseOptions::default())
+        .expect("literal recovery never fails a parse");
+    let mut problems: Vec<Problem> = errors
+        .iter()
+        .map(|error| Problem {
+            line: error.span.line,
+            column: error.span.column,
+            message: format!(
+                "markup kept as text: unexpected {:?} in {:?} state",
+                error.event, error.state
+            ),
+        })
+        .collect();
+
+    for (rule, message) in RULES {
+        let result = query(&document, rule).expect("lint rules are valid queries");
+        for row in &result.rows {
+            problems.push(Problem {
+                line: number(&row[0]),
+                column: number(&row[1]),
+                message: message.to_string(),
+            });
+        }
+    }
+
+    let mut headings = HeadingJumps {
+        previous: None,
+        problems: &mut problems,
+    };
+    headings.visit_lines(&document);
+
+    problems.sort_by_key(|problem| (problem.line, problem.column));
+    problems
+}
+
+fn number(value: &Value) -> usize {
+    match value {
+        Value::Number(n) => *n as usize,
+        _ => 0,
+    }
+}
+
+/// Reports headings more than one level below the heading before them.
+struct HeadingJumps<'a> {
+    previous: Option<u8>,
+    problems: &'a mut Vec<Problem>,
+}
+
+impl Visit for HeadingJumps<'_> {
+    fn visit_header(&mut self, level: &HeaderLevel, _: &[Token], span: Span) {
+        let level = level.number();
+        if let Some(previous) = self.previous.filter(|&previous| level > previous + 1) {
+            self.problems.push(Problem {
+                line: span.line,
+                column: span.column,
+                message: format!("heading jumps from h{} to h{}", previous, level),
+            });
+        }
+        self.previous = Some(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lint;
+
+    #[test]
+    fn problems() {
+        let text = "# Title\n\n### Details\n\nSee [](https://a.com) and [docs]().\n\n![](x.png)\n";
+        let problems: Vec<String> = lint(text)
+            .iter()
+            .map(|p| format!("{}:{}: {}", p.line, p.column, p.message))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                "3:1: heading jumps from h1 to h3",
+                "5:5: link has no text",
+                "5:27: link has no URL",
+                "7:1: image has no alt text",
+            ]
+        );
+        assert!(lint("# Title\n\n## Section\n\nSee [docs](https://docs.rs).\n").is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+//! `anansi`, a command line tool to parse, query and check markdown.
+
+mod lint;
+mod repl;
+mod toc;
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use md_parser::html;
+use md_parser::query::{Format, Query};
+use md_parser::{Corpus, Document, ParseOptions, Recovery};
+
+const USAGE: &str = "\
+usage: anansi <command> [options]
+
+commands:
+  parse [--strict] [FILE]                     print the syntax tree of a document
+  render [--to html] [--unsafe-urls] [FILE]   render a document
+  query [--format FORMAT] QUERY [FILE...]     run a query or a selector over documents
+  lint [FILE...]                              report problems in documents
+  fmt [FILE]                                  print a document as canonical markdown
+  toc [FILE]                                  print the table of contents of a document
+  repl [FILE...]                              load documents and prompt for queries
+  help                                        show this message
+
+A FILE of `-`, or none, reads standard input. The files of `query`, `lint`
+and `repl` may be glob patterns such as `docs/**/*.md`. A query without
+files reads the files of its `IN` pattern, or else standard input.
+`render` leaves out the URLs of links and images that could run script,
+unless given `--unsafe-urls`.
+FORMAT is json, ndjson, csv or table.";
+
+/// Why a command failed.
+pub enum Error {
+    /// The command line is wrong: the message is shown with the usage and
+    /// the exit code is 2.
+    Usage(String),
+    /// The command could not do its work: the exit code is 1.
+    Failed(String),
+    /// Whatever reads the output closed it, as `head` does: the command
+    /// stops quietly and the exit code is 0.
+    Closed,
+}
+
+impl Error {
+    fn failed(error: impl std::fmt::Display) -> Self {
+        Error::Failed(error.to_string())
+    }
+
+    /// Fails with an error writing the output, unless it is that the pipe
+    /// it goes to was closed.
+    pub fn output(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::BrokenPipe {
+            Error::Closed
+        } else {
+            Error::failed(error)
+        }
+    }
+
+    /// Fails for a glob pattern that matches no files.
+    pub fn no_match(pattern: &str) -> Self {
+        Error::Failed(format!("no files match `{}`", pattern))
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(code) => code,
+        Err(Error::Usage(message)) => {
+            eprintln!("anansi: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Error::Failed(message)) => {
+            eprintln!("anansi: {}", message);
+            ExitCode::FAILURE
+        }
+        Err(Error::Closed) => ExitCode::SUCCESS,
+    }
+}
+
+fn run(args: &[String]) -> Result<ExitCode, Error> {
+    let Some((command, args)) = args.split_first() else {
+        return Err(Error::Usage("no command given".to_string()));
+    };
+    match command.as_str() {
+        "parse" => parse(Args::parse(args, &[], &["--strict"])?),
+        "render" => render(Args::parse(args, &["--to"], &["--unsafe-urls"])?),
+        "query" => query(Args::parse(args, &["--format"], &[])?),
+        "lint" => lint::run(&Args::parse(args, &[], &[])?.files()),
+        "fmt" => fmt(Args::parse(args, &[], &[])?),
+        "toc" => toc::run(Args::parse(args, &[], &[])?.file()?),
+        "repl" => {
+            let args = Args::parse(args, &[], &[])?;
+            repl::run(&args.positional).map_err(Error::Failed)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        "help" | "--help" | "-h" => {
+            writeln!(io::stdout().lock(), "{}", USAGE).map_err(Error::output)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        command => Err(Error::Usage(format!("unknown command `{}`", command))),
+    }
+}
+
+fn parse(args: Args) -> Result<ExitCode, Error> {
+    let recovery = if args.flag("--strict") {
+        Recovery::Strict
+    } else {
+        Recovery::Literal
+    };
+    let (name, text) = read(args.file()?)?;
+    let parsed = md_parser::parse_with_options(&text, &ParseOptions { recovery })
+        .map_err(|error| Error::Failed(format!("{}: {}", name, error)))?;
+    writeln!(io::stdout().lock(), "{:#?}", parsed.document).map_err(Error::output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn render(args: Args) -> Result<ExitCode, Error> {
+    let to = args.value("--to").unwrap_or("html");
+    if to != "html" {
+        return Err(Error::Usage(format!(
+            "cannot render to `{}`, only to `html`",
+            to
+        )));
+    }
+    let (_, document) = load(args.file()?)?;
+    let options = html::Options {
+        heading_ids: true,
+        safe_urls: !args.flag("--unsafe-urls"),
+        ..Default::default()
+    };
+    html::write(&document, &options, &mut io::stdout().lock()).map_err(Error::output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn query(args: Args) -> Result<ExitCode, Error> {
+    let format = match args.value("--format") {
+        Some(format) => format.parse::<Format>().map_err(Error::Usage)?,
+        None => Format::default(),
+    };
+    let Some((text, files)) = args.positional.split_first() else {
+        return Err(Error::Usage("no query given".to_string()));
+    };
+
+    let query = Query::parse_any(text).map_err(|error| Error::Failed(error.render(text)))?;
+    let result = if files.is_empty() && query.source.is_none() {
+        let (_, content) = read("-")?;
+        query.execute(&md_parser::parse(&content))
+    } else {
+        let patterns = if files.is_empty() {
+            query.source.iter().cloned().collect()
+        } else {
+            files.to_vec()
+        };
+        let mut corpus = Corpus::new();
+        for pattern in &patterns {
+            if pattern == "-" {
+                let (name, content) = read(pattern)?;
+                corpus.add(name, md_parser::parse(&content));
+            } else if corpus.load_more(pattern).map_err(Error::failed)? == 0 {
+                return Err(Error::no_match(pattern));
+            }
+        }
+        query.execute_corpus(&corpus)
+    };
+
+    result
+        .write(format, &mut io::stdout().lock())
+        .map_err(Error::output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn fmt(args: Args) -> Result<ExitCode, Error> {
+    let (_, document) = load(args.file()?)?;
+    md_parser::markdown::write(&document, &mut io::stdout().lock()).map_err(Error::output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reads a file, or standard input for `-`, returning the name to report
+/// it by and its text.
+pub fn read(path: &str) -> Result<(String, String), Error> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|error| Error::Failed(format!("cannot read standard input: {}", error)))?;
+        return Ok(("<stdin>".to_string(), text));
+    }
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| Error::Failed(format!("cannot read {}: {}", path, error)))?;
+    Ok((path.to_string(), text))
+}
+
+/// Reads and parses a file, or standard input for `-`.
+pub fn load(path: &str) -> Result<(String, Document), Error> {
+    let (name, text) = read(path)?;
+    Ok((name, md_parser::parse(&text)))
+}
+
+/// The arguments of a command: its options, and the rest in order.
+struct Args {
+    options: Vec<(String, Option<String>)>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    /// Splits `args` into the options in `valued`, which take a value as
+    /// in `--to html` or `--to=html`, the options in `flags`, which do not,
+    /// and the rest. A `--` ends the options.
+    fn parse(args: &[String], valued: &[&str], flags: &[&str]) -> Result<Self, Error> {
+        let mut options = Vec::new();
+        let mut positional = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                positional.extend(args.by_ref().cloned());
+            } else if arg.starts_with('-') && arg != "-" {
+                let (name, value) = match arg.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (arg.as_str(), None),
+                };
+                if valued.contains(&name) {
+                    let value = match value {
+                        Some(value) => value,
+                        None => args
+                            .next()
+                            .cloned()
+                            .ok_or_else(|| Error::Usage(format!("`{}` needs a value", name)))?,
+                    };
+                    options.push((name.to_string(), Some(value)));
+                } else if flags.contains(&name) && value.is_none() {
+                    options.push((name.to_string(), None));
+                } else {
+                    return Err(Error::Usage(format!("unknown option `{}`", arg)));
+                }
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Args {
+            options,
+            positional,
+        })
+    }
+
+    /// The value of the last `name` option.
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    /// The one file the command reads, `-` if none is given.
+    fn file(&self) -> Result<&str, Error> {
+        match self.positional.as_slice() {
+            [] => Ok("-"),
+            [file] => Ok(file),
+            [_, extra, ..] => Err(Error::Usage(format!("unexpected argument `{}`", extra))),
+        }
+    }
+
+    /// The files the command reads, `-` alone if none are given.
+    fn files(&self) -> Vec<String> {
+        if self.positional.is_empty() {
+            vec!["-".to_string()]
+        } else {
+            self.positional.clone()
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! `anansi repl`: loads documents once, then runs queries over them as they
+//! are typed.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use md_parser::query::{Format, Kind, Query, COMMON_FIELDS};
+use md_parser::Corpus;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+const HELP: &str = "\
+Type a query or a selector to run it over the loaded documents. A query
+that stops short continues on the next line, until it is complete, a line
+ends with `;` or a blank line is entered.
+
+.schema              list the node kinds and their fields
+.format [FORMAT]     show or set the output format: json, ndjson, csv or table
+.load PATTERN...     load more files, which may be glob patterns
+.files               list the loaded files
+.help                show this message
+.quit                leave (or press Ctrl-D)";
+
+/// Runs the prompt over the files `patterns` match until the user leaves.
+pub fn run(patterns: &[String]) -> Result<(), String> {
+    let mut session = Session::new();
+    for pattern in patterns {
+        session
+            .corpus
+            .load_more(pattern)
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut editor = DefaultEditor::new().map_err(|e| e.to_string())?;
+    let history = history_file();
+    if let Some(history) = &history {
+        // There is no history the first time.
+        let _ = editor.load_history(history);
+    }
+
+    println!(
+        "{} document{} loaded, type .help for help",
+        session.corpus.len(),
+        if session.corpus.len() == 1 { "" } else { "s" }
+    );
+    let mut stdout = io::stdout();
+    loop {
+        let prompt = if session.is_pending() {
+            "   ...> "
+        } else {
+            "anansi> "
+        };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                let status = session
+                    .input(&line, &mut stdout)
+                    .map_err(|e| e.to_string())?;
+                if let Status::Ran(entry) = &status {
+                    let _ = editor.add_history_entry(entry.as_str());
+                }
+                if status == Status::Quit {
+                    break;
+                }
+            }
+            // Ctrl-C drops the query being typed, as in a shell.
+            Err(ReadlineError::Interrupted) => session.pending.clear(),
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error.to_string()),
+        }
+    }
+
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    Ok(())
+}
+
+fn history_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".anansi_history"))
+}
+
+/// What became of a line of input.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The line was added to a query that is not complete yet.
+    Pending,
+    /// The input up to this line, which may span several lines, was run.
+    Ran(String),
+    /// The user asked to leave.
+    Quit,
+}
+
+/// The documents, settings and unfinished input of a prompt, apart from
+/// the terminal it reads from.
+#[derive(Debug, Default)]
+pub struct Session {
+    corpus: Corpus,
+    format: Format,
+    pending: String,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Takes one line of input, writing whatever it prints to `out`.
+    pub fn input(&mut self, line: &str, out: &mut impl Write) -> io::Result<Status> {
+        if !self.is_pending() {
+            let line = line.trim();
+            if line.is_empty() {
+                return Ok(Status::Pending);
+            }
+            if line.starts_with('.') {
+                return self.command(line, out);
+            }
+        }
+
+        if !self.pending.is_empty() {
+            self.pending.push('\n');
+        }
+        self.pending.push_str(line);
+
+        let trimmed = self.pending.trim_end();
+        let (text, terminated) = match trimmed.strip_suffix(';') {
+            Some(text) => (text, true),
+            None => (trimmed, line.trim().is_empty()),
+        };
+        let parsed = Query::parse_any(text);
+        match parsed {
+            // The parser ran out of text: wait for the rest of the query.
+            Err(error) if !terminated && error.span.start >= text.trim_end().len() => {
+                return Ok(Status::Pending);
+            }
+            Err(error) => writeln!(out, "{}", error.render(text))?,
+            Ok(query) => {
+                let result = query.execute_corpus(&self.corpus);
+                result.write(self.format, out)?;
+            }
+        }
+        Ok(Status::Ran(std::mem::take(&mut self.pending)))
+    }
+
+    fn command(&mut self, line: &str, out: &mut impl Write) -> io::Result<Status> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        match command {
+            ".quit" | ".exit" => return Ok(Status::Quit),
+            ".help" => writeln!(out, "{}", HELP)?,
+            ".schema" => {
+                writeln!(out, "every node: {}", COMMON_FIELDS.join(", "))?;
+                for kind in Kind::ALL {
+                    match kind.fields() {
+                        [] => writeln!(out, "{}", kind.name())?,
+                        fields => writeln!(out, "{}: {}", kind.name(), fields.join(", "))?,
+                    }
+                }
+            }
+            ".format" => match words.next() {
+                None => writeln!(out, "{}", self.format.name())?,
+                Some(name) => match name.parse() {
+                    Ok(format) => self.format = format,
+                    Err(error) => writeln!(out, "{}", error)?,
+                },
+            },
+            ".load" => {
+                let mut patterns = words.peekable();
+                if patterns.peek().is_none() {
+                    writeln!(out, "usage: .load PATTERN...")?;
+                }
+                for pattern in patterns {
+                    match self.corpus.load_more(pattern) {
+                        Ok(loaded) => writeln!(
+                            out,
+                            "{} document{} loaded",
+                            loaded,
+                            if loaded == 1 { "" } else { "s" }
+                        )?,
+                        Err(error) => writeln!(out, "{}", error)?,
+                    }
+                }
+            }
+            ".files" => {
+                for (path, _) in self.corpus.iter() {
+                    writeln!(out, "{}", path)?;
+                }
+            }
+            _ => writeln!(out, "unknown command `{}`, type .help for help", command)?,
+        }
+        Ok(Status::Ran(line.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Session, Status};
+
+    fn input(session: &mut Session, line: &str) -> (Status, String) {
+        let mut out = Vec::new();
+        let status = session.input(line, &mut out).unwrap();
+        (status, String::from_utf8(out).unwrap())
+    }
+
+    fn session() -> Session {
+        let mut session = Session::new();
+        let document = md_parser::parse("# Title\n\nSee [docs](https://docs.rs).\n");
+        session.corpus.add("README.md", document);
+        session
+    }
+
+    #[test]
+    fn multi_line_queries() {
+        let mut session = session();
+        let rows = "file       line  column  url\n\
+                    ---------  ----  ------  ---------------\n\
+                    README.md     3       5  https://docs.rs\n";
+        assert_eq!(
+            input(&mut session, "SELECT url FROM link"),
+            (
+                Status::Ran("SELECT url FROM link".to_string()),
+                rows.to_string()
+            )
+        );
+
+        assert_eq!(
+            input(&mut session, "SELECT url FROM"),
+            (Status::Pending, String::new())
+        );
+        assert_eq!(
+            input(&mut session, "link WHERE"),
+            (Status::Pending, String::new())
+        );
+        let (status, out) = input(&mut session, "host(url) = 'docs.rs';");
+        assert_eq!(
+            status,
+            Status::Ran("SELECT url FROM\nlink WHERE\nhost(url) = 'docs.rs';".to_string())
+        );
+        assert!(out.contains("https://docs.rs"));
+
+        assert_eq!(
+            input(&mut session, "SELECT url FROM"),
+            (Status::Pending, String::new())
+        );
+        let (status, out) = input(&mut session, "");
+        assert!(matches!(status, Status::Ran(_)));
+        assert!(out.starts_with("expected a node kind"), "{}", out);
+    }
+
+    #[test]
+    fn commands() {
+        let mut session = session();
+        let (_, out) = input(&mut session, ".schema");
+        assert!(out.contains("\nlink: url, label\n"), "{}", out);
+
+        input(&mut session, ".format json");
+        let (_, out) = input(&mut session, "header");
+        assert!(out.starts_with('['), "{}", out);
+        let (_, out) = input(&mut session, ".format yaml");
+        assert!(out.contains("yaml"), "{}", out);
+        assert_eq!(input(&mut session, ".format").1, "json\n");
+
+        let (_, out) = input(&mut session, ".load no/such/file.md");
+        assert!(out.starts_with("cannot read no/such/file.md"), "{}", out);
+        assert_eq!(input(&mut session, ".files").1, "README.md\n");
+        assert_eq!(input(&mut session, ".quit").0, Status::Quit);
+    }
+}
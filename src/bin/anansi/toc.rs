@@ -0,0 +1,98 @@
+//! `anansi toc`: the headings of a document as a nested list of links to
+//! them.
+
+use std::fmt::Write;
+use std::io::{self, Write as _};
+use std::process::ExitCode;
+
+use md_parser::html::HeadingIds;
+use md_parser::{markdown, plain_text, Document, HeaderLevel, Span, Token, Visit};
+
+use crate::{load, Error};
+
+pub fn run(path: &str) -> Result<ExitCode, Error> {
+    let (_, document) = load(path)?;
+    io::stdout()
+        .lock()
+        .write_all(toc(&document).as_bytes())
+        .map_err(Error::output)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// One item per heading, indented by its level below the top one, linking
+/// to the id `anansi render` gives it, which is the one GitHub gives it.
+fn toc(document: &Document) -> String {
+    let mut headings = Headings(Vec::new());
+    headings.visit_lines(document);
+    let top = headings
+        .0
+        .iter()
+        .map(|(level, _)| *level)
+        .min()
+        .unwrap_or(1);
+
+    let mut ids = HeadingIds::new();
+    let mut toc = String::new();
+    for (level, tokens) in &headings.0 {
+        writeln!(
+            toc,
+            "{}- [{}](#{})",
+            "  ".repeat((level - top) as usize),
+            markdown::render_inline(&unlinked(tokens)),
+            ids.next(&plain_text(tokens))
+        )
+        .expect("writing to a String never fails");
+    }
+    toc
+}
+
+/// The level and text of every heading, in order.
+struct Headings(Vec<(u8, Vec<Token>)>);
+
+impl Visit for Headings {
+    fn visit_header(&mut self, level: &HeaderLevel, tokens: &[Token], _: Span) {
+        self.0.push((level.number(), tokens.to_vec()));
+    }
+}
+
+/// The tokens with the links in them replaced by their labels, as a link
+/// cannot be in the label of another.
+fn unlinked(tokens: &[Token]) -> Vec<Token> {
+    tokens
+        .iter()
+        .flat_map(|token| match token {
+            Token::Link { label, .. } => unlinked(label),
+            Token::Bold(tokens, span) => vec![Token::Bold(unlinked(tokens), *span)],
+            Token::Italic(tokens, span) => vec![Token::Italic(unlinked(tokens), *span)],
+            token => vec![token.clone()],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::toc;
+
+    #[test]
+    fn nested_headings() {
+        let document = md_parser::parse(
+            "## Getting *started*\n\n### Install\n\n## FAQ & Help\n\n### Install\n",
+        );
+        assert_eq!(
+            toc(&document),
+            "- [Getting *started*](#getting-started)\n\
+             \x20 - [Install](#install)\n\
+             - [FAQ & Help](#faq--help)\n\
+             \x20 - [Install](#install-1)\n"
+        );
+    }
+
+    #[test]
+    fn escape_titles() {
+        let document = md_parser::parse("# Use \\[x\\] *here* and [docs](https://a.com)\n");
+        assert_eq!(
+            toc(&document),
+            "- [Use \\[x\\] *here* and docs](#use-x-here-and-docs)\n"
+        );
+    }
+}
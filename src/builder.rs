@@ -1,13 +1,19 @@
-use crate::{Document, HeaderLevel, Line, Token};
+use crate::{Alignment, Document, HeaderLevel, Line, ListItem, Span, Token};
 
 #[derive(Debug, Default)]
 pub struct Builder {
     lines: Vec<Line>,
-    parsing: Vec<Parsing>,
-    bold_tokens: Vec<Token>,
-    italic_tokens: Vec<Token>,
-    inline_code_tokens: Vec<Token>,
+    parsing: Vec<(Parsing, Vec<Token>, Span)>,
     label_tokens: Vec<Token>,
+    label_span: Span,
+    list_indents: Vec<usize>,
+    item_depth: usize,
+    line_indent: usize,
+    quote_depth: usize,
+    line_quote_depth: usize,
+    line_quote_span: Span,
+    line_break: Span,
+    span: Span,
 }
 
 #[derive(Debug)]
@@ -15,6 +21,7 @@ enum Parsing {
     Bold,
     Italic,
     Label,
+    Image,
     InlineCode,
 }
 
@@ -27,164 +34,519 @@ impl Builder {
         self.lines.clone()
     }
 
+    pub fn into_document(self) -> Document {
+        self.lines
+    }
+
+    /// Sets the source span of the event being handled, which new nodes
+    /// start at and open nodes are extended to.
+    pub(crate) fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+
     pub(crate) fn add_header(&mut self) {
-        self.lines.push(Line::Header {
+        self.open_block(false);
+        let span = self.span;
+        self.push_line(Line::Header {
             level: HeaderLevel::H1,
             tokens: Vec::new(),
+            span,
         });
     }
 
     pub(crate) fn set_header_level(&mut self, l: HeaderLevel) {
-        if let Some(Line::Header { level, .. }) = self.lines.last_mut() {
+        if let Some(Line::Header { level, .. }) = self.container().last_mut() {
             *level = l;
         }
     }
 
+    /// Starts a paragraph, or continues the paragraph or list item on the
+    /// previous line after a soft break.
     pub(crate) fn add_text(&mut self) {
-        self.lines.push(Line::Paragraph(Vec::new()));
+        if !self.open_block(true) {
+            let span = self.span;
+            self.push_line(Line::Paragraph(Vec::new(), span));
+            return;
+        }
+
+        let soft_break = Token::SoftBreak(self.line_break);
+        match self.container().last_mut() {
+            Some(Line::Paragraph(tokens, _)) => tokens.push(soft_break),
+            Some(Line::List { items, .. }) => {
+                if let Some(item) = last_item(items) {
+                    item.tokens.push(soft_break);
+                }
+            }
+            _ => {}
+        }
     }
 
     pub(crate) fn add_image(&mut self) {
-        self.lines.push(Line::Image {
+        self.open_block(false);
+        let span = self.span;
+        self.push_line(Line::Image {
             label: Vec::new(),
             url: String::new(),
+            span,
         })
     }
 
-    pub(crate) fn blank_line(&mut self) {
-        self.lines.push(Line::Blank);
+    pub(crate) fn add_code_block(&mut self) {
+        self.open_block(false);
+        let span = self.span;
+        self.push_line(Line::CodeBlock {
+            info: String::new(),
+            content: String::new(),
+            span,
+        })
     }
 
-    pub(crate) fn start_bold(&mut self) {
-        self.parsing.push(Parsing::Bold);
+    pub(crate) fn set_code_block_info(&mut self, i: &str) {
+        if let Some(Line::CodeBlock { info, .. }) = self.container().last_mut() {
+            *info = i.to_string();
+        }
     }
 
-    pub(crate) fn end_bold(&mut self) {
-        let tokens: Vec<Token> = self.bold_tokens.drain(..).collect();
+    pub(crate) fn add_code_line(&mut self, line: &str) {
+        if let Some(Line::CodeBlock { content, .. }) = self.container().last_mut() {
+            content.push_str(line);
+            content.push('\n');
+        }
+        self.extend(self.span);
+    }
+
+    pub(crate) fn end_code_block(&mut self) {
+        self.extend(self.span);
+        self.line_indent = 0;
+        self.line_quote_depth = 0;
+    }
 
-        let wrap_with_bold = |tokens: &mut Vec<Token>| {
-            if let Some(t) = tokens.last_mut() {
-                if let Token::Italic(_) = t {
-                    *t = Token::Bold(vec![t.clone()]);
+    /// Adds a list item, nesting it under the previous item when it is
+    /// indented further than the list that item belongs to. Blank lines
+    /// between items leave the list open.
+    pub(crate) fn add_list_item(&mut self, ordered: bool, number: u64, indent: usize) {
+        self.open_block(false);
+        let blanks = self.take_blanks_after_list();
+        let blanks_at = self.container().len();
+        if !matches!(self.container().last(), Some(Line::List { .. })) {
+            self.list_indents.clear();
+        }
+
+        while self.list_indents.len() > 1 && self.list_indents.last() > Some(&indent) {
+            self.list_indents.pop();
+        }
+
+        let depth = self.list_indents.len();
+        let span = self.span;
+        let new_item = || ListItem {
+            span,
+            ..ListItem::default()
+        };
+        let new_list = Line::List {
+            ordered,
+            start: number,
+            loose: false,
+            items: vec![new_item()],
+            span,
+        };
+
+        match self.list_indents.last() {
+            Some(top) if *top >= indent => {
+                let Some(container) = container_at(
+                    quote_container(&mut self.lines, self.quote_depth),
+                    depth - 1,
+                ) else {
+                    return;
+                };
+
+                let same_kind = matches!(
+                    container.last(),
+                    Some(Line::List { ordered: o, .. }) if *o == ordered
+                );
+
+                // Blank lines between the items of a list make it loose.
+                match container.last_mut() {
+                    Some(Line::List { loose, items, .. }) if same_kind => {
+                        *loose |= !blanks.is_empty();
+                        items.push(new_item());
+                    }
+                    _ => container.push(new_list),
                 }
             }
-        };
+            _ => {
+                if let Some(container) =
+                    container_at(quote_container(&mut self.lines, self.quote_depth), depth)
+                {
+                    container.push(new_list);
+                    self.list_indents.push(indent);
+                }
+            }
+        }
 
-        if tokens.is_empty() {
-            match self.lines.last_mut() {
-                Some(Line::Header { tokens, .. }) => wrap_with_bold(tokens),
-                Some(Line::Paragraph(tokens, ..)) => wrap_with_bold(tokens),
-                Some(Line::Image { label, .. }) => wrap_with_bold(label),
-                Some(Line::Blank) => {}
-                None => {}
-            };
-        } else {
-            let bold = Token::Bold(tokens);
-            match self.lines.last_mut() {
-                Some(Line::Header { tokens, .. }) => tokens.push(bold),
-                Some(Line::Paragraph(tokens, ..)) => tokens.push(bold),
-                Some(Line::Image { label, .. }) => label.push(bold),
-                Some(Line::Blank) => {}
-                None => {}
-            };
+        // A new list after the blank lines is a block of its own.
+        if self.container().len() > blanks_at {
+            self.container().splice(blanks_at..blanks_at, blanks);
+        }
+        self.extend(span);
+    }
+
+    pub(crate) fn add_table(&mut self) {
+        self.open_block(false);
+        let span = self.span;
+        self.push_line(Line::Table {
+            alignments: Vec::new(),
+            header: Vec::new(),
+            rows: Vec::new(),
+            span,
+        })
+    }
+
+    pub(crate) fn set_table_alignments(&mut self, a: &[Alignment]) {
+        if let Some(Line::Table { alignments, .. }) = self.container().last_mut() {
+            *alignments = a.to_vec();
+        }
+    }
+
+    pub(crate) fn add_table_row(&mut self) {
+        if let Some(Line::Table { rows, .. }) = self.container().last_mut() {
+            rows.push(Vec::new());
         }
+        self.extend(self.span);
+    }
 
-        self.parsing.pop();
+    pub(crate) fn add_table_cell(&mut self) {
+        if let Some(Line::Table { header, rows, .. }) = self.container().last_mut() {
+            rows.last_mut().unwrap_or(header).push(Vec::new());
+        }
+    }
+
+    pub(crate) fn blank_line(&mut self) {
+        self.open_block(false);
+        let span = self.span;
+        self.push_line(Line::Blank(span));
+        self.line_break = span;
+        self.line_quote_depth = 0;
+        self.line_indent = 0;
+    }
+
+    /// Sets how far the current line is indented, which nests the block it
+    /// starts in the list items it lines up with.
+    pub(crate) fn set_line_indent(&mut self, indent: usize) {
+        self.line_indent = indent;
+    }
+
+    pub(crate) fn set_quote_depth(&mut self, depth: usize) {
+        self.line_quote_depth = depth;
+        self.line_quote_span = self.span;
+    }
+
+    pub(crate) fn start_bold(&mut self) {
+        self.parsing.push((Parsing::Bold, Vec::new(), self.span));
+    }
+
+    pub(crate) fn end_bold(&mut self) {
+        if let Some((Parsing::Bold, tokens, span)) = self.parsing.pop() {
+            self.add_token(Token::Bold(tokens, span));
+        }
     }
 
     pub(crate) fn start_italic(&mut self) {
-        self.parsing.push(Parsing::Italic);
+        self.parsing.push((Parsing::Italic, Vec::new(), self.span));
     }
 
     pub(crate) fn end_italic(&mut self) {
-        let italic = Token::Italic(self.italic_tokens.drain(..).collect());
-        match self.lines.last_mut() {
-            Some(Line::Header { tokens, .. }) => tokens.push(italic),
-            Some(Line::Paragraph(tokens, ..)) => tokens.push(italic),
-            Some(Line::Image { label, .. }) => label.push(italic),
-            Some(Line::Blank) => {}
-            None => {}
-        };
-
-        self.parsing.pop();
+        if let Some((Parsing::Italic, tokens, span)) = self.parsing.pop() {
+            self.add_token(Token::Italic(tokens, span));
+        }
     }
 
     pub(crate) fn start_inline_code(&mut self) {
-        self.parsing.push(Parsing::InlineCode);
+        self.parsing
+            .push((Parsing::InlineCode, Vec::new(), self.span));
     }
 
     pub(crate) fn end_inline_code(&mut self) {
-        let inline_code = Token::InlineCode(self.inline_code_tokens.drain(..).collect());
-        match self.lines.last_mut() {
-            Some(Line::Header { tokens, .. }) => tokens.push(inline_code),
-            Some(Line::Paragraph(tokens, ..)) => tokens.push(inline_code),
-            Some(Line::Image { label, .. }) => label.push(inline_code),
-            Some(Line::Blank) => {}
-            None => {}
-        };
-
-        self.parsing.pop();
+        if let Some((Parsing::InlineCode, tokens, span)) = self.parsing.pop() {
+            self.add_token(Token::InlineCode(tokens, span));
+        }
     }
 
     pub(crate) fn start_label(&mut self) {
-        self.parsing.push(Parsing::Label);
+        self.parsing.push((Parsing::Label, Vec::new(), self.span));
     }
 
+    /// Starts an inline image, whose label and url follow.
+    pub(crate) fn start_image(&mut self) {
+        self.parsing.push((Parsing::Image, Vec::new(), self.span));
+    }
+
+    /// Ends a label, which becomes the alt text of an image or waits for
+    /// its url to become a link.
     pub(crate) fn end_label(&mut self) {
-        self.parsing.pop();
-        if let Some(Line::Image { label: tokens, .. }) = self.lines.last_mut() {
-            tokens.append(&mut self.label_tokens.drain(..).collect::<Vec<_>>());
+        let Some((Parsing::Label, mut tokens, span)) = self.parsing.pop() else {
+            return;
+        };
+
+        if let Some((Parsing::Image, label, _)) = self.parsing.last_mut() {
+            label.append(&mut tokens);
+            return;
         }
+
+        if self.parsing.is_empty() {
+            if let Some(Line::Image { label, .. }) = self.container().last_mut() {
+                label.append(&mut tokens);
+                return;
+            }
+        }
+
+        self.label_tokens = tokens;
+        self.label_span = span;
     }
 
     pub(crate) fn add_url(&mut self, u: &str) {
-        let label = self.label_tokens.drain(..).collect();
-        let link = Token::Link {
-            label,
-            url: u.to_string(),
-        };
+        if let Some((Parsing::Image, ..)) = self.parsing.last() {
+            if let Some((_, label, span)) = self.parsing.pop() {
+                self.add_token(Token::Image {
+                    label,
+                    url: u.to_string(),
+                    span,
+                });
+            }
+            return;
+        }
 
-        if let Some(parse_type) = self.parsing.last() {
-            match parse_type {
-                Parsing::Bold => self.bold_tokens.push(link),
-                Parsing::Italic => self.italic_tokens.push(link),
-                _ => {}
-            };
-        } else {
-            match self.lines.last_mut() {
-                Some(Line::Header { tokens, .. }) => tokens.push(link),
-                Some(Line::Paragraph(tokens, ..)) => tokens.push(link),
-                Some(Line::Image { url, .. }) => {
-                    *url = u.to_string();
-                }
-                Some(Line::Blank) => {}
-                None => {}
-            };
+        if self.parsing.is_empty() {
+            if let Some(Line::Image { url, .. }) = self.container().last_mut() {
+                *url = u.to_string();
+                return;
+            }
         }
+
+        let label = std::mem::take(&mut self.label_tokens);
+        self.add_token(Token::Link {
+            label,
+            url: u.to_string(),
+            span: self.label_span,
+        });
     }
 
     pub(crate) fn add_word(&mut self, word: &str) {
-        match self.parsing.last() {
-            Some(Parsing::Bold) => self.bold_tokens.push(Token::Regular(word.to_string())),
-            Some(Parsing::Italic) => self.italic_tokens.push(Token::Regular(word.to_string())),
-            Some(Parsing::Label) => self.label_tokens.push(Token::Regular(word.to_string())),
-            Some(Parsing::InlineCode) => self
-                .inline_code_tokens
-                .push(Token::Regular(word.to_string())),
-            None => match self.lines.last_mut() {
-                Some(Line::Header { tokens, .. }) => tokens.push(Token::Regular(word.to_string())),
-                Some(Line::Paragraph(tokens, ..)) => tokens.push(Token::Regular(word.to_string())),
-                Some(Line::Image { .. }) => {}
-                Some(Line::Blank) => {}
-                None => {}
-            },
+        if self.parsing.is_empty() && matches!(self.container().last(), Some(Line::Image { .. })) {
+            return;
         }
+
+        self.add_token(Token::Regular(word.to_string(), self.span));
     }
 
     pub(crate) fn end_line(&mut self) {
         self.parsing.clear();
-        self.bold_tokens.clear();
-        self.italic_tokens.clear();
         self.label_tokens.clear();
+        self.line_break = self.span;
+        self.line_quote_depth = 0;
+        self.line_indent = 0;
+    }
+
+    /// Adds a finished inline token to the innermost open span, or to the
+    /// current line when no span is open.
+    fn add_token(&mut self, token: Token) {
+        if let Some((_, tokens, _)) = self.parsing.last_mut() {
+            tokens.push(token);
+        } else if let Some(tokens) = self.tokens_mut() {
+            let span = token.span();
+            tokens.push(token);
+            self.extend(span);
+        }
+    }
+
+    /// Adds a new block to the current container, extending the blocks it
+    /// is nested in to cover it.
+    fn push_line(&mut self, line: Line) {
+        let span = line.span();
+        self.container().push(line);
+        self.extend(span);
+    }
+
+    /// Extends the last block, and every block it is nested in, to the end
+    /// of `span`.
+    fn extend(&mut self, span: Span) {
+        extend_last(&mut self.lines, span);
+    }
+
+    /// Closes or opens blockquotes so that the `>` markers of the current
+    /// line match the quotes new blocks are added to, and finds the list
+    /// items its indentation nests it in. A lazy paragraph line stays inside
+    /// the quote and item it continues, and returns true.
+    fn open_block(&mut self, lazy: bool) -> bool {
+        let continues = lazy
+            && match self.container().last() {
+                Some(Line::Paragraph(..)) => self.line_quote_depth <= self.quote_depth,
+                Some(Line::List { .. }) => self.line_quote_depth == self.quote_depth,
+                _ => false,
+            };
+        if continues {
+            return true;
+        }
+
+        self.item_depth = 0;
+        self.quote_depth = self.quote_depth.min(self.line_quote_depth);
+        while self.quote_depth < self.line_quote_depth {
+            let span = self.line_quote_span;
+            self.container().push(Line::Blockquote(Vec::new(), span));
+            self.quote_depth += 1;
+        }
+
+        if self.line_indent > 0 {
+            self.item_depth = self.continued_items();
+            if self.item_depth > 0 {
+                self.take_blanks_after_list();
+            }
+        }
+        false
+    }
+
+    /// How many lists deep a block indented by `line_indent` is nested: it
+    /// continues the last item of every list whose marker is indented less,
+    /// after any blank lines.
+    fn continued_items(&mut self) -> usize {
+        let mut lines = quote_container(&mut self.lines, self.quote_depth).as_slice();
+        while let Some(Line::Blank(_)) = lines.last() {
+            lines = &lines[..lines.len() - 1];
+        }
+
+        let mut depth = 0;
+        for indent in &self.list_indents {
+            match lines.last() {
+                Some(Line::List { items, .. }) if *indent < self.line_indent => {
+                    match items.last() {
+                        Some(item) => lines = &item.children,
+                        None => break,
+                    }
+                    depth += 1;
+                }
+                _ => break,
+            }
+        }
+        depth
+    }
+
+    /// Removes the blank lines after a list that the next block continues.
+    fn take_blanks_after_list(&mut self) -> Vec<Line> {
+        let lines = self.container();
+        let blanks = lines
+            .iter()
+            .rev()
+            .take_while(|line| matches!(line, Line::Blank(_)))
+            .count();
+        let start = lines.len() - blanks;
+        if blanks > 0 && matches!(lines[..start].last(), Some(Line::List { .. })) {
+            lines.split_off(start)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// The lines new blocks are added to: those of the innermost open
+    /// blockquote, or the document itself, or of the list item they
+    /// continue.
+    fn container(&mut self) -> &mut Vec<Line> {
+        item_container(
+            quote_container(&mut self.lines, self.quote_depth),
+            self.item_depth,
+        )
+    }
+
+    /// The inline tokens that new content on the current line is added to.
+    fn tokens_mut(&mut self) -> Option<&mut Vec<Token>> {
+        match self.container().last_mut()? {
+            Line::Header { tokens, .. } => Some(tokens),
+            Line::Paragraph(tokens, _) => Some(tokens),
+            Line::Image { label, .. } => Some(label),
+            Line::List { items, .. } => last_item(items).map(|item| &mut item.tokens),
+            Line::CodeBlock { .. } => None,
+            Line::Table { header, rows, .. } => rows.last_mut().unwrap_or(header).last_mut(),
+            Line::Blockquote(..) => None,
+            Line::Blank(_) => None,
+        }
+    }
+}
+
+/// The lines `depth` blockquotes deep, following the last line of each quote.
+fn quote_container(lines: &mut Vec<Line>, depth: usize) -> &mut Vec<Line> {
+    if depth == 0 {
+        return lines;
+    }
+
+    if matches!(lines.last(), Some(Line::Blockquote(..))) {
+        match lines.last_mut() {
+            Some(Line::Blockquote(children, _)) => quote_container(children, depth - 1),
+            _ => unreachable!(),
+        }
+    } else {
+        lines
+    }
+}
+
+/// The children of the last item of the list `depth` lists deep at the
+/// end of `lines`, or `lines` itself.
+fn item_container(lines: &mut Vec<Line>, depth: usize) -> &mut Vec<Line> {
+    if depth == 0 || !matches!(lines.last(), Some(Line::List { items, .. }) if !items.is_empty()) {
+        return lines;
+    }
+
+    match lines.last_mut() {
+        Some(Line::List { items, .. }) => match items.last_mut() {
+            Some(item) => item_container(&mut item.children, depth - 1),
+            None => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// The innermost item at the end of a (possibly nested) list.
+fn last_item(items: &mut [ListItem]) -> Option<&mut ListItem> {
+    let item = items.last_mut()?;
+
+    if matches!(item.children.last(), Some(Line::List { .. })) {
+        match item.children.last_mut() {
+            Some(Line::List { items, .. }) => last_item(items),
+            _ => None,
+        }
+    } else {
+        Some(item)
+    }
+}
+
+/// The lines that a list nested `depth` levels deep is stored in, following
+/// the last item of each enclosing list.
+fn container_at(lines: &mut Vec<Line>, depth: usize) -> Option<&mut Vec<Line>> {
+    if depth == 0 {
+        return Some(lines);
+    }
+
+    match lines.last_mut() {
+        Some(Line::List { items, .. }) => container_at(&mut items.last_mut()?.children, depth - 1),
+        _ => None,
+    }
+}
+
+/// Extends the last line to the end of `span`, following the last child of
+/// blockquotes and the last item of lists down to the innermost block.
+fn extend_last(lines: &mut [Line], span: Span) {
+    let Some(line) = lines.last_mut() else {
+        return;
+    };
+
+    let line_span = line.span_mut();
+    *line_span = line_span.to(span);
+
+    match line {
+        Line::Blockquote(children, _) => extend_last(children, span),
+        Line::List { items, .. } => {
+            if let Some(item) = items.last_mut() {
+                item.span = item.span.to(span);
+                extend_last(&mut item.children, span);
+            }
+        }
+        _ => {}
     }
 }
@@ -1,66 +1,99 @@
+use crate::{Alignment, Span};
+
 pub trait TokenCollector {
-    fn h1(&mut self);
-    fn h2(&mut self);
-    fn h3(&mut self);
-    fn h4(&mut self);
-    fn h5(&mut self);
-    fn h6(&mut self);
-
-    fn begin_bold(&mut self);
+    fn h1(&mut self, span: Span);
+    fn h2(&mut self, span: Span);
+    fn h3(&mut self, span: Span);
+    fn h4(&mut self, span: Span);
+    fn h5(&mut self, span: Span);
+    fn h6(&mut self, span: Span);
+
+    fn begin_bold(&mut self, span: Span);
     fn end_bold(&mut self);
 
-    fn begin_italic(&mut self);
+    fn begin_italic(&mut self, span: Span);
     fn end_italic(&mut self);
 
-    fn begin_inline_code(&mut self);
+    fn begin_inline_code(&mut self, span: Span);
     fn end_inline_code(&mut self);
 
-    fn begin_label(&mut self);
+    fn begin_label(&mut self, span: Span);
     fn end_label(&mut self);
 
     fn url(&mut self, url: &str);
-    fn word(&mut self, text: &str);
+    fn word(&mut self, text: &str, span: Span);
+
+    /// An image on a line of its own, followed by its label and url.
+    fn image(&mut self, span: Span);
+    /// An image among other inline content, followed by its label and url.
+    fn inline_image(&mut self, span: Span);
+
+    /// A list item, with `marker` its text up to its content, as in `3)  `.
+    fn unordered_item(&mut self, marker: &str, indent: usize, span: Span);
+    fn ordered_item(&mut self, number: u64, marker: &str, indent: usize, span: Span);
+
+    fn blockquote(&mut self, depth: usize, span: Span);
 
-    fn image(&mut self);
+    /// How far the block that starts the current line is indented, when it
+    /// is and is not a list item.
+    fn line_indent(&mut self, indent: usize);
 
-    fn line_break(&mut self);
+    fn begin_table(&mut self, span: Span);
+    fn table_alignments(&mut self, alignments: &[Alignment]);
+    fn table_row(&mut self, span: Span);
+    fn table_cell(&mut self);
+
+    fn begin_code_block(&mut self, info: &str, span: Span);
+    fn code_line(&mut self, line: &str, span: Span);
+    fn end_code_block(&mut self, span: Span);
+
+    fn line_break(&mut self, span: Span);
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::TokenCollector;
+    use crate::{Alignment, Span};
 
     #[derive(Debug, Default)]
     pub struct MockTokenCollector {
         pub tokens: Vec<String>,
+        pub spans: Vec<Span>,
     }
 
     impl TokenCollector for MockTokenCollector {
-        fn h1(&mut self) {
+        fn h1(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("h1".to_string());
         }
 
-        fn h2(&mut self) {
+        fn h2(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("h2".to_string());
         }
 
-        fn h3(&mut self) {
+        fn h3(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("h3".to_string());
         }
 
-        fn h4(&mut self) {
+        fn h4(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("h4".to_string());
         }
 
-        fn h5(&mut self) {
+        fn h5(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("h5".to_string());
         }
 
-        fn h6(&mut self) {
+        fn h6(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("h6".to_string());
         }
 
-        fn begin_bold(&mut self) {
+        fn begin_bold(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("begin_bold".to_string());
         }
 
@@ -68,7 +101,8 @@ pub mod tests {
             self.tokens.push("end_bold".to_string());
         }
 
-        fn begin_italic(&mut self) {
+        fn begin_italic(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("begin_italic".to_string());
         }
 
@@ -76,7 +110,8 @@ pub mod tests {
             self.tokens.push("end_italic".to_string());
         }
 
-        fn begin_inline_code(&mut self) {
+        fn begin_inline_code(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("begin_inline_code".to_string());
         }
 
@@ -84,7 +119,8 @@ pub mod tests {
             self.tokens.push("end_inline_code".to_string())
         }
 
-        fn begin_label(&mut self) {
+        fn begin_label(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("begin_label".to_string());
         }
 
@@ -96,15 +132,80 @@ pub mod tests {
             self.tokens.push(format!("url({})", url));
         }
 
-        fn word(&mut self, text: &str) {
+        fn word(&mut self, text: &str, span: Span) {
+            self.spans.push(span);
             self.tokens.push(format!("word({})", text));
         }
 
-        fn image(&mut self) {
+        fn image(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("img".to_string());
         }
 
-        fn line_break(&mut self) {
+        fn inline_image(&mut self, span: Span) {
+            self.spans.push(span);
+            self.tokens.push("inline_img".to_string());
+        }
+
+        fn unordered_item(&mut self, marker: &str, indent: usize, span: Span) {
+            self.spans.push(span);
+            self.tokens
+                .push(format!("unordered_item({:?}, {})", marker, indent));
+        }
+
+        fn ordered_item(&mut self, number: u64, marker: &str, indent: usize, span: Span) {
+            self.spans.push(span);
+            self.tokens.push(format!(
+                "ordered_item({}, {:?}, {})",
+                number, marker, indent
+            ));
+        }
+
+        fn blockquote(&mut self, depth: usize, span: Span) {
+            self.spans.push(span);
+            self.tokens.push(format!("blockquote({})", depth));
+        }
+
+        fn line_indent(&mut self, indent: usize) {
+            self.tokens.push(format!("line_indent({})", indent));
+        }
+
+        fn begin_table(&mut self, span: Span) {
+            self.spans.push(span);
+            self.tokens.push("begin_table".to_string());
+        }
+
+        fn table_alignments(&mut self, alignments: &[Alignment]) {
+            self.tokens
+                .push(format!("table_alignments({:?})", alignments));
+        }
+
+        fn table_row(&mut self, span: Span) {
+            self.spans.push(span);
+            self.tokens.push("table_row".to_string());
+        }
+
+        fn table_cell(&mut self) {
+            self.tokens.push("table_cell".to_string());
+        }
+
+        fn begin_code_block(&mut self, info: &str, span: Span) {
+            self.spans.push(span);
+            self.tokens.push(format!("begin_code_block({})", info));
+        }
+
+        fn code_line(&mut self, line: &str, span: Span) {
+            self.spans.push(span);
+            self.tokens.push(format!("code_line({})", line));
+        }
+
+        fn end_code_block(&mut self, span: Span) {
+            self.spans.push(span);
+            self.tokens.push("end_code_block".to_string());
+        }
+
+        fn line_break(&mut self, span: Span) {
+            self.spans.push(span);
             self.tokens.push("line_break".to_string());
         }
     }
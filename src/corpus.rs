@@ -0,0 +1,147 @@
+//! Many documents, each known by the path it was read from.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::Document;
+
+/// Parsed documents and their paths, in the order they were added.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Corpus {
+    documents: Vec<(String, Document)>,
+}
+
+/// Why files could not be loaded into a `Corpus`.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The glob pattern is malformed.
+    Pattern { pattern: String, message: String },
+    /// A file matching the pattern could not be read.
+    Io { path: String, error: io::Error },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Pattern { pattern, message } => {
+                write!(f, "invalid pattern `{}`: {}", pattern, message)
+            }
+            LoadError::Io { path, error } => write!(f, "cannot read {}: {}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Pattern { .. } => None,
+            LoadError::Io { error, .. } => Some(error),
+        }
+    }
+}
+
+/// How paths are matched: `*` stays within a directory and `**` crosses
+/// them, as in `docs/**/*.md`.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+impl Corpus {
+    pub fn new() -> Self {
+        Corpus::default()
+    }
+
+    /// Reads and parses the files matching a glob pattern, in path order.
+    pub fn load(pattern: &str) -> Result<Self, LoadError> {
+        let mut corpus = Corpus::new();
+        corpus.load_more(pattern)?;
+        Ok(corpus)
+    }
+
+    /// Reads and parses the files matching a glob pattern into the corpus,
+    /// returning how many there were. A pattern without wildcards names a
+    /// single file, which must exist.
+    pub fn load_more(&mut self, pattern: &str) -> Result<usize, LoadError> {
+        let paths = Corpus::paths(pattern)?;
+        for path in &paths {
+            self.add_file(path)?;
+        }
+        Ok(paths.len())
+    }
+
+    /// The files a glob pattern matches, in path order, as `load_more`
+    /// would read them.
+    pub fn paths(pattern: &str) -> Result<Vec<PathBuf>, LoadError> {
+        if Pattern::escape(pattern) == pattern {
+            return Ok(vec![PathBuf::from(pattern)]);
+        }
+
+        let mut paths = Vec::new();
+        for entry in glob::glob_with(pattern, MATCH_OPTIONS)
+            .map_err(|error| invalid_pattern(pattern, error))?
+        {
+            let path = entry.map_err(|error| LoadError::Io {
+                path: error.path().display().to_string(),
+                error: error.into(),
+            })?;
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+
+    fn add_file(&mut self, path: &Path) -> Result<(), LoadError> {
+        let name = path.display().to_string();
+        let content = std::fs::read_to_string(path).map_err(|error| LoadError::Io {
+            path: name.clone(),
+            error,
+        })?;
+        self.add(name, crate::parse(&content));
+        Ok(())
+    }
+
+    pub fn add(&mut self, path: impl Into<String>, document: Document) {
+        self.documents.push((path.into(), document));
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// The documents and their paths.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Document)> {
+        self.documents
+            .iter()
+            .map(|(path, document)| (path.as_str(), document))
+    }
+
+    /// The documents whose path matches a glob pattern. A leading `./` on
+    /// either is ignored.
+    pub fn matching(
+        &self,
+        pattern: &str,
+    ) -> Result<impl Iterator<Item = (&str, &Document)>, LoadError> {
+        let pattern = Pattern::new(pattern.strip_prefix("./").unwrap_or(pattern))
+            .map_err(|error| invalid_pattern(pattern, error))?;
+        Ok(self.iter().filter(move |(path, _)| {
+            pattern.matches_with(path.strip_prefix("./").unwrap_or(path), MATCH_OPTIONS)
+        }))
+    }
+}
+
+fn invalid_pattern(pattern: &str, error: glob::PatternError) -> LoadError {
+    LoadError::Pattern {
+        pattern: pattern.to_string(),
+        message: error.msg.to_string(),
+    }
+}
@@ -0,0 +1,305 @@
+//! Renders a `Document` as HTML.
+//!
+//! ```
+//! let document = md_parser::parse("# Hello\n\nSome **bold** [text](https://a.com).");
+//! let options = md_parser::html::Options {
+//!     heading_ids: true,
+//!     ..Default::default()
+//! };
+//! assert_eq!(
+//!     md_parser::html::render(&document, &options),
+//!     "<h1 id=\"hello\">Hello</h1>\n\
+//!      <p>Some <strong>bold</strong> <a href=\"https://a.com\">text</a>.</p>\n"
+//! );
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::io;
+
+use crate::ast::plain_text;
+use crate::sink::write_io;
+use crate::{Alignment, Document, Line, ListItem, Token};
+
+/// Options for rendering.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Options {
+    /// Whether headings get an `id` attribute to link to, made from their
+    /// text as GitHub does it: `## Getting started` gets `getting-started`.
+    pub heading_ids: bool,
+    /// Put before every heading id, to keep them apart from the other ids
+    /// of the page the document is shown in.
+    pub id_prefix: String,
+    /// Whether links and images leave out a `javascript:`, `vbscript:` or
+    /// `data:` URL, which could run script from a document that is not
+    /// trusted. They keep their text.
+    pub safe_urls: bool,
+}
+
+/// Renders a document to a string.
+pub fn render(document: &Document, options: &Options) -> String {
+    let mut out = String::new();
+    render_to(document, options, &mut out).expect("writing to a String never fails");
+    out
+}
+
+/// Renders a document to a `fmt::Write`, such as a `Formatter`, a block
+/// at a time.
+pub fn render_to(document: &Document, options: &Options, out: &mut impl Write) -> fmt::Result {
+    Renderer::new(options, out).lines(document)
+}
+
+/// Renders a document to an `io::Write`, such as a file or standard
+/// output, a block at a time.
+pub fn write(document: &Document, options: &Options, out: &mut impl io::Write) -> io::Result<()> {
+    write_io(out, |out| Renderer::new(options, out).lines(document))
+}
+
+struct Renderer<'a> {
+    options: &'a Options,
+    ids: HeadingIds,
+    out: &'a mut dyn Write,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(options: &'a Options, out: &'a mut dyn Write) -> Self {
+        Renderer {
+            options,
+            ids: HeadingIds::new(),
+            out,
+        }
+    }
+
+    fn lines(&mut self, lines: &[Line]) -> fmt::Result {
+        lines.iter().try_for_each(|line| self.line(line))
+    }
+
+    fn line(&mut self, line: &Line) -> fmt::Result {
+        match line {
+            Line::Header { level, tokens, .. } => {
+                let level = level.number();
+                write!(self.out, "<h{}", level)?;
+                if self.options.heading_ids {
+                    let id = self.ids.next(&plain_text(tokens));
+                    self.out.write_str(" id=\"")?;
+                    escape(&self.options.id_prefix, self.out)?;
+                    escape(&id, self.out)?;
+                    self.out.write_char('"')?;
+                }
+                self.out.write_char('>')?;
+                self.tokens(tokens)?;
+                writeln!(self.out, "</h{}>", level)
+            }
+            Line::Paragraph(tokens, _) => {
+                self.out.write_str("<p>")?;
+                self.tokens(tokens)?;
+                self.out.write_str("</p>\n")
+            }
+            Line::Image { label, url, .. } => {
+                self.out.write_str("<p><img")?;
+                self.url("src", url)?;
+                self.out.write_str(" alt=\"")?;
+                escape(&plain_text(label), self.out)?;
+                self.out.write_str("\"></p>\n")
+            }
+            Line::CodeBlock { info, content, .. } => {
+                self.out.write_str("<pre><code")?;
+                if let Some(language) = info.split_whitespace().next() {
+                    self.out.write_str(" class=\"language-")?;
+                    escape(language, self.out)?;
+                    self.out.write_char('"')?;
+                }
+                self.out.write_char('>')?;
+                escape(content, self.out)?;
+                self.out.write_str("</code></pre>\n")
+            }
+            Line::List {
+                ordered,
+                start,
+                loose,
+                items,
+                ..
+            } => {
+                let tag = if *ordered { "ol" } else { "ul" };
+                write!(self.out, "<{}", tag)?;
+                if *ordered && *start != 1 {
+                    write!(self.out, " start=\"{}\"", start)?;
+                }
+                self.out.write_str(">\n")?;
+                items.iter().try_for_each(|item| self.item(item, *loose))?;
+                writeln!(self.out, "</{}>", tag)
+            }
+            Line::Blockquote(lines, _) => {
+                self.out.write_str("<blockquote>\n")?;
+                self.lines(lines)?;
+                self.out.write_str("</blockquote>\n")
+            }
+            Line::Table {
+                alignments,
+                header,
+                rows,
+                ..
+            } => {
+                self.out.write_str("<table>\n<thead>\n")?;
+                self.row("th", alignments, header)?;
+                self.out.write_str("</thead>\n")?;
+                if !rows.is_empty() {
+                    self.out.write_str("<tbody>\n")?;
+                    for row in rows {
+                        self.row("td", alignments, row)?;
+                    }
+                    self.out.write_str("</tbody>\n")?;
+                }
+                self.out.write_str("</table>\n")
+            }
+            Line::Blank(_) => Ok(()),
+        }
+    }
+
+    /// Writes a list item. The text of an item of a loose list is a
+    /// paragraph of its own.
+    fn item(&mut self, item: &ListItem, loose: bool) -> fmt::Result {
+        self.out.write_str("<li>")?;
+        if loose && !item.tokens.is_empty() {
+            self.out.write_str("\n<p>")?;
+            self.tokens(&item.tokens)?;
+            self.out.write_str("</p>")?;
+        } else {
+            self.tokens(&item.tokens)?;
+        }
+        if loose || !item.children.is_empty() {
+            self.out.write_char('\n')?;
+            self.lines(&item.children)?;
+        }
+        self.out.write_str("</li>\n")
+    }
+
+    fn row(&mut self, tag: &str, alignments: &[Alignment], cells: &[Vec<Token>]) -> fmt::Result {
+        self.out.write_str("<tr>\n")?;
+        for (i, cell) in cells.iter().enumerate() {
+            write!(self.out, "<{}", tag)?;
+            match alignments.get(i) {
+                Some(Alignment::Left) => self.out.write_str(" align=\"left\"")?,
+                Some(Alignment::Center) => self.out.write_str(" align=\"center\"")?,
+                Some(Alignment::Right) => self.out.write_str(" align=\"right\"")?,
+                Some(Alignment::None) | None => {}
+            }
+            self.out.write_char('>')?;
+            self.tokens(cell)?;
+            writeln!(self.out, "</{}>", tag)?;
+        }
+        self.out.write_str("</tr>\n")
+    }
+
+    fn tokens(&mut self, tokens: &[Token]) -> fmt::Result {
+        tokens.iter().try_for_each(|token| self.token(token))
+    }
+
+    fn token(&mut self, token: &Token) -> fmt::Result {
+        match token {
+            Token::Bold(tokens, _) => self.tag("strong", tokens),
+            Token::Italic(tokens, _) => self.tag("em", tokens),
+            Token::InlineCode(tokens, _) => self.tag("code", tokens),
+            Token::Regular(text, _) => escape(text, self.out),
+            Token::Link { label, url, .. } => {
+                self.out.write_str("<a")?;
+                self.url("href", url)?;
+                self.out.write_char('>')?;
+                self.tokens(label)?;
+                self.out.write_str("</a>")
+            }
+            Token::Image { label, url, .. } => {
+                self.out.write_str("<img")?;
+                self.url("src", url)?;
+                self.out.write_str(" alt=\"")?;
+                escape(&plain_text(label), self.out)?;
+                self.out.write_str("\">")
+            }
+            Token::SoftBreak(_) => self.out.write_char('\n'),
+        }
+    }
+
+    /// Writes `url` as the `attribute` of the tag being written, unless it
+    /// is left out for `safe_urls`.
+    fn url(&mut self, attribute: &str, url: &str) -> fmt::Result {
+        if self.options.safe_urls && runs_script(url) {
+            return Ok(());
+        }
+        write!(self.out, " {}=\"", attribute)?;
+        escape(url, self.out)?;
+        self.out.write_char('"')
+    }
+
+    fn tag(&mut self, tag: &str, tokens: &[Token]) -> fmt::Result {
+        write!(self.out, "<{}>", tag)?;
+        self.tokens(tokens)?;
+        write!(self.out, "</{}>", tag)
+    }
+}
+
+/// Whether `url` has a scheme that runs script or embeds content. Browsers
+/// ignore whitespace and control characters in a scheme, so this does too.
+fn runs_script(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    let scheme: String = scheme
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .collect();
+    ["javascript", "vbscript", "data"]
+        .iter()
+        .any(|unsafe_scheme| scheme.eq_ignore_ascii_case(unsafe_scheme))
+}
+
+/// Writes `text` with the characters that mean something in HTML, in text
+/// or in a quoted attribute, replaced by entities.
+fn escape(text: &str, out: &mut dyn Write) -> fmt::Result {
+    let mut rest = text;
+    while let Some(i) = rest.find(['&', '<', '>', '"', '\'']) {
+        out.write_str(&rest[..i])?;
+        out.write_str(match rest.as_bytes()[i] {
+            b'&' => "&amp;",
+            b'<' => "&lt;",
+            b'>' => "&gt;",
+            b'"' => "&quot;",
+            _ => "&#39;",
+        })?;
+        rest = &rest[i + 1..];
+    }
+    out.write_str(rest)
+}
+
+/// The ids of the headings of a document, as GitHub makes them: the text in
+/// lower case with spaces as `-` and punctuation left out. A text seen before
+/// gets `-1`, `-2` and so on after it, so that every id is unique.
+#[derive(Debug, Default, Clone)]
+pub struct HeadingIds {
+    seen: HashMap<String, usize>,
+}
+
+impl HeadingIds {
+    pub fn new() -> Self {
+        HeadingIds::default()
+    }
+
+    /// The id of the next heading, which has the plain text `text`.
+    pub fn next(&mut self, text: &str) -> String {
+        let slug: String = text
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect();
+        let count = self.seen.entry(slug.clone()).or_default();
+        *count += 1;
+        match *count {
+            1 => slug,
+            n => format!("{}-{}", slug, n - 1),
+        }
+    }
+}
@@ -0,0 +1,548 @@
+//! Character-level scanner for inline content.
+//!
+//! Text is first split into literal text, code spans, links, images and
+//! `*`/`_` delimiter runs, then the delimiter runs are matched into emphasis
+//! following the CommonMark "process emphasis" algorithm.
+
+use std::ops::Range;
+
+/// An inline node and the byte range of the scanned text it came from.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Inline {
+    pub(crate) node: Node,
+    pub(crate) range: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Node {
+    Text(String),
+    Code(String),
+    Emphasis { strong: bool, children: Vec<Inline> },
+    Link { label: Vec<Inline>, url: String },
+    Image { label: Vec<Inline>, url: String },
+}
+
+#[derive(Debug)]
+enum Item {
+    Node(Inline),
+    Delimiter(Delimiter),
+}
+
+/// The unmatched part of a delimiter run, `start..start + count`.
+#[derive(Debug)]
+struct Delimiter {
+    ch: char,
+    start: usize,
+    count: usize,
+    original: usize,
+    can_open: bool,
+    can_close: bool,
+}
+
+pub(crate) fn parse(text: &str) -> Vec<Inline> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    parse_chars(&chars, text.len())
+}
+
+fn parse_chars(chars: &[(usize, char)], end: usize) -> Vec<Inline> {
+    let items = process_emphasis(scan(chars, end));
+
+    let mut inlines = Vec::new();
+    for item in items {
+        push_inline(&mut inlines, into_inline(item));
+    }
+    inlines
+}
+
+/// Scans `chars`, whose text ends at byte offset `end`.
+fn scan(chars: &[(usize, char)], end: usize) -> Vec<Item> {
+    let offset = |i: usize| chars.get(i).map_or(end, |(o, _)| *o);
+    let char_at = |i: usize| chars.get(i).map(|(_, c)| *c);
+
+    let mut items = Vec::new();
+    let mut text = String::new();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i].1;
+        if text.is_empty() {
+            text_start = offset(i);
+        }
+
+        match c {
+            '\\' if char_at(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                text.push(chars[i + 1].1);
+                i += 2;
+            }
+            '`' => {
+                let n = run_length(chars, i);
+                match code_span_end(chars, i + n, n) {
+                    Some(close) => {
+                        flush_text(&mut items, &mut text, text_start..offset(i));
+                        let code = code_span_content(&chars[i + n..close]);
+                        items.push(Item::Node(Inline {
+                            node: Node::Code(code),
+                            range: offset(i)..offset(close + n),
+                        }));
+                        i = close + n;
+                    }
+                    None => {
+                        text.extend(chars[i..i + n].iter().map(|(_, c)| c));
+                        i += n;
+                    }
+                }
+            }
+            '*' | '_' => {
+                let n = run_length(chars, i);
+                let before = i.checked_sub(1).and_then(char_at);
+                let after = char_at(i + n);
+                let (left, right) = flanking(before, after);
+
+                let (can_open, can_close) = if c == '*' {
+                    (left, right)
+                } else {
+                    (
+                        left && (!right || is_punctuation(before)),
+                        right && (!left || is_punctuation(after)),
+                    )
+                };
+
+                flush_text(&mut items, &mut text, text_start..offset(i));
+                items.push(Item::Delimiter(Delimiter {
+                    ch: c,
+                    start: offset(i),
+                    count: n,
+                    original: n,
+                    can_open,
+                    can_close,
+                }));
+                i += n;
+            }
+            '[' => match link_at(chars, i) {
+                Some((label_end, url, next)) => {
+                    flush_text(&mut items, &mut text, text_start..offset(i));
+                    let label = parse_chars(&chars[i + 1..label_end], offset(label_end));
+                    items.push(Item::Node(Inline {
+                        node: Node::Link { label, url },
+                        range: offset(i)..offset(next),
+                    }));
+                    i = next;
+                }
+                None => {
+                    text.push(c);
+                    i += 1;
+                }
+            },
+            '!' if char_at(i + 1) == Some('[') => match link_at(chars, i + 1) {
+                Some((label_end, url, next)) => {
+                    flush_text(&mut items, &mut text, text_start..offset(i));
+                    let label = parse_chars(&chars[i + 2..label_end], offset(label_end));
+                    items.push(Item::Node(Inline {
+                        node: Node::Image { label, url },
+                        range: offset(i)..offset(next),
+                    }));
+                    i = next;
+                }
+                None => {
+                    text.push(c);
+                    i += 1;
+                }
+            },
+            _ => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    flush_text(&mut items, &mut text, text_start..end);
+    items
+}
+
+/// Matches closing delimiter runs with the nearest compatible opener,
+/// wrapping everything between them in emphasis.
+fn process_emphasis(mut items: Vec<Item>) -> Vec<Item> {
+    let mut i = 0;
+
+    while i < items.len() {
+        let Item::Delimiter(closer) = &items[i] else {
+            i += 1;
+            continue;
+        };
+
+        if !closer.can_close || closer.count == 0 {
+            i += 1;
+            continue;
+        }
+
+        let opener = (0..i).rev().find(|&j| match &items[j] {
+            Item::Delimiter(opener) => {
+                opener.ch == closer.ch
+                    && opener.can_open
+                    && opener.count > 0
+                    && !violates_rule_of_three(opener, closer)
+            }
+            Item::Node(_) => false,
+        });
+
+        let Some(j) = opener else {
+            i += 1;
+            continue;
+        };
+
+        let used = match (&items[j], &items[i]) {
+            (Item::Delimiter(o), Item::Delimiter(c)) if o.count >= 2 && c.count >= 2 => 2,
+            _ => 1,
+        };
+
+        let mut children = Vec::new();
+        for item in items.drain(j + 1..i) {
+            push_inline(&mut children, into_inline(item));
+        }
+
+        // The opener gives up the end of its run and the closer the start.
+        let mut range = 0..0;
+        if let Item::Delimiter(opener) = &mut items[j] {
+            opener.count -= used;
+            range.start = opener.start + opener.count;
+        }
+        if let Item::Delimiter(closer) = &mut items[j + 1] {
+            closer.start += used;
+            closer.count -= used;
+            range.end = closer.start;
+        }
+
+        items.insert(
+            j + 1,
+            Item::Node(Inline {
+                node: Node::Emphasis {
+                    strong: used == 2,
+                    children,
+                },
+                range,
+            }),
+        );
+
+        let mut closer_index = j + 2;
+        if matches!(&items[j], Item::Delimiter(o) if o.count == 0) {
+            items.remove(j);
+            closer_index -= 1;
+        }
+        if matches!(&items[closer_index], Item::Delimiter(c) if c.count == 0) {
+            items.remove(closer_index);
+        }
+        i = closer_index;
+    }
+
+    items
+}
+
+/// A run that can both open and close only matches when the combined
+/// length is not a multiple of three, unless both lengths are.
+fn violates_rule_of_three(opener: &Delimiter, closer: &Delimiter) -> bool {
+    (opener.can_close || closer.can_open)
+        && (opener.original + closer.original).is_multiple_of(3)
+        && !(opener.original.is_multiple_of(3) && closer.original.is_multiple_of(3))
+}
+
+fn into_inline(item: Item) -> Inline {
+    match item {
+        Item::Node(inline) => inline,
+        Item::Delimiter(d) => Inline {
+            node: Node::Text(d.ch.to_string().repeat(d.count)),
+            range: d.start..d.start + d.count,
+        },
+    }
+}
+
+/// Pushes an inline, merging adjacent text so it is emitted as one run.
+fn push_inline(inlines: &mut Vec<Inline>, inline: Inline) {
+    match (inlines.last_mut(), inline) {
+        (
+            _,
+            Inline {
+                node: Node::Text(t),
+                ..
+            },
+        ) if t.is_empty() => {}
+        (
+            Some(Inline {
+                node: Node::Text(last),
+                range,
+            }),
+            Inline {
+                node: Node::Text(t),
+                range: next,
+            },
+        ) => {
+            last.push_str(&t);
+            range.end = next.end;
+        }
+        (_, inline) => inlines.push(inline),
+    }
+}
+
+fn flush_text(items: &mut Vec<Item>, text: &mut String, range: Range<usize>) {
+    if !text.is_empty() {
+        items.push(Item::Node(Inline {
+            node: Node::Text(std::mem::take(text)),
+            range,
+        }));
+    }
+}
+
+fn run_length(chars: &[(usize, char)], start: usize) -> usize {
+    chars[start..]
+        .iter()
+        .take_while(|(_, c)| *c == chars[start].1)
+        .count()
+}
+
+fn code_span_end(chars: &[(usize, char)], start: usize, n: usize) -> Option<usize> {
+    let mut j = start;
+    while j < chars.len() {
+        if chars[j].1 == '`' {
+            let m = run_length(chars, j);
+            if m == n {
+                return Some(j);
+            }
+            j += m;
+        } else {
+            j += 1;
+        }
+    }
+    None
+}
+
+/// Strips one surrounding space from code spans like `` ` a ` ``, unless the
+/// span is made only of spaces.
+fn code_span_content(chars: &[(usize, char)]) -> String {
+    let content: String = chars.iter().map(|(_, c)| c).collect();
+    let padded = content.len() > 2 && content.starts_with(' ') && content.ends_with(' ');
+
+    if padded && !content.trim().is_empty() {
+        content[1..content.len() - 1].to_string()
+    } else {
+        content
+    }
+}
+
+/// Finds a `[label](url)` link starting at `start`, returning the index of
+/// the closing `]`, the destination and the index after the closing `)`.
+fn link_at(chars: &[(usize, char)], start: usize) -> Option<(usize, String, usize)> {
+    let mut depth = 0;
+    let mut i = start;
+    let label_end = loop {
+        match chars.get(i)?.1 {
+            '\\' => i += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    };
+
+    if chars.get(label_end + 1).map(|(_, c)| *c) != Some('(') {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut i = label_end + 1;
+    let url_end = loop {
+        match chars.get(i)?.1 {
+            '\\' => i += 1,
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    };
+
+    let destination: String = chars[label_end + 2..url_end]
+        .iter()
+        .map(|(_, c)| c)
+        .collect();
+    let destination = destination.split_whitespace().next().unwrap_or_default();
+    let url = destination
+        .strip_prefix('<')
+        .and_then(|d| d.strip_suffix('>'))
+        .unwrap_or(destination);
+
+    Some((label_end, url.to_string(), url_end + 1))
+}
+
+fn flanking(before: Option<char>, after: Option<char>) -> (bool, bool) {
+    let left = !is_whitespace(after)
+        && (!is_punctuation(after) || is_whitespace(before) || is_punctuation(before));
+    let right = !is_whitespace(before)
+        && (!is_punctuation(before) || is_whitespace(after) || is_punctuation(after));
+    (left, right)
+}
+
+/// The start and end of the text count as whitespace.
+fn is_whitespace(c: Option<char>) -> bool {
+    c.is_none_or(char::is_whitespace)
+}
+
+fn is_punctuation(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_ascii_punctuation() || (!c.is_alphanumeric() && !c.is_whitespace()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Inline, Node};
+
+    /// Parses `text`, dropping ranges so tests can focus on structure.
+    fn parse_nodes(text: &str) -> Vec<Inline> {
+        fn strip(inlines: Vec<Inline>) -> Vec<Inline> {
+            inlines
+                .into_iter()
+                .map(|inline| match inline.node {
+                    Node::Emphasis { strong, children } => emphasis(strong, strip(children)),
+                    Node::Link { label, url } => link(strip(label), &url),
+                    Node::Image { label, url } => image(strip(label), &url),
+                    node => Inline { node, range: 0..0 },
+                })
+                .collect()
+        }
+        strip(parse(text))
+    }
+
+    fn text(t: &str) -> Inline {
+        Inline {
+            node: Node::Text(t.to_string()),
+            range: 0..0,
+        }
+    }
+
+    fn code(c: &str) -> Inline {
+        Inline {
+            node: Node::Code(c.to_string()),
+            range: 0..0,
+        }
+    }
+
+    fn emphasis(strong: bool, children: Vec<Inline>) -> Inline {
+        Inline {
+            node: Node::Emphasis { strong, children },
+            range: 0..0,
+        }
+    }
+
+    fn link(label: Vec<Inline>, url: &str) -> Inline {
+        Inline {
+            node: Node::Link {
+                label,
+                url: url.to_string(),
+            },
+            range: 0..0,
+        }
+    }
+
+    fn image(label: Vec<Inline>, url: &str) -> Inline {
+        Inline {
+            node: Node::Image {
+                label,
+                url: url.to_string(),
+            },
+            range: 0..0,
+        }
+    }
+
+    #[test]
+    fn delimiter_runs() {
+        assert_eq!(
+            parse_nodes("**bold**, (*italic*) snake_case_word"),
+            vec![
+                emphasis(true, vec![text("bold")]),
+                text(", ("),
+                emphasis(false, vec![text("italic")]),
+                text(") snake_case_word")
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_delimiters_are_text() {
+        assert_eq!(parse_nodes("a * b ** c"), vec![text("a * b ** c")]);
+        assert_eq!(parse_nodes("**open"), vec![text("**open")]);
+        assert_eq!(parse_nodes(r"\*escaped\*"), vec![text("*escaped*")]);
+    }
+
+    #[test]
+    fn nested_emphasis() {
+        assert_eq!(
+            parse_nodes("*a **b** c*"),
+            vec![emphasis(
+                false,
+                vec![text("a "), emphasis(true, vec![text("b")]), text(" c")]
+            )]
+        );
+    }
+
+    #[test]
+    fn code_spans() {
+        assert_eq!(
+            parse_nodes("`` a `b` `` and `*c*`"),
+            vec![code("a `b`"), text(" and "), code("*c*")]
+        );
+        assert_eq!(parse_nodes("`unclosed"), vec![text("`unclosed")]);
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            parse_nodes("see [the *docs*](<https://a.com> \"title\")."),
+            vec![
+                text("see "),
+                link(
+                    vec![text("the "), emphasis(false, vec![text("docs")])],
+                    "https://a.com"
+                ),
+                text(".")
+            ]
+        );
+        assert_eq!(parse_nodes("[not a link]"), vec![text("[not a link]")]);
+    }
+
+    #[test]
+    fn images() {
+        assert_eq!(
+            parse_nodes("a ![*b*](c.png) d"),
+            vec![
+                text("a "),
+                image(vec![emphasis(false, vec![text("b")])], "c.png"),
+                text(" d")
+            ]
+        );
+        assert_eq!(parse_nodes("![a] and !"), vec![text("![a] and !")]);
+    }
+
+    #[test]
+    fn ranges() {
+        let inlines = parse("é ***x*** [y](u)");
+        let ranges: Vec<_> = inlines.iter().map(|i| i.range.clone()).collect();
+        assert_eq!(ranges, vec![0..3, 3..10, 10..11, 11..17]);
+
+        let Node::Emphasis { children, .. } = &inlines[1].node else {
+            panic!("expected emphasis");
+        };
+        assert_eq!(children[0].range, 4..9);
+
+        let Node::Link { label, .. } = &inlines[3].node else {
+            panic!("expected link");
+        };
+        assert_eq!(label[0].range, 12..13);
+    }
+}
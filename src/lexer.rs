@@ -1,10 +1,36 @@
-use crate::TokenCollector;
+use crate::inline::{self, Inline, Node};
+use crate::{Alignment, Span, TokenCollector};
 
 pub struct Lexer<'a, T>
 where
     T: TokenCollector,
 {
     collector: &'a mut T,
+    fence: Option<Fence>,
+    table_columns: usize,
+    table_quote_depth: usize,
+    position: Position,
+}
+
+/// An open fenced code block: its marker and how many of them there are,
+/// how far the opening fence is indented and how many `>` quote it.
+#[derive(Clone, Copy)]
+struct Fence {
+    marker: char,
+    len: usize,
+    indent: usize,
+    quote_depth: usize,
+}
+
+/// Where the lexer is in its input, carried across calls to `lex` as if
+/// the inputs were consecutive lines of one document.
+#[derive(Default)]
+struct Position {
+    offset: usize,
+    line_number: usize,
+    line_start: usize,
+    line_address: usize,
+    line: String,
 }
 
 impl<'a, T> Lexer<'a, T>
@@ -12,191 +38,459 @@ where
     T: TokenCollector,
 {
     pub fn new(collector: &'a mut T) -> Self {
-        Self { collector }
+        Self {
+            collector,
+            fence: None,
+            table_columns: 0,
+            table_quote_depth: 0,
+            position: Position::default(),
+        }
     }
 
     pub fn lex(&mut self, input: &str) {
-        let lines = input.lines();
+        let mut lines = input.lines().peekable();
+
+        while let Some(line) = lines.next() {
+            self.start_line(input, line);
+
+            if self.fence.is_some() && self.lex_code_line(line) {
+                continue;
+            }
+
+            if self.lex_fence(line).is_some() {
+                self.table_columns = 0;
+            } else {
+                // Tables may be quoted, with the same `>` on every line.
+                let (quote_depth, row) = quote_markers(line, usize::MAX);
+                // A line inside a table is a row, even if the next line
+                // could be the delimiter row of another.
+                let in_table = self.table_columns > 0 && quote_depth == self.table_quote_depth;
+                let alignments = lines
+                    .peek()
+                    .map(|next| quote_markers(next, usize::MAX))
+                    .filter(|(next_depth, _)| !in_table && *next_depth == quote_depth)
+                    .and_then(|(_, next)| delimiter_row(next))
+                    .filter(|a| row.contains('|') && a.len() == split_cells(row).len());
+
+                if let Some(alignments) = alignments {
+                    self.lex_table_quote(line, quote_depth);
+                    self.lex_table_head(row, &alignments);
+                    self.table_quote_depth = quote_depth;
+                    if let Some(delimiter) = lines.next() {
+                        self.collector.line_break(self.line_break_span());
+                        self.start_line(input, delimiter);
+                        self.collector.table_alignments(&alignments);
+                    }
+                } else if in_table && row.contains('|') {
+                    self.lex_table_quote(line, quote_depth);
+                    self.lex_table_row(row);
+                } else {
+                    self.table_columns = 0;
+                    self.lex_line(line);
+                }
+                self.collector.line_break(self.line_break_span());
+            }
+        }
+
+        self.position.offset += input.len();
+        if !input.is_empty() && !input.ends_with('\n') {
+            self.position.offset += 1;
+        }
+    }
+
+    fn start_line(&mut self, input: &str, line: &str) {
+        let position = &mut self.position;
+        position.line_number += 1;
+        position.line_start = position.offset + (line.as_ptr() as usize - input.as_ptr() as usize);
+        position.line_address = line.as_ptr() as usize;
+        position.line = line.to_string();
+    }
+
+    /// The span of `text`, which must be a slice of the current line.
+    fn span(&self, text: &str) -> Span {
+        let position = &self.position;
+        let relative = text.as_ptr() as usize - position.line_address;
+        let start = position.line_start + relative;
+
+        Span {
+            start,
+            end: start + text.len(),
+            line: position.line_number,
+            column: position.line[..relative].chars().count() + 1,
+        }
+    }
+
+    /// The span of the line break that ends the current line.
+    fn line_break_span(&self) -> Span {
+        let position = &self.position;
+        let start = position.line_start + position.line.len();
+
+        Span {
+            start,
+            end: start + 1,
+            line: position.line_number,
+            column: position.line.chars().count() + 1,
+        }
+    }
+
+    /// Lexes the `>` markers before a table line, if there are any.
+    fn lex_table_quote(&mut self, line: &str, depth: usize) {
+        if depth > 0 {
+            self.collector.blockquote(depth, self.span(line.trim()));
+        }
+    }
+
+    fn lex_table_head(&mut self, line: &str, alignments: &[Alignment]) {
+        self.table_columns = alignments.len();
+        self.collector.begin_table(self.span(line.trim()));
+        for cell in split_cells(line) {
+            self.collector.table_cell();
+            self.lex_cell(cell);
+        }
+    }
+
+    fn lex_table_row(&mut self, line: &str) {
+        let cells = split_cells(line);
+
+        self.collector.table_row(self.span(line.trim()));
+        for column in 0..self.table_columns {
+            self.collector.table_cell();
+            if let Some(cell) = cells.get(column) {
+                self.lex_cell(cell);
+            }
+        }
+    }
+
+    /// Lexes an opening fence, which may be quoted and indented to nest the
+    /// code block in a list item.
+    fn lex_fence(&mut self, line: &str) -> Option<()> {
+        let (quote_depth, rest) = quote_markers(line, usize::MAX);
+        let indent = indentation(rest);
+        let fence = rest.trim();
+        let marker = fence.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let len = fence.chars().take_while(|c| *c == marker).count();
+        let info = fence[len..].trim();
+
+        if len < 3 || (marker == '`' && info.contains('`')) {
+            return None;
+        }
+
+        if quote_depth > 0 {
+            self.collector
+                .blockquote(quote_depth, self.span(line.trim()));
+        }
+        if indent > 0 {
+            self.collector.line_indent(indent);
+        }
+        self.fence = Some(Fence {
+            marker,
+            len,
+            indent,
+            quote_depth,
+        });
+        self.collector.begin_code_block(info, self.span(fence));
+        Some(())
+    }
+
+    /// Lexes a line of an open code block, without the quote markers and
+    /// the indentation of its fence. A line outside the quote the block is
+    /// in ends the block and returns false, to be lexed as any other line.
+    fn lex_code_line(&mut self, line: &str) -> bool {
+        let Some(fence) = self.fence else {
+            return false;
+        };
 
-        for line in lines {
-            self.lex_line(line.trim());
-            self.collector.line_break();
+        let (quote_depth, rest) = quote_markers(line, fence.quote_depth);
+        if quote_depth < fence.quote_depth {
+            self.fence = None;
+            self.collector.end_code_block(self.span(&line[..0]));
+            return false;
         }
+
+        let content = strip_indentation(rest, fence.indent);
+        let trimmed = content.trim();
+        let closing = trimmed.len() >= fence.len && trimmed.chars().all(|c| c == fence.marker);
+
+        if closing {
+            self.fence = None;
+            self.collector.end_code_block(self.span(trimmed));
+        } else {
+            self.collector.code_line(content, self.span(content));
+        }
+        true
     }
 
     fn lex_line(&mut self, line: &str) {
+        let indent = indentation(line);
+        let line = line.trim();
         let first_char = line.chars().next();
 
+        let item = matches!(first_char, Some('-' | '*' | '+' | '0'..='9'))
+            && self.lex_list_item(line, indent).is_some();
+        if item {
+            return;
+        }
+        if indent > 0 && !matches!(first_char, None | Some('>')) {
+            self.collector.line_indent(indent);
+        }
+
         match first_char {
             Some('#') => self.lex_header(line),
+            Some('>') => self.lex_blockquote(line),
             Some('!') => self.lex_image(line),
-            Some(_) => {
-                let words = line.split_whitespace();
-                for word in words {
-                    self.lex_word(word);
-                }
-            }
+            Some(_) => self.lex_inline(line),
             None => {}
         }
     }
 
-    fn lex_header(&mut self, line: &str) {
-        let mut words = line.split_whitespace();
-
-        match words.next() {
-            Some("#") => self.collector.h1(),
-            Some("##") => self.collector.h2(),
-            Some("###") => self.collector.h3(),
-            Some("####") => self.collector.h4(),
-            Some("#####") => self.collector.h5(),
-            Some("######") => self.collector.h6(),
-            _ => {}
-        };
-
-        for word in words {
-            self.lex_word(word);
-        }
+    fn lex_blockquote(&mut self, line: &str) {
+        let (depth, rest) = quote_markers(line, usize::MAX);
+        self.collector.blockquote(depth, self.span(line));
+        self.lex_line(rest);
     }
 
-    fn lex_word(&mut self, word: &str) {
-        self.lex_inline_code(word)
-            .or_else(|| self.lex_bold(word))
-            .or_else(|| self.lex_italic(word))
-            .or_else(|| self.lex_label(word))
-            .unwrap_or_else(|| {
-                self.collector.word(word);
-            });
+    fn lex_inline(&mut self, text: &str) {
+        let text = text.trim();
+        for inline in inline::parse(text) {
+            self.emit_inline(text, inline);
+        }
     }
 
-    fn lex_bold(&mut self, word: &str) -> Option<()> {
-        if word.starts_with("**") || word.starts_with("__") {
-            self.collector.begin_bold();
-            if word.ends_with("**") || word.ends_with("__") {
-                self.lex_word(&word[2..word.len() - 2]);
-                self.collector.end_bold();
-            } else {
-                self.lex_word(&word[2..]);
+    /// Lexes a table cell, where `\|` is unescaped even inside code spans.
+    fn lex_cell(&mut self, cell: &str) {
+        fn unescape_pipes(inline: &mut Inline) {
+            match &mut inline.node {
+                Node::Code(code) => *code = code.replace("\\|", "|"),
+                Node::Emphasis { children, .. } => children.iter_mut().for_each(unescape_pipes),
+                Node::Link { label, .. } | Node::Image { label, .. } => {
+                    label.iter_mut().for_each(unescape_pipes)
+                }
+                Node::Text(_) => {}
             }
-            Some(())
-        } else if word.ends_with("**") || word.ends_with("__") {
-            self.lex_word(&word[..word.len() - 2]);
-            self.collector.end_bold();
-            Some(())
-        } else if word.ends_with("**.") || word.ends_with("__.") {
-            let word = format!("{}.", &word[..word.len() - 3]);
-            self.lex_word(&word);
-            self.collector.end_bold();
-            Some(())
-        } else {
-            None
+        }
+
+        let cell = cell.trim();
+        for mut inline in inline::parse(cell) {
+            unescape_pipes(&mut inline);
+            self.emit_inline(cell, inline);
         }
     }
 
-    fn lex_italic(&mut self, word: &str) -> Option<()> {
-        if word.starts_with('*') || word.starts_with('_') {
-            self.collector.begin_italic();
-            if word.ends_with('*') || word.ends_with('_') {
-                self.lex_word(&word[1..word.len() - 1]);
-                self.collector.end_italic();
-            } else {
-                self.lex_word(&word[1..]);
+    /// Emits an inline parsed from `text`, which its range is relative to.
+    fn emit_inline(&mut self, text: &str, inline: Inline) {
+        let span = self.span(&text[inline.range]);
+
+        match inline.node {
+            Node::Text(t) => self.collector.word(&t, span),
+            Node::Code(code) => {
+                self.collector.begin_inline_code(span);
+                self.collector.word(&code, span);
+                self.collector.end_inline_code();
+            }
+            Node::Emphasis { strong, children } => {
+                if strong {
+                    self.collector.begin_bold(span);
+                } else {
+                    self.collector.begin_italic(span);
+                }
+
+                for child in children {
+                    self.emit_inline(text, child);
+                }
+
+                if strong {
+                    self.collector.end_bold();
+                } else {
+                    self.collector.end_italic();
+                }
+            }
+            Node::Link { label, url } => {
+                self.collector.begin_label(span);
+                for child in label {
+                    self.emit_inline(text, child);
+                }
+                self.collector.end_label();
+                self.collector.url(&url);
+            }
+            Node::Image { label, url } => {
+                self.collector.inline_image(span);
+                self.collector.begin_label(span);
+                for child in label {
+                    self.emit_inline(text, child);
+                }
+                self.collector.end_label();
+                self.collector.url(&url);
             }
-            Some(())
-        } else if word.ends_with('*') || word.ends_with('_') {
-            self.lex_word(&word[..word.len() - 1]);
-            self.collector.end_italic();
-            Some(())
-        } else if word.ends_with("*.") || word.ends_with("_.") {
-            let word = format!("{}.", &word[..word.len() - 2]);
-            self.lex_word(&word);
-            self.collector.end_italic();
-            Some(())
-        } else {
-            None
         }
     }
 
-    fn lex_inline_code(&mut self, word: &str) -> Option<()> {
-        if let Some(word) = word.strip_prefix('`') {
-            self.collector.begin_inline_code();
-            if let Some(word) = word.strip_suffix('`') {
-                self.collector.word(word);
-                self.collector.end_inline_code();
-            } else {
-                self.collector.word(word);
+    fn lex_list_item(&mut self, line: &str, indent: usize) -> Option<()> {
+        // The marker of an item, with the spaces after it.
+        let marker = |rest: &str| &line[..line.len() - rest.trim_start().len()];
+
+        if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+            if !rest.starts_with(' ') {
+                return None;
             }
-            Some(())
-        } else if let Some(word) = word.strip_suffix('`') {
-            self.collector.word(word);
-            self.collector.end_inline_code();
-            Some(())
-        } else if let Some(word) = word.strip_suffix("`.") {
-            let word = word.to_owned() + ".";
-            self.collector.word(&word);
-            self.collector.end_inline_code();
-            Some(())
-        } else {
-            None
+
+            self.collector
+                .unordered_item(marker(rest), indent, self.span(line));
+            self.lex_inline(rest);
+            return Some(());
+        }
+
+        let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+        let rest = line[digits..].strip_prefix(['.', ')'])?;
+        if digits > 9 || !rest.starts_with(' ') {
+            return None;
         }
+
+        let number = line[..digits].parse().ok()?;
+        self.collector
+            .ordered_item(number, marker(rest), indent, self.span(line));
+        self.lex_inline(rest);
+        Some(())
     }
 
-    fn lex_label(&mut self, word: &str) -> Option<()> {
-        if let Some(word) = word.strip_prefix('[') {
-            self.collector.begin_label();
+    fn lex_header(&mut self, line: &str) {
+        let marker = line.split_whitespace().next().unwrap_or_default();
+        let span = self.span(line);
 
-            if word.contains("](") {
-                let mut parts = word.split("](");
-                let label = parts.next().unwrap_or_default();
-                let url = parts.next().unwrap_or_default();
+        match marker {
+            "#" => self.collector.h1(span),
+            "##" => self.collector.h2(span),
+            "###" => self.collector.h3(span),
+            "####" => self.collector.h4(span),
+            "#####" => self.collector.h5(span),
+            "######" => self.collector.h6(span),
+            _ => return self.lex_inline(line),
+        };
 
-                self.lex_word(label);
-                self.collector.end_label();
+        self.lex_inline(&line[marker.len()..]);
+    }
 
-                if let Some(url) = url.strip_suffix(").") {
-                    self.collector.url(url);
-                    self.collector.word(".");
-                } else if let Some(url) = url.strip_suffix(')') {
-                    self.collector.url(url);
+    /// Lexes a line starting with `!`, which is an image line when an
+    /// image is all there is on it.
+    fn lex_image(&mut self, line: &str) {
+        match inline::parse(line).as_slice() {
+            [Inline {
+                node: Node::Image { label, url },
+                range,
+            }] if range.len() == line.len() => {
+                self.collector.image(self.span(line));
+                self.collector.begin_label(self.span(&line[1..]));
+                for child in label {
+                    self.emit_inline(line, child.clone());
                 }
-            } else {
-                self.lex_word(word);
+                self.collector.end_label();
+                self.collector.url(url);
             }
+            _ => self.lex_inline(line),
+        }
+    }
+}
 
-            Some(())
-        } else if word.contains(']') {
-            let mut parts = word.split(']');
-            let label = parts.next().unwrap_or_default();
-            let url = parts.next().unwrap_or_default();
+/// How far `line` is indented, counting a tab as four columns.
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
 
-            self.lex_word(label);
-            self.collector.end_label();
+/// `line` without up to `indent` columns of its indentation.
+fn strip_indentation(line: &str, indent: usize) -> &str {
+    let mut columns = 0;
+    let start = line
+        .char_indices()
+        .find(|&(_, c)| {
+            let width = match c {
+                ' ' => 1,
+                '\t' => 4,
+                _ => return true,
+            };
+            columns += width;
+            columns > indent
+        })
+        .map_or(line.len(), |(i, _)| i);
+    &line[start..]
+}
 
-            if url.ends_with(").") {
-                self.collector.url(&url[1..url.len() - 3]);
-                self.collector.word(".");
-            } else if url.ends_with(')') {
-                self.collector.url(&url[1..url.len() - 1]);
-            }
+/// Strips up to `max` blockquote markers, each with the space after it,
+/// from the start of `line`. Returns how many there were and the rest.
+fn quote_markers(line: &str, max: usize) -> (usize, &str) {
+    let mut depth = 0;
+    let mut rest = line;
 
-            Some(())
-        } else {
-            None
+    while depth < max {
+        let Some(inner) = rest.trim_start().strip_prefix('>') else {
+            break;
+        };
+        depth += 1;
+        rest = inner.strip_prefix(' ').unwrap_or(inner);
+    }
+    (depth, rest)
+}
+
+/// Splits a table row into its cells, dropping the optional outer pipes.
+/// Escaped pipes (`\|`) stay inside their cell.
+fn split_cells(line: &str) -> Vec<&str> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell_start = 0;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '|' if !escaped => {
+                cells.push(line[cell_start..i].trim());
+                cell_start = i + 1;
+            }
+            _ => {}
         }
+        escaped = c == '\\' && !escaped;
     }
+    cells.push(line[cell_start..].trim());
 
-    fn lex_image(&mut self, line: &str) {
-        let line = &line[1..];
-        self.collector.image();
-        line.split_whitespace().for_each(|word| {
-            self.lex_word(word);
-        });
+    cells
+}
+
+/// Parses a table delimiter row like `|---|:---:|` into column alignments.
+fn delimiter_row(line: &str) -> Option<Vec<Alignment>> {
+    if !line.contains('-') {
+        return None;
     }
+
+    split_cells(line)
+        .iter()
+        .map(|cell| {
+            let left = cell.starts_with(':');
+            let right = cell.ends_with(':');
+            let dashes = cell.trim_matches(':');
+
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+
+            Some(match (left, right) {
+                (true, true) => Alignment::Center,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (false, false) => Alignment::None,
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::Lexer;
-    use crate::MockTokenCollector;
+    use crate::{MockTokenCollector, Span};
 
     #[test]
     fn lex_header() {
@@ -246,25 +540,23 @@ mod tests {
                 "word(bold)",
                 "end_bold",
                 "line_break",
-                "word(regular)",
+                "word(regular )",
                 "begin_bold",
                 "word(bold)",
                 "end_bold",
-                "word(word)",
+                "word( word)",
                 "line_break",
-                "word(and)",
+                "word(and )",
                 "begin_bold",
                 "word(another)",
                 "end_bold",
-                "word(bold)",
-                "word(word)",
+                "word( bold word)",
                 "line_break",
-                "word(a)",
+                "word(a )",
                 "begin_bold",
-                "word(bold)",
-                "word(with)",
-                "word(spaces.)",
+                "word(bold with spaces)",
                 "end_bold",
+                "word(.)",
                 "line_break"
             ]
         );
@@ -281,25 +573,23 @@ mod tests {
         assert_eq!(
             mock.tokens,
             vec![
-                "word(regular)",
+                "word(regular )",
                 "begin_italic",
                 "word(italic)",
                 "end_italic",
-                "word(word)",
+                "word( word)",
                 "line_break",
-                "word(and)",
+                "word(and )",
                 "begin_italic",
                 "word(italic)",
                 "end_italic",
-                "word(bold)",
-                "word(word)",
+                "word( bold word)",
                 "line_break",
-                "word(a)",
+                "word(a )",
                 "begin_italic",
-                "word(italic)",
-                "word(with)",
-                "word(spaces.)",
+                "word(italic with spaces)",
                 "end_italic",
+                "word(.)",
                 "line_break"
             ]
         );
@@ -316,23 +606,20 @@ mod tests {
         assert_eq!(
             mock.tokens,
             vec![
-                "word(a)",
-                "word(regular)",
+                "word(a regular )",
                 "begin_label",
                 "word(Link)",
                 "end_label",
                 "url(https://a.com)",
                 "line_break",
-                "word(and)",
+                "word(and )",
                 "begin_label",
-                "word(Another)",
-                "word(Link)",
+                "word(Another Link)",
                 "end_label",
                 "url(https://b.com)",
-                "word(with)",
-                "word(spaces)",
+                "word( with spaces)",
                 "line_break",
-                "word(a)",
+                "word(a )",
                 "begin_label",
                 "word(Link)",
                 "end_label",
@@ -374,8 +661,7 @@ mod tests {
             vec![
                 "begin_bold",
                 "begin_label",
-                "word(Bold)",
-                "word(Link)",
+                "word(Bold Link)",
                 "end_label",
                 "url(https://a.com)",
                 "end_bold",
@@ -415,8 +701,7 @@ mod tests {
             vec![
                 "begin_italic",
                 "begin_label",
-                "word(Italic)",
-                "word(Link)",
+                "word(Italic Link)",
                 "end_label",
                 "url(https://a.com)",
                 "end_italic",
@@ -449,6 +734,7 @@ mod tests {
         let mut mock = MockTokenCollector::default();
         let mut lexer = Lexer::new(&mut mock);
         lexer.lex("![image](https://www.a.com)");
+        lexer.lex("![a](b) c");
 
         assert_eq!(
             mock.tokens,
@@ -458,6 +744,13 @@ mod tests {
                 "word(image)",
                 "end_label",
                 "url(https://www.a.com)",
+                "line_break",
+                "inline_img",
+                "begin_label",
+                "word(a)",
+                "end_label",
+                "url(b)",
+                "word( c)",
                 "line_break"
             ]
         );
@@ -473,18 +766,17 @@ mod tests {
         assert_eq!(
             mock.tokens,
             vec![
-                "word(regular)",
+                "word(regular )",
                 "begin_inline_code",
                 "word(code)",
                 "end_inline_code",
-                "word(word)",
+                "word( word)",
                 "line_break",
-                "word(a)",
+                "word(a )",
                 "begin_inline_code",
-                "word(code)",
-                "word(with)",
-                "word(spaces.)",
+                "word(code with spaces)",
                 "end_inline_code",
+                "word(.)",
                 "line_break"
             ]
         );
@@ -498,11 +790,10 @@ mod tests {
 
         assert_eq!(
             mock.tokens.join(" "),
-            vec![
-                "word(an)",
+            [
+                "word(an )",
                 "begin_inline_code",
-                "word(inline)",
-                "word(**bold**)",
+                "word(inline **bold**)",
                 "end_inline_code",
                 "line_break"
             ]
@@ -519,14 +810,272 @@ mod tests {
         assert_eq!(
             mock.tokens,
             vec![
+                "begin_italic",
                 "begin_bold",
+                "word(bold italic)",
+                "end_bold",
+                "end_italic",
+                "line_break"
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_code_block() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("```rust\nfn main() {\n    let a  =  1;\n```\nafter");
+        lexer.lex("~~~~\n```\n~~~~");
+
+        assert_eq!(
+            mock.tokens,
+            vec![
+                "begin_code_block(rust)",
+                "code_line(fn main() {)",
+                "code_line(    let a  =  1;)",
+                "end_code_block",
+                "word(after)",
+                "line_break",
+                "begin_code_block()",
+                "code_line(```)",
+                "end_code_block"
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_indented_and_quoted_code_blocks() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("  ```\n  a\n    b\nc\n  ```\n> ```sh\n> >x\n>\n> ```\n> ```\nout");
+
+        assert_eq!(
+            mock.tokens,
+            vec![
+                "line_indent(2)",
+                "begin_code_block()",
+                "code_line(a)",
+                "code_line(  b)",
+                "code_line(c)",
+                "end_code_block",
+                "blockquote(1)",
+                "begin_code_block(sh)",
+                "code_line(>x)",
+                "code_line()",
+                "end_code_block",
+                "blockquote(1)",
+                "begin_code_block()",
+                "end_code_block",
+                "word(out)",
+                "line_break"
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_list() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("- one\n  * *two*\n+ three");
+        lexer.lex("1. first\n10)  tenth");
+        lexer.lex("-not a list\n2.0 is a number");
+
+        assert_eq!(
+            mock.tokens,
+            vec![
+                "unordered_item(\"- \", 0)",
+                "word(one)",
+                "line_break",
+                "unordered_item(\"* \", 2)",
                 "begin_italic",
+                "word(two)",
+                "end_italic",
+                "line_break",
+                "unordered_item(\"+ \", 0)",
+                "word(three)",
+                "line_break",
+                "ordered_item(1, \"1. \", 0)",
+                "word(first)",
+                "line_break",
+                "ordered_item(10, \"10)  \", 0)",
+                "word(tenth)",
+                "line_break",
+                "word(-not a list)",
+                "line_break",
+                "word(2.0 is a number)",
+                "line_break"
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_blockquote() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("> # quoted\n> > - nested\n>\nlazy");
+
+        assert_eq!(
+            mock.tokens,
+            vec![
+                "blockquote(1)",
+                "h1",
+                "word(quoted)",
+                "line_break",
+                "blockquote(2)",
+                "unordered_item(\"- \", 0)",
+                "word(nested)",
+                "line_break",
+                "blockquote(1)",
+                "line_break",
+                "word(lazy)",
+                "line_break"
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_table() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("| Key | Value |\n|:---|---:|\n| `a\\|b` | **1** |\n| c |\nafter");
+
+        assert_eq!(
+            mock.tokens,
+            vec![
+                "begin_table",
+                "table_cell",
+                "word(Key)",
+                "table_cell",
+                "word(Value)",
+                "line_break",
+                "table_alignments([Left, Right])",
+                "line_break",
+                "table_row",
+                "table_cell",
+                "begin_inline_code",
+                "word(a|b)",
+                "end_inline_code",
+                "table_cell",
+                "begin_bold",
+                "word(1)",
+                "end_bold",
+                "line_break",
+                "table_row",
+                "table_cell",
+                "word(c)",
+                "table_cell",
+                "line_break",
+                "word(after)",
+                "line_break"
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_quoted_table() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("> | a |\n> | - |\n> | 1 |\n| 2 |");
+
+        assert_eq!(
+            mock.tokens,
+            vec![
+                "blockquote(1)",
+                "begin_table",
+                "table_cell",
+                "word(a)",
+                "line_break",
+                "table_alignments([None])",
+                "line_break",
+                "blockquote(1)",
+                "table_row",
+                "table_cell",
+                "word(1)",
+                "line_break",
+                "word(| 2 |)",
+                "line_break"
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_rows_like_delimiter_rows() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("| a |\n| - |\n| b |\n| - |");
+
+        assert_eq!(
+            mock.tokens,
+            vec![
+                "begin_table",
+                "table_cell",
+                "word(a)",
+                "line_break",
+                "table_alignments([None])",
+                "line_break",
+                "table_row",
+                "table_cell",
+                "word(b)",
+                "line_break",
+                "table_row",
+                "table_cell",
+                "word(-)",
+                "line_break"
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_punctuation_and_spacing() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("**bold**, (*italic*)  two  spaces and snake_case");
+
+        assert_eq!(
+            mock.tokens,
+            vec![
+                "begin_bold",
                 "word(bold)",
+                "end_bold",
+                "word(, ()",
+                "begin_italic",
                 "word(italic)",
                 "end_italic",
-                "end_bold",
+                "word()  two  spaces and snake_case)",
                 "line_break"
             ]
         );
     }
+
+    #[test]
+    fn lex_spans() {
+        let mut mock = MockTokenCollector::default();
+        let mut lexer = Lexer::new(&mut mock);
+        lexer.lex("# Hé\n- *x* [y](u)");
+        lexer.lex("ok");
+
+        let span = |start, end, line, column| Span {
+            start,
+            end,
+            line,
+            column,
+        };
+        assert_eq!(
+            mock.spans,
+            vec![
+                span(0, 5, 1, 1),
+                span(2, 5, 1, 3),
+                span(5, 6, 1, 5),
+                span(6, 18, 2, 1),
+                span(8, 11, 2, 3),
+                span(9, 10, 2, 4),
+                span(11, 12, 2, 6),
+                span(12, 18, 2, 7),
+                span(13, 14, 2, 8),
+                span(18, 19, 2, 13),
+                span(19, 21, 3, 1),
+                span(21, 22, 3, 3),
+            ]
+        );
+    }
 }
@@ -1,14 +1,67 @@
 mod ast;
 mod builder;
 mod collector;
+mod corpus;
+pub mod html;
+mod inline;
 mod lexer;
+pub mod markdown;
 mod parser;
+pub mod query;
+#[cfg(feature = "serde")]
+mod serialize;
+mod sink;
 
 pub use ast::*;
 pub use builder::Builder;
 pub use collector::TokenCollector;
+pub use corpus::{Corpus, LoadError};
 pub use lexer::Lexer;
-pub use parser::Parser;
+pub use parser::{Event, ParseError, Parser, Recovery, State};
 
 #[cfg(test)]
 pub use collector::tests::MockTokenCollector;
+
+/// Options for `parse_with_options`.
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    pub recovery: Recovery,
+}
+
+/// Parses a markdown document, recovering from unexpected markup by
+/// keeping it as literal text.
+pub fn parse(input: &str) -> Document {
+    parse_with_options(input, &ParseOptions::default())
+        .expect("literal recovery never fails a parse")
+        .document
+}
+
+/// Writes a document as canonical markdown, which parses back to the same
+/// document: see `markdown` for what it can and cannot keep.
+pub fn to_markdown(document: &Document) -> String {
+    markdown::render(document)
+}
+
+/// A parsed document, with the errors that were recovered from on the way.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Parsed {
+    pub document: Document,
+    pub errors: Vec<ParseError>,
+}
+
+/// Parses a markdown document with the given options. Under
+/// `Recovery::Literal` the markup behind each error is kept as text and
+/// the errors come back with the document; under `Recovery::Strict` the
+/// first error fails the parse.
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Parsed, ParseError> {
+    let mut builder = Builder::new();
+    let mut parser = Parser::with_recovery(&mut builder, options.recovery);
+    Lexer::new(&mut parser).lex(input);
+    let errors = parser.errors().to_vec();
+    parser.finish()?;
+
+    Ok(Parsed {
+        document: builder.into_document(),
+        errors,
+    })
+}
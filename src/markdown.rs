@@ -0,0 +1,309 @@
+//! Writes a `Document` back as markdown.
+//!
+//! The markdown is canonical: `#` headings, `**` and `*` emphasis, `-` and
+//! `1.` list markers, fenced code blocks and tables with outer pipes, with
+//! markup characters in text escaped. A document `parse` produces comes back
+//! the same when the markdown is parsed again, as long as its text has no
+//! leading or trailing spaces on a line, no URL has spaces or unbalanced
+//! brackets, no code span is empty or, in a table, has `\|` in it, no
+//! emphasis starts or ends with emphasis that starts or ends with emphasis,
+//! and no list follows a list of the same kind: blank lines between them
+//! do not end a list, so they are read back as one.
+
+use std::fmt::{self, Write};
+use std::io;
+
+use crate::ast::plain_text;
+use crate::sink::write_io;
+use crate::{Alignment, Document, Line, ListItem, Token};
+
+/// Writes a document to a string.
+pub fn render(document: &Document) -> String {
+    let mut out = String::new();
+    render_to(document, &mut out).expect("writing to a String never fails");
+    out
+}
+
+/// Writes a document to a `fmt::Write`, such as a `Formatter`, a block at
+/// a time.
+pub fn render_to(document: &Document, out: &mut impl Write) -> fmt::Result {
+    lines(document, out)
+}
+
+/// Writes a document to an `io::Write`, such as a file or standard output,
+/// a block at a time.
+pub fn write(document: &Document, out: &mut impl io::Write) -> io::Result<()> {
+    write_io(out, |out| lines(document, out))
+}
+
+/// Writes inline tokens as they would be written in the middle of a line,
+/// such as in the label of a link.
+pub fn render_inline(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    let mut inline = Inline::new(&mut out);
+    inline.line_start = false;
+    inline
+        .tokens(tokens)
+        .expect("writing to a String never fails");
+    out
+}
+
+fn lines(lines: &[Line], out: &mut dyn Write) -> fmt::Result {
+    lines.iter().try_for_each(|l| line(l, out))
+}
+
+fn line(line: &Line, out: &mut dyn Write) -> fmt::Result {
+    match line {
+        Line::Header { level, tokens, .. } => {
+            write!(out, "{} ", "#".repeat(level.number().into()))?;
+            Inline::new(out).tokens(tokens)?;
+            out.write_char('\n')
+        }
+        Line::Paragraph(tokens, _) => {
+            Inline::new(out).tokens(tokens)?;
+            out.write_char('\n')
+        }
+        Line::Image { label, url, .. } => {
+            out.write_str("![")?;
+            Inline::new(out).tokens(label)?;
+            writeln!(out, "]({})", url)
+        }
+        Line::CodeBlock { info, content, .. } => {
+            // The fence is longer than any run of its marker in the block,
+            // and made of `~` if the info string has a backtick.
+            let marker = if info.contains('`') { '~' } else { '`' };
+            let longest = content.lines().map(|l| run(l, marker)).max().unwrap_or(0);
+            let fence = marker.to_string().repeat(longest.max(2) + 1);
+
+            out.write_str(&fence)?;
+            if !info.is_empty() {
+                write!(out, "{}", info)?;
+            }
+            out.write_char('\n')?;
+            out.write_str(content)?;
+            if !content.is_empty() && !content.ends_with('\n') {
+                out.write_char('\n')?;
+            }
+            writeln!(out, "{}", fence)
+        }
+        Line::List {
+            ordered,
+            start,
+            loose,
+            items,
+            ..
+        } => {
+            for (i, (n, item)) in (*start..).zip(items).enumerate() {
+                if *loose && i > 0 {
+                    out.write_char('\n')?;
+                }
+                let marker = if *ordered {
+                    format!("{}. ", n)
+                } else {
+                    "- ".to_string()
+                };
+                list_item(&marker, item, out)?;
+            }
+            Ok(())
+        }
+        Line::Blockquote(quoted, _) => {
+            let mut text = String::new();
+            lines(quoted, &mut text)?;
+            for line in text.lines() {
+                if line.is_empty() {
+                    out.write_str(">\n")?;
+                } else {
+                    writeln!(out, "> {}", line)?;
+                }
+            }
+            Ok(())
+        }
+        Line::Table {
+            alignments,
+            header,
+            rows,
+            ..
+        } => {
+            table_row(header, out)?;
+            out.write_char('|')?;
+            for alignment in alignments {
+                out.write_str(match alignment {
+                    Alignment::None => " --- |",
+                    Alignment::Left => " :-- |",
+                    Alignment::Center => " :-: |",
+                    Alignment::Right => " --: |",
+                })?;
+            }
+            out.write_char('\n')?;
+            rows.iter().try_for_each(|row| table_row(row, out))
+        }
+        Line::Blank(_) => out.write_char('\n'),
+    }
+}
+
+/// Writes a list item, with the lines that continue it and the blocks
+/// nested in it indented to line up with its text. Blocks other than lists
+/// come after a blank line, which keeps them apart from the text before.
+fn list_item(marker: &str, item: &ListItem, out: &mut dyn Write) -> fmt::Result {
+    let mut text = String::new();
+    Inline::new(&mut text).tokens(&item.tokens)?;
+    let mut children = String::new();
+    for child in &item.children {
+        if !matches!(child, Line::List { .. }) {
+            children.push('\n');
+        }
+        line(child, &mut children)?;
+    }
+
+    let mut text = text.lines();
+    match text.next() {
+        Some(first) if !first.is_empty() => writeln!(out, "{}{}", marker, first)?,
+        _ => writeln!(out, "{}", marker.trim_end())?,
+    }
+    let indent = " ".repeat(marker.len());
+    for line in text.chain(children.lines()) {
+        if line.is_empty() {
+            out.write_char('\n')?;
+        } else {
+            writeln!(out, "{}{}", indent, line)?;
+        }
+    }
+    Ok(())
+}
+
+fn table_row(cells: &[Vec<Token>], out: &mut dyn Write) -> fmt::Result {
+    out.write_char('|')?;
+    for cell in cells {
+        out.write_char(' ')?;
+        Inline {
+            out,
+            line_start: false,
+            in_table: true,
+        }
+        .tokens(cell)?;
+        out.write_str(" |")?;
+    }
+    out.write_char('\n')
+}
+
+/// The longest run of `marker` in `text`.
+fn run(text: &str, marker: char) -> usize {
+    text.split(|c| c != marker).map(str::len).max().unwrap_or(0)
+}
+
+/// Writes inline tokens, knowing whether the next text starts a line, where
+/// it could be read as block markup.
+struct Inline<'a> {
+    out: &'a mut dyn Write,
+    line_start: bool,
+    in_table: bool,
+}
+
+impl<'a> Inline<'a> {
+    fn new(out: &'a mut dyn Write) -> Self {
+        Inline {
+            out,
+            line_start: true,
+            in_table: false,
+        }
+    }
+
+    fn tokens(&mut self, tokens: &[Token]) -> fmt::Result {
+        for (i, token) in tokens.iter().enumerate() {
+            match (token, tokens.get(i + 1)) {
+                // A `!` right before a link would make it an image.
+                (Token::Regular(text, _), Some(Token::Link { .. })) if text.ends_with('!') => {
+                    self.text(&text[..text.len() - 1])?;
+                    self.out.write_str("\\!")?;
+                    self.line_start = false;
+                }
+                _ => self.token(token)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn token(&mut self, token: &Token) -> fmt::Result {
+        match token {
+            Token::Bold(tokens, _) => self.emphasis("**", tokens),
+            Token::Italic(tokens, _) => self.emphasis("*", tokens),
+            Token::InlineCode(tokens, _) => {
+                let mut code = plain_text(tokens);
+                if self.in_table {
+                    code = code.replace('|', "\\|");
+                }
+                let fence = "`".repeat(run(&code, '`') + 1);
+                let padded = code.starts_with('`')
+                    || code.ends_with('`')
+                    || (code.starts_with(' ') && code.ends_with(' ') && !code.trim().is_empty());
+                self.line_start = false;
+                if padded {
+                    write!(self.out, "{} {} {}", fence, code, fence)
+                } else {
+                    write!(self.out, "{}{}{}", fence, code, fence)
+                }
+            }
+            Token::Regular(text, _) => self.text(text),
+            Token::Link { label, url, .. } => {
+                self.out.write_char('[')?;
+                self.line_start = false;
+                self.tokens(label)?;
+                write!(self.out, "]({})", url)
+            }
+            Token::Image { label, url, .. } => {
+                self.out.write_str("![")?;
+                self.line_start = false;
+                self.tokens(label)?;
+                write!(self.out, "]({})", url)
+            }
+            Token::SoftBreak(_) => {
+                self.line_start = true;
+                self.out.write_char('\n')
+            }
+        }
+    }
+
+    fn emphasis(&mut self, delimiter: &str, tokens: &[Token]) -> fmt::Result {
+        self.out.write_str(delimiter)?;
+        self.line_start = false;
+        // Runs of delimiters at both ends are ambiguous: `***a***` is read as
+        // italic outside bold and `**a**` as bold, so an italic that starts
+        // content ending in emphasis is marked with `_` instead.
+        let both_ends = matches!(tokens.last(), Some(Token::Bold(..) | Token::Italic(..)));
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Italic(tokens, _) if i == 0 && both_ends => self.emphasis("_", tokens)?,
+                token => self.token(token)?,
+            }
+        }
+        self.out.write_str(delimiter)
+    }
+
+    /// Writes text with a backslash before the characters that would be
+    /// read as markup.
+    fn text(&mut self, text: &str) -> fmt::Result {
+        let mut chars = text.char_indices().peekable();
+        if self.line_start {
+            let digits = text.chars().take_while(char::is_ascii_digit).count();
+            // A `|` or `:` starts what could be read as a table's delimiter
+            // row under the line before.
+            if text.starts_with(['#', '>', '-', '+', '~', '!', '|', ':']) {
+                self.out.write_char('\\')?;
+            } else if digits > 0 && text[digits..].starts_with(['.', ')']) {
+                self.out.write_str(&text[..digits])?;
+                self.out.write_char('\\')?;
+                while chars.next_if(|(i, _)| *i < digits).is_some() {}
+            }
+        }
+        for (_, c) in chars {
+            if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') || (c == '|' && self.in_table) {
+                self.out.write_char('\\')?;
+            }
+            self.out.write_char(c)?;
+        }
+        if !text.is_empty() {
+            self.line_start = false;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{Builder, HeaderLevel, TokenCollector};
+use std::fmt;
+
+use crate::{Alignment, Builder, HeaderLevel, Span, TokenCollector};
 
 struct Transition {
     from: State,
@@ -18,17 +20,19 @@ impl From<(State, Event, State, Action)> for Transition {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-enum State {
+/// The state of the parser between events.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum State {
     Start,
     Header,
     Text,
+    Table,
+    CodeBlock,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Event {
     Header,
-    Text,
     StartBold,
     EndBold,
     StartItalic,
@@ -40,6 +44,47 @@ pub enum Event {
     EndLine,
     Word,
     Image,
+    InlineImage,
+    ListItem,
+    Blockquote,
+    StartTable,
+    TableAlignments,
+    TableRow,
+    TableCell,
+    StartCodeBlock,
+    CodeLine,
+    EndCodeBlock,
+}
+
+/// An event that the parser has no transition for in its current state.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseError {
+    pub event: Event,
+    pub state: State,
+    pub span: Span,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unexpected {:?} in {:?} state at line {}, column {}",
+            self.event, self.state, self.span.line, self.span.column
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// What the parser does with an event it has no transition for.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Recovery {
+    /// Record the error and add the markup that produced the event as
+    /// literal text, so that the rest of the document still parses.
+    #[default]
+    Literal,
+    /// Record the error and ignore every event after it.
+    Strict,
 }
 
 type Action = fn(&mut Builder);
@@ -48,86 +93,185 @@ pub struct Parser<'a> {
     state: State,
     transitions: Vec<Transition>,
     builder: &'a mut Builder,
+    recovery: Recovery,
+    errors: Vec<ParseError>,
+    span: Span,
 }
 
 impl<'a> TokenCollector for Parser<'a> {
-    fn h1(&mut self) {
-        self.handle_event(Event::Header);
-        self.builder.set_header_level(HeaderLevel::H1);
+    fn h1(&mut self, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::Header, "#") {
+            self.builder.set_header_level(HeaderLevel::H1);
+        }
     }
 
-    fn h2(&mut self) {
-        self.handle_event(Event::Header);
-        self.builder.set_header_level(HeaderLevel::H2);
+    fn h2(&mut self, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::Header, "##") {
+            self.builder.set_header_level(HeaderLevel::H2);
+        }
     }
 
-    fn h3(&mut self) {
-        self.handle_event(Event::Header);
-        self.builder.set_header_level(HeaderLevel::H3);
+    fn h3(&mut self, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::Header, "###") {
+            self.builder.set_header_level(HeaderLevel::H3);
+        }
     }
 
-    fn h4(&mut self) {
-        self.handle_event(Event::Header);
-        self.builder.set_header_level(HeaderLevel::H4);
+    fn h4(&mut self, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::Header, "####") {
+            self.builder.set_header_level(HeaderLevel::H4);
+        }
     }
 
-    fn h5(&mut self) {
-        self.handle_event(Event::Header);
-        self.builder.set_header_level(HeaderLevel::H5);
+    fn h5(&mut self, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::Header, "#####") {
+            self.builder.set_header_level(HeaderLevel::H5);
+        }
     }
 
-    fn h6(&mut self) {
-        self.handle_event(Event::Header);
-        self.builder.set_header_level(HeaderLevel::H6);
+    fn h6(&mut self, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::Header, "######") {
+            self.builder.set_header_level(HeaderLevel::H6);
+        }
     }
 
-    fn begin_bold(&mut self) {
-        self.handle_event(Event::StartBold);
+    fn begin_bold(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::StartBold, "**");
     }
 
     fn end_bold(&mut self) {
-        self.handle_event(Event::EndBold);
+        self.dispatch(Event::EndBold, "**");
     }
 
-    fn begin_italic(&mut self) {
-        self.handle_event(Event::StartItalic);
+    fn begin_italic(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::StartItalic, "*");
     }
 
     fn end_italic(&mut self) {
-        self.handle_event(Event::EndItalic);
+        self.dispatch(Event::EndItalic, "*");
     }
 
-    fn begin_inline_code(&mut self) {
-        self.handle_event(Event::StartInlineCode);
+    fn begin_inline_code(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::StartInlineCode, "`");
     }
 
     fn end_inline_code(&mut self) {
-        self.handle_event(Event::EndInlineCode);
+        self.dispatch(Event::EndInlineCode, "`");
     }
 
-    fn begin_label(&mut self) {
-        self.handle_event(Event::StartLabel);
+    fn begin_label(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::StartLabel, "[");
     }
 
     fn end_label(&mut self) {
-        self.handle_event(Event::EndLabel);
+        self.dispatch(Event::EndLabel, "]");
     }
 
     fn url(&mut self, url: &str) {
-        self.builder.add_url(url);
+        if !self.stopped() {
+            self.builder.add_url(url);
+        }
+    }
+
+    fn word(&mut self, word: &str, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::Word, word) {
+            self.builder.add_word(word);
+        }
     }
 
-    fn word(&mut self, word: &str) {
-        self.handle_event(Event::Word);
-        self.builder.add_word(word);
+    fn image(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::Image, "!");
     }
 
-    fn image(&mut self) {
-        self.handle_event(Event::Image);
+    fn inline_image(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::InlineImage, "!");
     }
 
-    fn line_break(&mut self) {
-        self.handle_event(Event::EndLine);
+    fn unordered_item(&mut self, marker: &str, indent: usize, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::ListItem, marker) {
+            self.builder.add_list_item(false, 1, indent);
+        }
+    }
+
+    fn ordered_item(&mut self, number: u64, marker: &str, indent: usize, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::ListItem, marker) {
+            self.builder.add_list_item(true, number, indent);
+        }
+    }
+
+    fn blockquote(&mut self, depth: usize, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::Blockquote, &"> ".repeat(depth)) {
+            self.builder.set_quote_depth(depth);
+        }
+    }
+
+    fn line_indent(&mut self, indent: usize) {
+        if !self.stopped() {
+            self.builder.set_line_indent(indent);
+        }
+    }
+
+    fn begin_table(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::StartTable, "");
+    }
+
+    fn table_alignments(&mut self, alignments: &[Alignment]) {
+        if self.dispatch(Event::TableAlignments, "") {
+            self.builder.set_table_alignments(alignments);
+        }
+    }
+
+    fn table_row(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::TableRow, "");
+    }
+
+    fn table_cell(&mut self) {
+        self.dispatch(Event::TableCell, "|");
+    }
+
+    fn begin_code_block(&mut self, info: &str, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::StartCodeBlock, "```") {
+            self.builder.set_code_block_info(info);
+        }
+    }
+
+    fn code_line(&mut self, line: &str, span: Span) {
+        self.set_span(span);
+        if self.dispatch(Event::CodeLine, line) {
+            self.builder.add_code_line(line);
+        }
+    }
+
+    fn end_code_block(&mut self, span: Span) {
+        self.set_span(span);
+        self.dispatch(Event::EndCodeBlock, "```");
+    }
+
+    fn line_break(&mut self, span: Span) {
+        self.set_span(span);
+        if !self.dispatch(Event::EndLine, "") && self.recovery == Recovery::Literal {
+            self.state = State::Start;
+            self.builder.end_line();
+        }
     }
 }
 
@@ -144,41 +288,51 @@ impl<'a> Parser<'a> {
                 .into(),
             (
                 State::Start,
-                Event::Text,
+                Event::StartBold,
                 State::Text,
                 (|b: &mut Builder| {
-                    eprintln!("got text");
-                    b.add_text()
+                    b.add_text();
+                    b.start_bold()
                 }) as Action,
             )
                 .into(),
             (
                 State::Start,
-                Event::StartBold,
+                Event::StartItalic,
                 State::Text,
                 (|b: &mut Builder| {
                     b.add_text();
-                    b.start_bold()
+                    b.start_italic()
                 }) as Action,
             )
                 .into(),
             (
                 State::Start,
-                Event::StartItalic,
+                Event::StartInlineCode,
                 State::Text,
                 (|b: &mut Builder| {
                     b.add_text();
-                    b.start_italic()
+                    b.start_inline_code();
                 }) as Action,
             )
                 .into(),
             (
                 State::Start,
-                Event::StartInlineCode,
+                Event::StartLabel,
                 State::Text,
                 (|b: &mut Builder| {
                     b.add_text();
-                    b.start_inline_code();
+                    b.start_label();
+                }) as Action,
+            )
+                .into(),
+            (
+                State::Start,
+                Event::InlineImage,
+                State::Text,
+                (|b: &mut Builder| {
+                    b.add_text();
+                    b.start_image();
                 }) as Action,
             )
                 .into(),
@@ -203,6 +357,48 @@ impl<'a> Parser<'a> {
                 (|b: &mut Builder| b.blank_line()) as Action,
             )
                 .into(),
+            (
+                State::Start,
+                Event::ListItem,
+                State::Text,
+                (|_: &mut Builder| {}) as Action,
+            )
+                .into(),
+            (
+                State::Start,
+                Event::Blockquote,
+                State::Start,
+                (|_: &mut Builder| {}) as Action,
+            )
+                .into(),
+            (
+                State::Start,
+                Event::StartTable,
+                State::Table,
+                (|b: &mut Builder| b.add_table()) as Action,
+            )
+                .into(),
+            (
+                State::Start,
+                Event::TableAlignments,
+                State::Table,
+                (|_: &mut Builder| {}) as Action,
+            )
+                .into(),
+            (
+                State::Start,
+                Event::TableRow,
+                State::Table,
+                (|b: &mut Builder| b.add_table_row()) as Action,
+            )
+                .into(),
+            (
+                State::Start,
+                Event::StartCodeBlock,
+                State::CodeBlock,
+                (|b: &mut Builder| b.add_code_block()) as Action,
+            )
+                .into(),
             // header transitions
             (
                 State::Header,
@@ -213,33 +409,47 @@ impl<'a> Parser<'a> {
                 .into(),
             (
                 State::Header,
-                Event::Text,
+                Event::StartLabel,
                 State::Text,
-                (|_: &mut Builder| {}) as Action,
+                (|b: &mut Builder| b.start_label()) as Action,
             )
                 .into(),
             (
                 State::Header,
-                Event::StartLabel,
+                Event::InlineImage,
                 State::Text,
-                (|b: &mut Builder| b.start_label()) as Action,
+                (|b: &mut Builder| b.start_image()) as Action,
             )
                 .into(),
             (
                 State::Header,
-                Event::Word,
+                Event::StartBold,
                 State::Text,
-                (|_: &mut Builder| {}) as Action,
+                (|b: &mut Builder| b.start_bold()) as Action,
+            )
+                .into(),
+            (
+                State::Header,
+                Event::StartItalic,
+                State::Text,
+                (|b: &mut Builder| b.start_italic()) as Action,
             )
                 .into(),
-            // text transitions
             (
+                State::Header,
+                Event::StartInlineCode,
                 State::Text,
-                Event::Text,
+                (|b: &mut Builder| b.start_inline_code()) as Action,
+            )
+                .into(),
+            (
+                State::Header,
+                Event::Word,
                 State::Text,
                 (|_: &mut Builder| {}) as Action,
             )
                 .into(),
+            // text transitions
             (
                 State::Text,
                 Event::Word,
@@ -317,23 +527,129 @@ impl<'a> Parser<'a> {
                 (|b: &mut Builder| b.end_label()) as Action,
             )
                 .into(),
+            (
+                State::Text,
+                Event::InlineImage,
+                State::Text,
+                (|b: &mut Builder| b.start_image()) as Action,
+            )
+                .into(),
+            (
+                State::Text,
+                Event::TableCell,
+                State::Text,
+                (|b: &mut Builder| b.add_table_cell()) as Action,
+            )
+                .into(),
+            // table transitions
+            (
+                State::Table,
+                Event::TableCell,
+                State::Text,
+                (|b: &mut Builder| b.add_table_cell()) as Action,
+            )
+                .into(),
+            (
+                State::Table,
+                Event::EndLine,
+                State::Start,
+                (|b: &mut Builder| b.end_line()) as Action,
+            )
+                .into(),
+            // code block transitions
+            (
+                State::CodeBlock,
+                Event::CodeLine,
+                State::CodeBlock,
+                (|_: &mut Builder| {}) as Action,
+            )
+                .into(),
+            (
+                State::CodeBlock,
+                Event::EndCodeBlock,
+                State::Start,
+                (|b: &mut Builder| b.end_code_block()) as Action,
+            )
+                .into(),
         ];
 
         Self {
             transitions,
             state: State::Start,
             builder,
+            recovery: Recovery::default(),
+            errors: Vec::new(),
+            span: Span::default(),
         }
     }
 
-    pub fn handle_event(&mut self, event: Event) {
+    pub fn with_recovery(builder: &'a mut Builder, recovery: Recovery) -> Self {
+        let mut parser = Self::new(builder);
+        parser.recovery = recovery;
+        parser
+    }
+
+    /// Handles an event, or fails without changing anything if there is
+    /// no transition for it from the current state.
+    pub fn handle_event(&mut self, event: Event) -> Result<(), ParseError> {
         let transition = self
             .transitions
             .iter()
             .find(|t| t.from == self.state && t.on == event)
-            .expect("No transition found");
+            .ok_or(ParseError {
+                event,
+                state: self.state,
+                span: self.span,
+            })?;
 
-        self.state = transition.to.clone();
+        self.state = transition.to;
         (transition.action)(self.builder);
+        Ok(())
+    }
+
+    /// The errors recorded so far, in the order they happened.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Fails with the first error if it stopped a strict parse. Errors that
+    /// were recovered from are only available from `errors`.
+    pub fn finish(self) -> Result<(), ParseError> {
+        match (self.recovery, self.errors.into_iter().next()) {
+            (Recovery::Strict, Some(error)) => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+        self.builder.set_span(span);
+    }
+
+    fn stopped(&self) -> bool {
+        self.recovery == Recovery::Strict && !self.errors.is_empty()
+    }
+
+    /// Handles an event from the lexer, recovering from a missing transition
+    /// as the recovery policy says. Under `Recovery::Literal` the event is
+    /// replaced by a word holding `literal`, the markup it came from.
+    ///
+    /// Returns whether the event itself was handled, so that callers only
+    /// pass its data on to the builder if it was.
+    fn dispatch(&mut self, event: Event, literal: &str) -> bool {
+        if self.stopped() {
+            return false;
+        }
+
+        let Err(error) = self.handle_event(event) else {
+            return true;
+        };
+        self.errors.push(error);
+
+        let recovers = self.recovery == Recovery::Literal && !literal.is_empty();
+        if recovers && self.handle_event(Event::Word).is_ok() {
+            self.builder.add_word(literal);
+        }
+        false
     }
 }
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::ops::Range;
+
+/// A query that could not be parsed, with the byte range of the query text
+/// it was found at.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct QueryError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl QueryError {
+    pub(crate) fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// An error about an unknown name, suggesting the candidate closest to
+    /// it if there is one close enough to be a typo.
+    pub(crate) fn unknown<'a>(
+        what: &str,
+        name: &str,
+        candidates: impl IntoIterator<Item = &'a str>,
+        span: Range<usize>,
+    ) -> Self {
+        let message = match did_you_mean(name, candidates) {
+            Some(candidate) => {
+                format!("unknown {} `{}`, did you mean `{}`?", what, name, candidate)
+            }
+            None => format!("unknown {} `{}`", what, name),
+        };
+        QueryError::new(message, span)
+    }
+
+    /// The message, followed by the line of `query` the error is on with a
+    /// caret under the part at fault:
+    ///
+    /// ```text
+    /// unknown node kind `lnk`, did you mean `link`?
+    ///  --> 1:17
+    ///   |
+    /// 1 | SELECT url FROM lnk
+    ///   |                 ^^^
+    /// ```
+    pub fn render(&self, query: &str) -> String {
+        let start = floor_char_boundary(query, self.span.start);
+        let line_start = query[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = query[start..].find('\n').map_or(query.len(), |i| start + i);
+        let line = &query[line_start..line_end];
+        let line_number = query[..start].matches('\n').count() + 1;
+
+        let column = query[line_start..start].chars().count();
+        let end = floor_char_boundary(query, self.span.end.clamp(start, line_end));
+        let width = query[start..end].chars().count().max(1);
+
+        let number = line_number.to_string();
+        let margin = " ".repeat(number.len());
+        format!(
+            "{}\n{}--> {}:{}\n{} |\n{} | {}\n{} | {}{}",
+            self.message,
+            margin,
+            line_number,
+            column + 1,
+            margin,
+            number,
+            line,
+            margin,
+            " ".repeat(column),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.span.start)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// The candidate closest to `name`, ignoring case, if it takes at most a
+/// third as many edits as `name` has characters, and at least one.
+pub(crate) fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between two strings, counting characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{did_you_mean, edit_distance, QueryError};
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(edit_distance("lnk", "link"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(did_you_mean("LNK", ["list", "link"]), Some("link"));
+        assert_eq!(did_you_mean("xyz", ["list", "link"]), None);
+        assert_eq!(did_you_mean("hedaer", ["header"]), Some("header"));
+    }
+
+    #[test]
+    fn render() {
+        let error = QueryError::new("unknown field `uri`", 20..23);
+        assert_eq!(
+            error.render("SELECT link\n  WHERE uri = 'x'"),
+            "unknown field `uri`\n --> 2:9\n  |\n2 |   WHERE uri = 'x'\n  |         ^^^"
+        );
+
+        let error = QueryError::new("expected an expression, found the end of the query", 17..17);
+        assert_eq!(
+            error.render("SELECT link WHERE"),
+            "expected an expression, found the end of the query\n --> 1:18\n  |\n\
+             1 | SELECT link WHERE\n  |                  ^"
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Runs a `Query` over the nodes of a document.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use super::parser::{Aggregate, Axis, CompareOp, Expr, Path, Query, Scope};
+use super::tree::{Kind, Tree};
+use super::{NodeValue, ResultSet, Value};
+use crate::Span;
+
+/// The columns a row is tagged with when a query runs over a corpus, giving
+/// the position of the row's node.
+const TAGS: [&str; 3] = ["file", "line", "column"];
+
+/// Runs a query over the nodes of `tree`. With `tagged`, each row starts
+/// with the `TAGS` of its node, or the `group_tags` of its group.
+pub(crate) fn execute(query: &Query, tree: &Tree, tagged: bool) -> ResultSet {
+    if query.explain {
+        return query.plan();
+    }
+    let grouped = is_grouped(query);
+
+    let matches = (0..tree.len()).filter(|&id| {
+        query.from.matches(tree, id)
+            && query.scopes.iter().all(|scope| in_scope(tree, id, scope))
+            && query
+                .filter
+                .as_ref()
+                .is_none_or(|filter| Context::new(tree, &[id]).eval(filter).is_truthy())
+    });
+
+    let groups: Vec<Vec<usize>> = if grouped {
+        group(tree, matches, &query.group_by)
+    } else {
+        matches.map(|id| vec![id]).collect()
+    };
+
+    // Each row keeps its tags and the values it is ordered by next to its
+    // columns.
+    let mut rows: Vec<(Vec<Value>, Vec<Value>, Vec<Value>)> = groups
+        .iter()
+        .map(|group| {
+            let context = Context::new(tree, group);
+            let eval = |expr| context.eval(expr);
+            let tags = match group.first() {
+                _ if !tagged => Vec::new(),
+                _ if grouped => group_tags(tree, group),
+                Some(&id) => TAGS.iter().map(|tag| tree.field(id, tag)).collect(),
+                None => vec![Value::Null; TAGS.len()],
+            };
+            (
+                tags,
+                query.columns.iter().map(|c| eval(&c.expr)).collect(),
+                query.order_by.iter().map(|o| eval(&o.expr)).collect(),
+            )
+        })
+        .collect();
+
+    // Tags tell where a row was first seen, so they are not compared.
+    if query.distinct {
+        let mut seen = HashSet::new();
+        rows.retain(|(_, row, _)| seen.insert(row.iter().map(Key::of).collect::<Vec<_>>()));
+    }
+
+    rows.sort_by(|(_, _, a), (_, _, b)| {
+        a.iter()
+            .zip(b)
+            .zip(&query.order_by)
+            .map(|((a, b), order)| {
+                let ordering = a.order(b);
+                if order.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let tags = if tagged { &TAGS[..] } else { &[] };
+    ResultSet {
+        columns: tags
+            .iter()
+            .map(|tag| tag.to_string())
+            .chain(query.columns.iter().map(|c| c.name.clone()))
+            .collect(),
+        rows: rows
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|(mut tags, row, _)| {
+                tags.extend(row);
+                tags
+            })
+            .collect(),
+    }
+}
+
+/// The tags of a row made from a group of nodes: the file all of them come
+/// from, if they do, and no line or column.
+fn group_tags(tree: &Tree, group: &[usize]) -> Vec<Value> {
+    let mut files = group.iter().map(|&id| tree.field(id, "file"));
+    let file = match files.next() {
+        Some(first) if files.all(|file| file == first) => first,
+        _ => Value::Null,
+    };
+    vec![file, Value::Null, Value::Null]
+}
+
+/// A value that can be hashed, equal to another key when their values are.
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Null,
+    Bool(bool),
+    Number(u64),
+    Text(String),
+    Node(Kind, String, Span),
+}
+
+impl Key {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Key::Null,
+            Value::Bool(b) => Key::Bool(*b),
+            // Adding zero turns `-0.0` into `0.0`, which it is equal to.
+            Value::Number(n) => Key::Number((n + 0.0).to_bits()),
+            Value::Text(s) => Key::Text(s.clone()),
+            Value::Node(node) => Key::Node(node.kind, node.text.clone(), node.span),
+        }
+    }
+}
+
+/// Whether a query produces a row per group of nodes rather than per node.
+fn is_grouped(query: &Query) -> bool {
+    !query.group_by.is_empty()
+        || query.columns.iter().any(|c| c.expr.contains_aggregate())
+        || query.order_by.iter().any(|o| o.expr.contains_aggregate())
+}
+
+/// Groups nodes by the values of `keys`, in the order each group is first
+/// seen. Without keys, all nodes form one group, even if there are none.
+fn group(tree: &Tree, ids: impl Iterator<Item = usize>, keys: &[Expr]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(Vec<Value>, Vec<usize>)> = Vec::new();
+    if keys.is_empty() {
+        groups.push((Vec::new(), Vec::new()));
+    }
+
+    for id in ids {
+        let context = Context::new(tree, std::slice::from_ref(&id));
+        let key: Vec<Value> = keys.iter().map(|k| context.eval(k)).collect();
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push(id),
+            None => groups.push((key, vec![id])),
+        }
+    }
+
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+fn in_scope(tree: &Tree, id: usize, scope: &Scope) -> bool {
+    tree.sections(id).any(|section| match scope {
+        Scope::Section(title) => tree
+            .text(section.heading)
+            .trim()
+            .eq_ignore_ascii_case(title.trim()),
+        Scope::Under(kind) => kind.matches(tree, section.heading),
+    })
+}
+
+/// The nodes an expression is evaluated for: a single node, or a group of
+/// them whose first node stands in outside aggregates.
+struct Context<'t, 'a> {
+    tree: &'t Tree<'a>,
+    group: &'t [usize],
+}
+
+impl<'t, 'a> Context<'t, 'a> {
+    fn new(tree: &'t Tree<'a>, group: &'t [usize]) -> Self {
+        Context { tree, group }
+    }
+
+    fn eval(&self, expr: &Expr) -> Value {
+        match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Parameter(_) => Value::Null,
+            Expr::Path(path) => self.path(path),
+            Expr::IsKind {
+                path,
+                kind,
+                filter,
+                negated,
+            } => {
+                let found = self.axis(path.axis).into_iter().any(|id| {
+                    kind.matches(self.tree, id)
+                        && filter.as_ref().is_none_or(|filter| {
+                            let context = Context::new(self.tree, std::slice::from_ref(&id));
+                            context.eval(filter).is_truthy()
+                        })
+                });
+                Value::Bool(found != *negated)
+            }
+            Expr::IsNull { expr, negated } => {
+                Value::Bool((self.eval(expr) == Value::Null) != *negated)
+            }
+            Expr::Compare { left, op, right } => {
+                let ordering = self.eval(left).compare(&self.eval(right));
+                Value::Bool(ordering.is_some_and(|ordering| match op {
+                    CompareOp::Eq => ordering == Ordering::Equal,
+                    CompareOp::Ne => ordering != Ordering::Equal,
+                    CompareOp::Lt => ordering == Ordering::Less,
+                    CompareOp::Le => ordering != Ordering::Greater,
+                    CompareOp::Gt => ordering == Ordering::Greater,
+                    CompareOp::Ge => ordering != Ordering::Less,
+                }))
+            }
+            Expr::And(left, right) => {
+                Value::Bool(self.eval(left).is_truthy() && self.eval(right).is_truthy())
+            }
+            Expr::Or(left, right) => {
+                Value::Bool(self.eval(left).is_truthy() || self.eval(right).is_truthy())
+            }
+            Expr::Not(expr) => Value::Bool(!self.eval(expr).is_truthy()),
+            Expr::Aggregate {
+                function,
+                arg,
+                distinct,
+            } => self.aggregate(*function, arg.as_deref(), *distinct),
+            Expr::Call { function, args } => {
+                let args: Vec<Value> = args.iter().map(|arg| self.eval(arg)).collect();
+                function.call(&args)
+            }
+        }
+    }
+
+    fn aggregate(&self, function: Aggregate, arg: Option<&Expr>, distinct: bool) -> Value {
+        let Some(arg) = arg else {
+            return Value::Number(self.group.len() as f64);
+        };
+
+        let mut values: Vec<Value> = Vec::new();
+        let mut seen = HashSet::new();
+        for id in self.group {
+            let value = Context::new(self.tree, std::slice::from_ref(id)).eval(arg);
+            if value != Value::Null && (!distinct || seen.insert(Key::of(&value))) {
+                values.push(value);
+            }
+        }
+
+        let numbers = values.iter().filter_map(|value| match value {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        });
+        match function {
+            Aggregate::Count => Value::Number(values.len() as f64),
+            Aggregate::Sum => numbers
+                .reduce(|a, b| a + b)
+                .map_or(Value::Null, Value::Number),
+            Aggregate::Avg => {
+                let (sum, count) = numbers.fold((0.0, 0), |(sum, count), n| (sum + n, count + 1));
+                if count == 0 {
+                    Value::Null
+                } else {
+                    Value::Number(sum / count as f64)
+                }
+            }
+            Aggregate::Min => values
+                .into_iter()
+                .min_by(|a, b| a.order(b))
+                .unwrap_or(Value::Null),
+            Aggregate::Max => values
+                .into_iter()
+                .max_by(|a, b| a.order(b))
+                .unwrap_or(Value::Null),
+        }
+    }
+
+    /// The nearest node along the path's axis, or the field of it.
+    fn path(&self, path: &Path) -> Value {
+        let Some(&id) = self.axis(path.axis).first() else {
+            return Value::Null;
+        };
+
+        match &path.field {
+            Some(field) => self.tree.field(id, field),
+            None => Value::Node(NodeValue {
+                kind: self.tree.nodes[id].kind,
+                text: self.tree.text(id),
+                span: self.tree.nodes[id].span,
+            }),
+        }
+    }
+
+    /// The nodes along an axis, nearest first.
+    fn axis(&self, axis: Axis) -> Vec<usize> {
+        let tree = self.tree;
+        let Some(&id) = self.group.first() else {
+            return Vec::new();
+        };
+        let node = &tree.nodes[id];
+        let siblings = tree.siblings(id);
+        let position = siblings.iter().position(|s| *s == id).unwrap_or(0);
+
+        let nodes: Vec<usize> = match axis {
+            Axis::Current => return vec![id],
+            Axis::Previous | Axis::PrecedingSibling => {
+                siblings[..position].iter().rev().copied().collect()
+            }
+            Axis::Next | Axis::FollowingSibling => siblings[position + 1..].to_vec(),
+            Axis::Preceding => {
+                let start = tree.source(id).nodes.start;
+                (start..id)
+                    .rev()
+                    .filter(|p| tree.nodes[*p].end <= id)
+                    .collect()
+            }
+            Axis::Following => (node.end..tree.source(id).nodes.end).collect(),
+            Axis::Parent => node.parent.into_iter().collect(),
+            Axis::Child => node.children.clone(),
+            Axis::Descendant => (id + 1..node.end).collect(),
+            Axis::Ancestor => {
+                std::iter::successors(node.parent, |p| tree.nodes[*p].parent).collect()
+            }
+        };
+
+        let mut nodes = nodes.into_iter().filter(|n| tree.is_significant(*n));
+        match axis {
+            Axis::Previous | Axis::Next => nodes.next().into_iter().collect(),
+            _ => nodes.collect(),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Writes queries back as text, and as the logical plan `EXPLAIN` shows.
+
+use std::fmt::{self, Write};
+
+use super::parser::{Aggregate, Axis, CompareOp, Expr, Path, Query, Scope};
+use super::tree::KindTest;
+use super::{ResultSet, Value};
+
+impl Query {
+    /// The steps the query takes, one per line, each indented under the
+    /// step that consumes its rows:
+    ///
+    /// ```text
+    /// Limit 10
+    ///   Sort links DESC
+    ///     Project section, COUNT(*) AS links
+    ///       Aggregate by section
+    ///         Filter NOT url = ''
+    ///           Scan link
+    /// ```
+    pub fn explain(&self) -> String {
+        let mut steps = Vec::new();
+
+        let mut scan = format!("Scan {}", self.from);
+        if let Some(pattern) = &self.source {
+            write!(scan, " in {}", quote(pattern)).expect("writing to a String never fails");
+        }
+        steps.push(scan);
+        for scope in &self.scopes {
+            steps.push(match scope {
+                Scope::Section(title) => {
+                    format!("Scope section {}", quote(title))
+                }
+                Scope::Under(kind) => format!("Scope under {}", kind),
+            });
+        }
+        if let Some(filter) = &self.filter {
+            steps.push(format!("Filter {}", filter));
+        }
+        if !self.group_by.is_empty() {
+            steps.push(format!("Aggregate by {}", list(&self.group_by)));
+        } else if self.columns.iter().any(|c| c.expr.contains_aggregate())
+            || self.order_by.iter().any(|o| o.expr.contains_aggregate())
+        {
+            steps.push("Aggregate".to_string());
+        }
+
+        let columns: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let expr = column.expr.to_string();
+                // A column of the nodes themselves is named after their kind.
+                let node = Expr::Path(Path {
+                    axis: Axis::Current,
+                    field: None,
+                });
+                if expr == column.name || column.expr == node {
+                    column.name.clone()
+                } else {
+                    format!("{} AS {}", expr, column.name)
+                }
+            })
+            .collect();
+        steps.push(format!("Project {}", columns.join(", ")));
+        if self.distinct {
+            steps.push("Distinct".to_string());
+        }
+        if !self.order_by.is_empty() {
+            let keys: Vec<String> = self
+                .order_by
+                .iter()
+                .map(|order| {
+                    let name = self
+                        .columns
+                        .iter()
+                        .find(|column| column.expr == order.expr)
+                        .map_or_else(|| order.expr.to_string(), |column| column.name.clone());
+                    let direction = if order.descending { "DESC" } else { "ASC" };
+                    format!("{} {}", name, direction)
+                })
+                .collect();
+            steps.push(format!("Sort {}", keys.join(", ")));
+        }
+        match (self.limit, self.offset) {
+            (Some(limit), 0) => steps.push(format!("Limit {}", limit)),
+            (Some(limit), offset) => steps.push(format!("Limit {} offset {}", limit, offset)),
+            (None, 0) => {}
+            (None, offset) => steps.push(format!("Offset {}", offset)),
+        }
+
+        let mut plan = String::new();
+        for (depth, step) in steps.iter().rev().enumerate() {
+            writeln!(plan, "{}{}", "  ".repeat(depth), step)
+                .expect("writing to a String never fails");
+        }
+        plan
+    }
+
+    /// The plan as a result, one row per step.
+    pub(crate) fn plan(&self) -> ResultSet {
+        ResultSet {
+            columns: vec!["plan".to_string()],
+            rows: self
+                .explain()
+                .lines()
+                .map(|line| vec![Value::Text(line.to_string())])
+                .collect(),
+        }
+    }
+}
+
+fn list(exprs: &[Expr]) -> String {
+    let exprs: Vec<String> = exprs.iter().map(Expr::to_string).collect();
+    exprs.join(", ")
+}
+
+impl Value {
+    /// The value as a query would write it.
+    fn literal(&self) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Bool(true) => "TRUE".to_string(),
+            Value::Bool(false) => "FALSE".to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Text(text) => quote(text),
+            Value::Node(node) => quote(&node.text),
+        }
+    }
+}
+
+/// Quotes text as a string literal.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+impl fmt::Display for KindTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            Some(level) => write!(f, "h{}", level),
+            None => f.write_str(self.kind.name()),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.axis, &self.field) {
+            (Axis::Current, Some(field)) => f.write_str(field),
+            (axis, Some(field)) => write!(f, "{}.{}", axis.name(), field),
+            (axis, None) => f.write_str(axis.name()),
+        }
+    }
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "<>",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        })
+    }
+}
+
+impl Expr {
+    /// How tightly the expression binds, to know where it needs brackets.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(..) => 1,
+            Expr::And(..) => 2,
+            Expr::Not(_) => 3,
+            Expr::Compare { .. } | Expr::IsKind { .. } | Expr::IsNull { .. } => 4,
+            _ => 5,
+        }
+    }
+
+    fn write_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Literal(value) => f.write_str(&value.literal()),
+            Expr::Parameter(_) => f.write_str("?"),
+            Expr::Path(path) => write!(f, "{}", path),
+            Expr::IsKind {
+                path,
+                kind,
+                filter,
+                negated,
+            } => {
+                write!(
+                    f,
+                    "{} IS {}{}",
+                    path,
+                    if *negated { "NOT " } else { "" },
+                    kind
+                )?;
+                match filter {
+                    Some(filter) => write!(f, "[{}]", filter),
+                    None => Ok(()),
+                }
+            }
+            Expr::IsNull { expr, negated } => {
+                expr.write_operand(f, 5)?;
+                write!(f, " IS {}NULL", if *negated { "NOT " } else { "" })
+            }
+            Expr::Compare { left, op, right } => {
+                left.write_operand(f, 5)?;
+                write!(f, " {} ", op)?;
+                right.write_operand(f, 5)
+            }
+            Expr::And(left, right) => {
+                left.write_operand(f, 2)?;
+                f.write_str(" AND ")?;
+                right.write_operand(f, 3)
+            }
+            Expr::Or(left, right) => {
+                left.write_operand(f, 1)?;
+                f.write_str(" OR ")?;
+                right.write_operand(f, 2)
+            }
+            Expr::Not(expr) => {
+                f.write_str("NOT ")?;
+                expr.write_operand(f, 3)
+            }
+            Expr::Aggregate {
+                function,
+                arg,
+                distinct,
+            } => {
+                write!(f, "{}(", function.name().to_uppercase())?;
+                if *distinct {
+                    f.write_str("DISTINCT ")?;
+                }
+                match arg {
+                    Some(arg) => write!(f, "{})", arg),
+                    None if *function == Aggregate::Count => f.write_str("*)"),
+                    None => f.write_str(")"),
+                }
+            }
+            Expr::Call { function, args } => write!(f, "{}({})", function.name(), list(args)),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Writes a `ResultSet` as JSON, newline-delimited JSON, CSV or an aligned
+//! text table.
+
+use std::fmt::{self, Write};
+use std::io;
+use std::str::FromStr;
+
+use super::{ResultSet, Value};
+use crate::sink::write_io;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    /// An array with one object per row, keyed by column name.
+    Json,
+    /// One JSON object per row, each on its own line.
+    Ndjson,
+    /// A header line with the column names, then one line per row.
+    Csv,
+    /// Columns padded to line up, for reading in a terminal.
+    #[default]
+    Table,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Json, Format::Ndjson, Format::Csv, Format::Table];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Csv => "csv",
+            Format::Table => "table",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "unknown format `{}`, expected one of json, ndjson, csv or table",
+                    s
+                )
+            })
+    }
+}
+
+impl ResultSet {
+    /// Writes the rows in `format` to a string.
+    pub fn render(&self, format: Format) -> String {
+        let mut out = String::new();
+        self.render_to(format, &mut out)
+            .expect("writing to a String never fails");
+        out
+    }
+
+    /// Writes the rows in `format` to `out`.
+    pub fn write(&self, format: Format, out: &mut impl io::Write) -> io::Result<()> {
+        write_io(out, |mut out| self.render_to(format, &mut out))
+    }
+
+    pub fn render_to(&self, format: Format, out: &mut impl Write) -> fmt::Result {
+        match format {
+            Format::Json => {
+                out.write_char('[')?;
+                for (i, row) in self.rows.iter().enumerate() {
+                    out.write_str(if i == 0 { "\n  " } else { ",\n  " })?;
+                    self.write_object(row, out)?;
+                }
+                if !self.rows.is_empty() {
+                    out.write_char('\n')?;
+                }
+                out.write_str("]\n")
+            }
+            Format::Ndjson => {
+                for row in &self.rows {
+                    self.write_object(row, out)?;
+                    out.write_char('\n')?;
+                }
+                Ok(())
+            }
+            Format::Csv => {
+                write_csv_line(self.columns.iter().map(String::as_str), out)?;
+                for row in &self.rows {
+                    let cells: Vec<String> = row.iter().map(Value::to_string).collect();
+                    write_csv_line(cells.iter().map(String::as_str), out)?;
+                }
+                Ok(())
+            }
+            Format::Table => self.write_table(out),
+        }
+    }
+
+    fn write_object(&self, row: &[Value], out: &mut impl Write) -> fmt::Result {
+        out.write_char('{')?;
+        for (i, (column, value)) in self.columns.iter().zip(row).enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write_json_string(column, out)?;
+            out.write_char(':')?;
+            write_json(value, out)?;
+        }
+        out.write_char('}')
+    }
+
+    fn write_table(&self, out: &mut impl Write) -> fmt::Result {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| table_cell(&value.to_string()))
+                    .collect()
+            })
+            .collect();
+
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|column| {
+                cells
+                    .iter()
+                    .map(|row| row[column].chars().count())
+                    .chain([self.columns[column].chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let headers: Vec<String> = self.columns.iter().map(|c| table_cell(c)).collect();
+        write_table_line(&headers, &widths, &[], out)?;
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        write_table_line(&rule, &widths, &[], out)?;
+
+        for (row, values) in cells.iter().zip(&self.rows) {
+            let numeric: Vec<bool> = values
+                .iter()
+                .map(|value| matches!(value, Value::Number(_)))
+                .collect();
+            write_table_line(row, &widths, &numeric, out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes one line of a table, right-aligning the cells marked numeric.
+fn write_table_line(
+    cells: &[String],
+    widths: &[usize],
+    numeric: &[bool],
+    out: &mut impl Write,
+) -> fmt::Result {
+    let mut line = String::new();
+    for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        if i > 0 {
+            line.push_str("  ");
+        }
+        if numeric.get(i).copied().unwrap_or(false) {
+            write!(line, "{:>width$}", cell, width = width)?;
+        } else {
+            write!(line, "{:<width$}", cell, width = width)?;
+        }
+    }
+    writeln!(out, "{}", line.trim_end())
+}
+
+/// Keeps a table cell on one line.
+fn table_cell(text: &str) -> String {
+    text.replace('\n', "\\n")
+}
+
+fn write_json(value: &Value, out: &mut impl Write) -> fmt::Result {
+    match value {
+        Value::Null => out.write_str("null"),
+        Value::Bool(b) => write!(out, "{}", b),
+        Value::Number(n) if n.is_finite() => write!(out, "{}", n),
+        Value::Number(_) => out.write_str("null"),
+        Value::Text(s) => write_json_string(s, out),
+        Value::Node(node) => {
+            out.write_str("{\"kind\":")?;
+            write_json_string(node.kind.name(), out)?;
+            out.write_str(",\"text\":")?;
+            write_json_string(&node.text, out)?;
+            write!(
+                out,
+                ",\"line\":{},\"column\":{}}}",
+                node.span.line, node.span.column
+            )
+        }
+    }
+}
+
+fn write_json_string(s: &str, out: &mut impl Write) -> fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+/// Writes a CSV line, quoting the fields that need it as RFC 4180 says.
+fn write_csv_line<'a>(fields: impl Iterator<Item = &'a str>, out: &mut impl Write) -> fmt::Result {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.write_char(',')?;
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(out, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            out.write_str(field)?;
+        }
+    }
+    out.write_str("\r\n")
+}
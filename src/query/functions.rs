@@ -0,0 +1,307 @@
+//! The scalar functions queries can call, such as `host(url)` or
+//! `contains(text, 'TODO')`, and the registry custom functions are added to.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use regex::Regex;
+
+use super::{Aggregate, Value};
+
+type Body = dyn Fn(&[Value]) -> Value + Send + Sync;
+
+/// A function a query can call, taking a fixed number of arguments.
+#[derive(Clone)]
+pub struct Function {
+    name: String,
+    arity: usize,
+    body: Arc<Body>,
+}
+
+impl Function {
+    pub fn new(
+        name: impl Into<String>,
+        arity: usize,
+        body: impl Fn(&[Value]) -> Value + Send + Sync + 'static,
+    ) -> Self {
+        Function {
+            name: name.into().to_ascii_lowercase(),
+            arity,
+            body: Arc::new(body),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn call(&self, args: &[Value]) -> Value {
+        (self.body)(args)
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Function({}/{})", self.name, self.arity)
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Arc::ptr_eq(&self.body, &other.body)
+    }
+}
+
+/// The functions a query may call, by name. `Functions::default()` holds
+/// the built-in ones:
+///
+/// | function | returns |
+/// |---|---|
+/// | `host(url)` | the host of an absolute URL, without port or user |
+/// | `scheme(url)` | the scheme of a URL, lowercased, as in `https` |
+/// | `path(url)` | the path of a URL, without query or fragment |
+/// | `is_relative(url)` | whether a URL has neither a scheme nor a host |
+/// | `lower(text)` | the text in lowercase |
+/// | `contains(text, part)` | whether `part` occurs in the text |
+/// | `starts_with(text, prefix)` | whether the text begins with `prefix` |
+/// | `ends_with(text, suffix)` | whether the text ends with `suffix` |
+/// | `regex_match(text, pattern)` | whether the regular expression matches |
+/// | `text(node)` | the plain text of a node and everything nested in it |
+/// | `len(text)` | the number of characters in the text |
+///
+/// Nodes passed to a function stand for their text. A function given
+/// `NULL` returns `NULL`, as does `regex_match` given an invalid pattern.
+#[derive(Debug, Clone)]
+pub struct Functions {
+    functions: BTreeMap<String, Function>,
+}
+
+impl Functions {
+    /// A registry without even the built-in functions.
+    pub fn empty() -> Self {
+        Functions {
+            functions: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a function, replacing any other of the same name. Names are
+    /// case insensitive.
+    ///
+    /// # Panics
+    ///
+    /// If the name is that of an aggregate like `count`, which a query
+    /// would never call.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        arity: usize,
+        body: impl Fn(&[Value]) -> Value + Send + Sync + 'static,
+    ) -> &mut Self {
+        let function = Function::new(name, arity, body);
+        assert!(
+            Aggregate::parse(&function.name).is_none(),
+            "cannot register a function named `{}`, the name of an aggregate",
+            function.name
+        );
+        self.functions.insert(function.name.clone(), function);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Function> {
+        self.functions.get(&name.to_ascii_lowercase())
+    }
+
+    /// The registered functions, by name.
+    pub fn iter(&self) -> impl Iterator<Item = &Function> {
+        self.functions.values()
+    }
+}
+
+impl Default for Functions {
+    fn default() -> Self {
+        let mut functions = Functions::empty();
+        functions
+            .register(
+                "host",
+                1,
+                text_fn(|url| url_parts(url).host.map(Value::from)),
+            )
+            .register(
+                "scheme",
+                1,
+                text_fn(|url| {
+                    url_parts(url)
+                        .scheme
+                        .map(|scheme| Value::Text(scheme.to_ascii_lowercase()))
+                }),
+            )
+            .register("path", 1, text_fn(|url| Some(url_parts(url).path.into())))
+            .register(
+                "is_relative",
+                1,
+                text_fn(|url| {
+                    let parts = url_parts(url);
+                    Some(Value::Bool(parts.scheme.is_none() && parts.host.is_none()))
+                }),
+            )
+            .register(
+                "lower",
+                1,
+                text_fn(|text| Some(Value::Text(text.to_lowercase()))),
+            )
+            .register("text", 1, text_fn(|text| Some(text.into())))
+            .register(
+                "len",
+                1,
+                text_fn(|text| Some(Value::Number(text.chars().count() as f64))),
+            )
+            .register(
+                "contains",
+                2,
+                texts_fn(|text, part| Some(Value::Bool(text.contains(part)))),
+            )
+            .register(
+                "starts_with",
+                2,
+                texts_fn(|text, prefix| Some(Value::Bool(text.starts_with(prefix)))),
+            )
+            .register(
+                "ends_with",
+                2,
+                texts_fn(|text, suffix| Some(Value::Bool(text.ends_with(suffix)))),
+            );
+
+        // Queries usually match every node against the same pattern, so the
+        // last one compiled is kept.
+        let last: Mutex<Option<Regex>> = Mutex::new(None);
+        functions.register(
+            "regex_match",
+            2,
+            texts_fn(move |text, pattern| {
+                let mut last = last.lock().unwrap_or_else(|e| e.into_inner());
+                if last.as_ref().is_none_or(|regex| regex.as_str() != pattern) {
+                    *last = Some(Regex::new(pattern).ok()?);
+                }
+                Some(Value::Bool(last.as_ref()?.is_match(text)))
+            }),
+        );
+        functions
+    }
+}
+
+/// A function of one text argument, returning `Null` for `Null` or for
+/// `None`.
+fn text_fn(
+    f: impl Fn(&str) -> Option<Value> + Send + Sync + 'static,
+) -> impl Fn(&[Value]) -> Value + Send + Sync + 'static {
+    move |args| {
+        argument(&args[0])
+            .as_deref()
+            .and_then(&f)
+            .unwrap_or(Value::Null)
+    }
+}
+
+/// A function of two text arguments, returning `Null` if either is `Null`
+/// or for `None`.
+fn texts_fn(
+    f: impl Fn(&str, &str) -> Option<Value> + Send + Sync + 'static,
+) -> impl Fn(&[Value]) -> Value + Send + Sync + 'static {
+    move |args| match (argument(&args[0]), argument(&args[1])) {
+        (Some(a), Some(b)) => f(&a, &b).unwrap_or(Value::Null),
+        _ => Value::Null,
+    }
+}
+
+/// A value as functions take it: `Null` stays `Null`, nodes stand for their
+/// text and other values are written out.
+fn argument(value: &Value) -> Option<Cow<'_, str>> {
+    match value {
+        Value::Null => None,
+        Value::Text(s) => Some(s.into()),
+        Value::Node(node) => Some((&node.text).into()),
+        value => Some(value.to_string().into()),
+    }
+}
+
+struct UrlParts<'a> {
+    scheme: Option<&'a str>,
+    host: Option<&'a str>,
+    path: &'a str,
+}
+
+/// Splits a URL as RFC 3986 does, without validating it.
+fn url_parts(url: &str) -> UrlParts<'_> {
+    let url = url.trim();
+    let url = &url[..url.find(['?', '#']).unwrap_or(url.len())];
+
+    let scheme_end = url.find(':').filter(|&end| {
+        let scheme = &url[..end];
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    let (scheme, rest) = match scheme_end {
+        Some(end) => (Some(&url[..end]), &url[end + 1..]),
+        None => (None, url),
+    };
+
+    let Some(rest) = rest.strip_prefix("//") else {
+        return UrlParts {
+            scheme,
+            host: None,
+            path: rest,
+        };
+    };
+
+    let authority_end = rest.find('/').unwrap_or(rest.len());
+    let authority = &rest[..authority_end];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    // A port follows the last colon, unless it is inside an IPv6 address.
+    let host = match host.rfind(':') {
+        Some(colon) if !host[colon..].contains(']') => &host[..colon],
+        _ => host,
+    };
+
+    UrlParts {
+        scheme,
+        host: Some(host),
+        path: &rest[authority_end..],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::url_parts;
+
+    fn parts(url: &str) -> (Option<&str>, Option<&str>, &str) {
+        let parts = url_parts(url);
+        (parts.scheme, parts.host, parts.path)
+    }
+
+    #[test]
+    fn split_urls() {
+        assert_eq!(
+            parts("https://a:b@host:8080/p/q?x#y"),
+            (Some("https"), Some("host"), "/p/q")
+        );
+        assert_eq!(
+            parts("http://[::1]:80/"),
+            (Some("http"), Some("[::1]"), "/")
+        );
+        assert_eq!(parts("http://[::1]"), (Some("http"), Some("[::1]"), ""));
+        assert_eq!(parts("mailto:a@b.c"), (Some("mailto"), None, "a@b.c"));
+        assert_eq!(parts("//cdn/x"), (None, Some("cdn"), "/x"));
+        assert_eq!(parts("docs/a:b.md#top"), (None, None, "docs/a:b.md"));
+        assert_eq!(parts("#top"), (None, None, ""));
+    }
+}
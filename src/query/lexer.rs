@@ -0,0 +1,194 @@
+//! Splits query text into identifiers, literals and symbols, keeping the
+//! byte range each came from for error messages.
+
+use std::ops::Range;
+
+use super::QueryError;
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum TokenKind {
+    /// A keyword or name. Keywords are recognised by the parser, case
+    /// insensitively.
+    Ident(String),
+    String(String),
+    Number(f64),
+    /// A named placeholder, `:name`, without the colon.
+    Parameter(String),
+    Symbol(&'static str),
+    End,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) span: Range<usize>,
+}
+
+/// Symbols, longest first so that `<=` is not read as `<` then `=`.
+const SYMBOLS: [&str; 15] = [
+    "!=", "<>", "<=", ">=", "=", "<", ">", ",", ".", "*", "(", ")", "[", "]", "?",
+];
+
+/// Tokenizes `text`, ending with a `TokenKind::End` token.
+pub(crate) fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let kind = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&(_, c)) = chars.peek().filter(|(_, c)| is_ident_char(*c)) {
+                    ident.push(c);
+                    chars.next();
+                }
+                TokenKind::Ident(ident)
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&(_, c)) = chars
+                    .peek()
+                    .filter(|(_, c)| c.is_ascii_digit() || *c == '.')
+                {
+                    number.push(c);
+                    chars.next();
+                }
+                let end = start + number.len();
+                let value = number.parse().map_err(|_| {
+                    QueryError::new(format!("invalid number `{}`", number), start..end)
+                })?;
+                TokenKind::Number(value)
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        // A doubled quote stands for the quote itself.
+                        Some((_, q)) if q == c && chars.peek().is_some_and(|(_, n)| *n == c) => {
+                            string.push(c);
+                            chars.next();
+                        }
+                        Some((_, q)) if q == c => break,
+                        Some((_, q)) => string.push(q),
+                        None => {
+                            return Err(QueryError::new("unterminated string", start..text.len()))
+                        }
+                    }
+                }
+                TokenKind::String(string)
+            }
+            ':' => {
+                chars.next();
+                let mut name = String::new();
+                while let Some(&(_, c)) = chars.peek().filter(|(_, c)| is_ident_char(*c)) {
+                    name.push(c);
+                    chars.next();
+                }
+                if name.is_empty() {
+                    return Err(QueryError::new(
+                        "expected a parameter name after `:`",
+                        start..start + 1,
+                    ));
+                }
+                TokenKind::Parameter(name)
+            }
+            _ => {
+                let symbol = SYMBOLS
+                    .into_iter()
+                    .find(|symbol| text[start..].starts_with(symbol))
+                    .ok_or_else(|| {
+                        QueryError::new(
+                            format!("unexpected character `{}`", c),
+                            start..start + c.len_utf8(),
+                        )
+                    })?;
+                for _ in 0..symbol.len() {
+                    chars.next();
+                }
+                TokenKind::Symbol(symbol)
+            }
+        };
+
+        let end = chars.peek().map_or(text.len(), |(i, _)| *i);
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        span: text.len()..text.len(),
+    });
+    Ok(tokens)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, TokenKind::*};
+
+    #[test]
+    fn tokens() {
+        let kinds: Vec<_> = tokenize(
+            "SELECT url, h2.text FROM link WHERE level >= 2.5 AND x <> 'it''s' OR ? = :a_1",
+        )
+        .unwrap()
+        .into_iter()
+        .map(|token| token.kind)
+        .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                Ident("SELECT".into()),
+                Ident("url".into()),
+                Symbol(","),
+                Ident("h2".into()),
+                Symbol("."),
+                Ident("text".into()),
+                Ident("FROM".into()),
+                Ident("link".into()),
+                Ident("WHERE".into()),
+                Ident("level".into()),
+                Symbol(">="),
+                Number(2.5),
+                Ident("AND".into()),
+                Ident("x".into()),
+                Symbol("<>"),
+                String("it's".into()),
+                Ident("OR".into()),
+                Symbol("?"),
+                Symbol("="),
+                Parameter("a_1".into()),
+                End
+            ]
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let spans: Vec<_> = tokenize("a  \"é\" !=")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.span)
+            .collect();
+
+        assert_eq!(spans, vec![0..1, 3..7, 8..10, 10..10]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(tokenize("a 'open").unwrap_err().span, 2..7);
+        assert_eq!(tokenize("a ; b").unwrap_err().span, 2..3);
+        assert_eq!(tokenize("a = : b").unwrap_err().span, 4..5);
+    }
+}
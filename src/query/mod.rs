@@ -0,0 +1,194 @@
+//! A SQL-like query language over the nodes of a `Document`.
+//!
+//! ```text
+//! SELECT url FROM image
+//! SELECT link WHERE prev IS bold
+//! SELECT level, text FROM header WHERE level <= 2
+//! SELECT url FROM link WITHIN SECTION 'Installation'
+//! SELECT text FROM link WHERE host(url) = 'github.com' OR is_relative(url)
+//! SELECT section, COUNT(*) AS links FROM link GROUP BY section ORDER BY links DESC
+//! SELECT url FROM link IN 'docs/**/*.md'
+//! SELECT text FROM cell WHERE column_header = 'Version'
+//! ```
+//!
+//! A query selects every node of the `FROM` kind for which the `WHERE`
+//! condition holds, and produces one row of columns per node. Columns and
+//! conditions refer to the fields of the node (`url`, `text`, `level`...),
+//! or to its neighbours through an axis such as `prev` or `next`, and can
+//! call the functions of a `Functions` registry. A table `cell` has the
+//! `index` of its column, from 1, and the `column_header`, the text of the
+//! cell heading that column.
+//!
+//! Selectors such as `bold > link` are a shorter way to write queries that
+//! only pick out nodes; see `Query::parse_selector`.
+//!
+//! A `QueryError` points at the part of the query at fault, and
+//! `QueryError::render` shows it under the query text. `EXPLAIN SELECT ...`
+//! produces the steps a query would take instead of its results.
+//!
+//! `Query::compile` parses a query once to run it many times, binding
+//! values to its `?` and `:name` placeholders on each run.
+//!
+//! A query over a `Corpus` runs over all of its documents at once, and each
+//! row starts with the `file`, `line` and `column` of the node it was
+//! produced for. A row made from a group of nodes has no line or column,
+//! and only has a file if all of its nodes come from the same one.
+
+mod error;
+mod eval;
+mod explain;
+mod format;
+mod functions;
+mod lexer;
+mod params;
+mod parser;
+mod selector;
+mod tree;
+
+use std::cmp::Ordering;
+use std::fmt;
+
+pub use error::QueryError;
+pub use format::Format;
+pub use functions::{Function, Functions};
+pub use params::{CompiledQuery, Params, Placeholder};
+pub use parser::{Aggregate, Axis, Column, CompareOp, Expr, Order, Path, Query, Scope};
+pub use tree::{Kind, KindTest, COMMON_FIELDS};
+
+use crate::{Corpus, Document, Span};
+use tree::Tree;
+
+/// Parses `query` and runs it over `document`.
+pub fn query(document: &Document, query: &str) -> Result<ResultSet, QueryError> {
+    Ok(Query::parse(query)?.execute(document))
+}
+
+/// Parses `query` and runs it over the documents of `corpus`.
+pub fn query_corpus(corpus: &Corpus, query: &str) -> Result<ResultSet, QueryError> {
+    Ok(Query::parse(query)?.execute_corpus(corpus))
+}
+
+impl Query {
+    pub fn execute(&self, document: &Document) -> ResultSet {
+        eval::execute(self, &Tree::new([(None, document)]), false)
+    }
+
+    /// Runs the query over the documents of `corpus` that match its `IN`
+    /// pattern, or over all of them. An invalid pattern matches none.
+    pub fn execute_corpus(&self, corpus: &Corpus) -> ResultSet {
+        let documents: Vec<(Option<&str>, &Document)> = match &self.source {
+            Some(pattern) => match corpus.matching(pattern) {
+                Ok(documents) => documents.map(|(path, d)| (Some(path), d)).collect(),
+                Err(_) => Vec::new(),
+            },
+            None => corpus.iter().map(|(path, d)| (Some(path), d)).collect(),
+        };
+        eval::execute(self, &Tree::new(documents), true)
+    }
+}
+
+/// The rows a query produced, one value per column.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+    Node(NodeValue),
+}
+
+/// A selected node, summarised by its kind, plain text and position.
+#[derive(Debug, PartialEq, Clone)]
+pub struct NodeValue {
+    pub kind: Kind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Value {
+    /// Whether the value counts as true in a condition.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0,
+            Value::Text(s) => !s.is_empty(),
+            Value::Node(_) => true,
+        }
+    }
+
+    /// Orders two values of the same type. Nodes compare by their text, and
+    /// values of different types, or `Null`, do not compare at all.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            _ => Some(self.as_text()?.cmp(other.as_text()?)),
+        }
+    }
+
+    /// A total order for sorting: `Null` first, then booleans, numbers and
+    /// text, each ordered by `compare`.
+    pub(crate) fn order(&self, other: &Value) -> Ordering {
+        let rank = |value: &Value| match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::Text(_) | Value::Node(_) => 3,
+        };
+
+        rank(self)
+            .cmp(&rank(other))
+            .then_with(|| self.compare(other).unwrap_or(Ordering::Equal))
+    }
+
+    fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(s) => Some(s),
+            Value::Node(node) => Some(&node.text),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Text(text.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Text(text)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => Ok(()),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Node(node) => write!(f, "{}", node.text),
+        }
+    }
+}
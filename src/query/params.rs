@@ -0,0 +1,191 @@
+//! Queries parsed once and run many times, with values bound to their
+//! placeholders on each run.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::parser::{parse, Expr, Placeholders};
+use super::{Functions, Query, QueryError, ResultSet, Value};
+use crate::{Corpus, Document};
+
+/// A placeholder in a query: the n-th `?`, counting from 1, or a `:name`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Placeholder {
+    Position(usize),
+    Name(String),
+}
+
+impl fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Placeholder::Position(n) => write!(f, "placeholder {}", n),
+            Placeholder::Name(name) => write!(f, "`:{}`", name),
+        }
+    }
+}
+
+/// Values for the placeholders of a `CompiledQuery`: one per `?`, in order,
+/// and one per `:name`. Values are data, and never parsed as query text.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Params {
+    positional: Vec<Value>,
+    named: BTreeMap<String, Value>,
+}
+
+impl Params {
+    pub fn new() -> Self {
+        Params::default()
+    }
+
+    /// Binds the next `?`.
+    pub fn value(mut self, value: impl Into<Value>) -> Self {
+        self.positional.push(value.into());
+        self
+    }
+
+    /// Binds every `:name`.
+    pub fn named(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.named.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// A parsed query that can run over any number of documents, binding new
+/// values to its placeholders each time.
+///
+/// ```
+/// use md_parser::query::{Params, Query};
+///
+/// let query = Query::compile("SELECT url FROM link WHERE host(url) = :host").unwrap();
+/// for text in ["[a](https://a.com/x)", "[b](https://b.com)"] {
+///     let document = md_parser::parse(text);
+///     let result = query.execute(&document, &Params::new().named("host", "a.com"));
+///     println!("{:?}", result.unwrap().rows);
+/// }
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompiledQuery {
+    query: Query,
+    placeholders: Placeholders,
+    text_len: usize,
+}
+
+impl Query {
+    /// Parses a query that may contain placeholders and call the built-in
+    /// functions.
+    pub fn compile(text: &str) -> Result<CompiledQuery, QueryError> {
+        Query::compile_with(text, &Functions::default())
+    }
+
+    /// Parses a query that may contain placeholders and call the functions
+    /// in `functions`.
+    pub fn compile_with(text: &str, functions: &Functions) -> Result<CompiledQuery, QueryError> {
+        let (query, placeholders) = parse(text, functions)?;
+        Ok(CompiledQuery {
+            query,
+            placeholders,
+            text_len: text.len(),
+        })
+    }
+}
+
+impl CompiledQuery {
+    /// The placeholders of the query, each listed once, in the order they
+    /// first appear.
+    pub fn placeholders(&self) -> impl Iterator<Item = &Placeholder> {
+        self.placeholders.iter().map(|(placeholder, _)| placeholder)
+    }
+
+    /// The query with `params` in place of its placeholders. Every
+    /// placeholder must be bound, and every value must have a placeholder.
+    pub fn bind(&self, params: &Params) -> Result<Query, QueryError> {
+        let positions = self
+            .placeholders()
+            .filter(|p| matches!(p, Placeholder::Position(_)))
+            .count();
+        if params.positional.len() > positions {
+            return Err(QueryError::new(
+                format!(
+                    "{} values given for {} `?` placeholder{}",
+                    params.positional.len(),
+                    positions,
+                    if positions == 1 { "" } else { "s" }
+                ),
+                0..self.text_len,
+            ));
+        }
+        if let Some(name) = params.named.keys().find(|name| {
+            !self
+                .placeholders()
+                .any(|p| matches!(p, Placeholder::Name(n) if n == *name))
+        }) {
+            return Err(QueryError::new(
+                format!("there is no placeholder `:{}`", name),
+                0..self.text_len,
+            ));
+        }
+
+        let values = self
+            .placeholders
+            .iter()
+            .map(|(placeholder, span)| {
+                let value = match placeholder {
+                    Placeholder::Position(n) => params.positional.get(n - 1),
+                    Placeholder::Name(name) => params.named.get(name),
+                };
+                value.cloned().ok_or_else(|| {
+                    QueryError::new(format!("no value for {}", placeholder), span.clone())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut query = self.query.clone();
+        let exprs = query
+            .columns
+            .iter_mut()
+            .map(|column| &mut column.expr)
+            .chain(query.filter.iter_mut())
+            .chain(query.group_by.iter_mut())
+            .chain(query.order_by.iter_mut().map(|order| &mut order.expr));
+        for expr in exprs {
+            bind(expr, &values);
+        }
+        Ok(query)
+    }
+
+    pub fn execute(&self, document: &Document, params: &Params) -> Result<ResultSet, QueryError> {
+        Ok(self.bind(params)?.execute(document))
+    }
+
+    pub fn execute_corpus(
+        &self,
+        corpus: &Corpus,
+        params: &Params,
+    ) -> Result<ResultSet, QueryError> {
+        Ok(self.bind(params)?.execute_corpus(corpus))
+    }
+}
+
+/// Replaces the parameters in `expr` with their values.
+fn bind(expr: &mut Expr, values: &[Value]) {
+    match expr {
+        Expr::Parameter(slot) => *expr = Expr::Literal(values[*slot].clone()),
+        Expr::IsNull { expr, .. } | Expr::Not(expr) => bind(expr, values),
+        Expr::Compare { left, right, .. } | Expr::And(left, right) | Expr::Or(left, right) => {
+            bind(left, values);
+            bind(right, values);
+        }
+        Expr::Aggregate { arg, .. } => {
+            if let Some(arg) = arg {
+                bind(arg, values);
+            }
+        }
+        Expr::Call { args, .. } => args.iter_mut().for_each(|arg| bind(arg, values)),
+        Expr::IsKind { filter, .. } => {
+            if let Some(filter) = filter {
+                bind(filter, values);
+            }
+        }
+        Expr::Literal(_) | Expr::Path(_) => {}
+    }
+}
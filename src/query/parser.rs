@@ -0,0 +1,978 @@
+//! Parses query text into a `Query`.
+//!
+//! ```text
+//! query   = [EXPLAIN] SELECT [DISTINCT] columns [FROM kind] [IN pattern] scope*
+//!           [WHERE expr]
+//!           [GROUP BY expr ("," expr)*] [ORDER BY order ("," order)*]
+//!           [LIMIT number [OFFSET number]]
+//! scope   = WITHIN SECTION string | UNDER heading
+//! order   = (column | expr) [ASC | DESC]
+//! columns = "*" | column ("," column)*
+//! column  = expr [AS name]
+//! expr    = and (OR and)*
+//! and     = not (AND not)*
+//! not     = NOT not | compare
+//! compare = primary [op primary | IS [NOT] (NULL | kind ["[" expr "]"])]
+//! primary = literal | "?" | ":" name | "(" expr ")" | call | path
+//! call    = aggregate "(" ("*" | [DISTINCT] expr) ")"
+//!         | function "(" [expr ("," expr)*] ")"
+//! path    = axis ["." field] | field
+//! ```
+//!
+//! When `FROM` is left out, the first column names the kind of node to
+//! select, as in `SELECT link WHERE prev IS bold`.
+//!
+//! `IN 'docs/**/*.md'` limits a query over a `Corpus` to the documents whose
+//! path matches the glob pattern.
+//!
+//! An axis (`prev`, `following`, `ancestor`...) leads from the current
+//! node to others. `axis IS kind` holds if any node along the axis is of
+//! that kind, while `axis` and `axis.field` take the nearest one. With a
+//! condition in brackets, as in `ancestor IS paragraph[prev IS h2]`, the
+//! node of that kind must also meet the condition.
+//!
+//! A query with `GROUP BY` or an aggregate such as `COUNT(*)` produces one
+//! row per group of nodes instead of one per node. Other columns take their
+//! value from the first node of the group. `ORDER BY` and `GROUP BY` can
+//! name a column by its alias or its position.
+//!
+//! Other functions, such as `host(url)`, come from a `Functions` registry
+//! and are resolved as the query is parsed.
+//!
+//! `EXPLAIN` makes the query produce its logical plan rather than run.
+//!
+//! The placeholders `?` and `:name` stand for values bound when a
+//! `CompiledQuery` runs.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::error::did_you_mean;
+use super::lexer::{tokenize, Token, TokenKind};
+use super::tree::{is_field, Kind, KindTest, COMMON_FIELDS};
+use super::{Function, Functions, Placeholder, QueryError, Value};
+
+/// A parsed query.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Query {
+    pub columns: Vec<Column>,
+    pub from: KindTest,
+    /// The glob pattern of `IN`, which the paths of documents in a corpus
+    /// must match.
+    pub source: Option<String>,
+    pub scopes: Vec<Scope>,
+    pub filter: Option<Expr>,
+    pub distinct: bool,
+    pub group_by: Vec<Expr>,
+    pub order_by: Vec<Order>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+    /// Whether the query produces its plan instead of its results.
+    pub explain: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Order {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+/// Limits a query to the nodes in some sections of the document, where a
+/// section runs from a heading to the next heading of the same or a higher
+/// level.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Scope {
+    /// `WITHIN SECTION 'title'`: sections whose heading has this text,
+    /// ignoring case.
+    Section(String),
+    /// `UNDER h2`: sections of headings of this kind.
+    Under(KindTest),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Column {
+    pub name: String,
+    pub expr: Expr,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Literal(Value),
+    /// A placeholder, by the number of its slot in the bound values. It is
+    /// `NULL` until bound.
+    Parameter(usize),
+    Path(Path),
+    /// `path IS [NOT] kind[filter]`, true if any node on the path is of
+    /// the kind and meets the filter.
+    IsKind {
+        path: Path,
+        kind: KindTest,
+        filter: Option<Box<Expr>>,
+        negated: bool,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    Compare {
+        left: Box<Expr>,
+        op: CompareOp,
+        right: Box<Expr>,
+    },
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// An aggregate over a group of nodes. `COUNT(*)` has no argument.
+    Aggregate {
+        function: Aggregate,
+        arg: Option<Box<Expr>>,
+        distinct: bool,
+    },
+    Call {
+        function: Function,
+        args: Vec<Expr>,
+    },
+}
+
+impl Expr {
+    pub fn contains_aggregate(&self) -> bool {
+        match self {
+            Expr::Aggregate { .. } => true,
+            Expr::IsNull { expr, .. } | Expr::Not(expr) => expr.contains_aggregate(),
+            Expr::Compare { left, right, .. } => {
+                left.contains_aggregate() || right.contains_aggregate()
+            }
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.contains_aggregate() || right.contains_aggregate()
+            }
+            Expr::Call { args, .. } => args.iter().any(Expr::contains_aggregate),
+            Expr::Literal(_) | Expr::Parameter(_) | Expr::Path(_) | Expr::IsKind { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub const ALL: [Aggregate; 5] = [
+        Aggregate::Count,
+        Aggregate::Sum,
+        Aggregate::Avg,
+        Aggregate::Min,
+        Aggregate::Max,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+
+    pub(super) fn parse(name: &str) -> Option<Self> {
+        Aggregate::ALL
+            .into_iter()
+            .find(|function| function.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// The nodes reached from the current node along an axis, or a field of
+/// the nearest of them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Path {
+    pub axis: Axis,
+    pub field: Option<String>,
+}
+
+/// A direction to move in from the current node. Every axis but `self`
+/// skips blank lines, soft breaks and whitespace-only text.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Axis {
+    /// The current node itself.
+    Current,
+    /// The closest sibling before the current node.
+    Previous,
+    /// The closest sibling after the current node.
+    Next,
+    /// All siblings before the current node, closest first.
+    PrecedingSibling,
+    /// All siblings after the current node, closest first.
+    FollowingSibling,
+    /// All nodes before the current node in document order, except its
+    /// ancestors, closest first.
+    Preceding,
+    /// All nodes after the current node in document order, except its
+    /// descendants, closest first.
+    Following,
+    Parent,
+    Child,
+    /// The nodes nested in the current node, in document order.
+    Descendant,
+    /// The nodes the current node is nested in, closest first.
+    Ancestor,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 11] = [
+        Axis::Current,
+        Axis::Previous,
+        Axis::Next,
+        Axis::PrecedingSibling,
+        Axis::FollowingSibling,
+        Axis::Preceding,
+        Axis::Following,
+        Axis::Parent,
+        Axis::Child,
+        Axis::Descendant,
+        Axis::Ancestor,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Axis::Current => "self",
+            Axis::Previous => "prev",
+            Axis::Next => "next",
+            Axis::PrecedingSibling => "preceding_sibling",
+            Axis::FollowingSibling => "following_sibling",
+            Axis::Preceding => "preceding",
+            Axis::Following => "following",
+            Axis::Parent => "parent",
+            Axis::Child => "child",
+            Axis::Descendant => "descendant",
+            Axis::Ancestor => "ancestor",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Axis::ALL
+            .into_iter()
+            .find(|axis| axis.name().eq_ignore_ascii_case(name))
+    }
+}
+
+const KEYWORDS: [&str; 23] = [
+    "explain", "select", "distinct", "from", "in", "within", "under", "where", "group", "order",
+    "by", "asc", "desc", "limit", "offset", "and", "or", "not", "is", "null", "as", "true",
+    "false",
+];
+
+impl Query {
+    /// Parses a query that may call the built-in functions.
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        Query::parse_with(text, &Functions::default())
+    }
+
+    /// Parses a query that may call the functions in `functions`.
+    pub fn parse_with(text: &str, functions: &Functions) -> Result<Self, QueryError> {
+        let (query, placeholders) = parse(text, functions)?;
+        match placeholders.first() {
+            Some((_, span)) => Err(QueryError::new(
+                "placeholders are only allowed in compiled queries",
+                span.clone(),
+            )),
+            None => Ok(query),
+        }
+    }
+}
+
+/// A placeholder and where it is in the query text, by slot.
+pub(crate) type Placeholders = Vec<(Placeholder, Range<usize>)>;
+
+/// Parses a query and the placeholders in it.
+pub(crate) fn parse(
+    text: &str,
+    functions: &Functions,
+) -> Result<(Query, Placeholders), QueryError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        text,
+        tokens: &tokens,
+        position: 0,
+        functions,
+        placeholders: Vec::new(),
+    };
+    let query = parser.query()?;
+    Ok((query, parser.placeholders))
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: &'a [Token],
+    position: usize,
+    functions: &'a Functions,
+    placeholders: Placeholders,
+}
+
+impl Parser<'_> {
+    fn query(&mut self) -> Result<Query, QueryError> {
+        let explain = self.keyword("explain");
+        self.expect_keyword("select")?;
+        let distinct = self.keyword("distinct");
+
+        let columns_start = self.position;
+        let star = self.symbol("*");
+        let mut columns = Vec::new();
+        if !star {
+            columns.push(self.column()?);
+            while self.symbol(",") {
+                columns.push(self.column()?);
+            }
+        }
+
+        let from = if self.keyword("from") {
+            let token = self.next();
+            match &token.kind {
+                TokenKind::Ident(name) => kind_test(name, token.span.clone())?,
+                _ => return Err(self.unexpected(&token, "a node kind")),
+            }
+        } else {
+            match columns.first() {
+                Some((_, _, ColumnExpr::Kind(kind))) => *kind,
+                _ => {
+                    let span = self.tokens[columns_start].span.clone();
+                    return Err(QueryError::new(
+                        "expected FROM, or a node kind as the first column",
+                        span,
+                    ));
+                }
+            }
+        };
+
+        let source = if self.keyword("in") {
+            let token = self.next();
+            let TokenKind::String(pattern) = token.kind else {
+                return Err(self.unexpected(&token, "a file pattern"));
+            };
+            glob::Pattern::new(&pattern).map_err(|error| {
+                QueryError::new(format!("invalid file pattern: {}", error.msg), token.span)
+            })?;
+            Some(pattern)
+        } else {
+            None
+        };
+
+        let mut scopes = Vec::new();
+        loop {
+            if self.keyword("within") {
+                self.expect_keyword("section")?;
+                let token = self.next();
+                match token.kind {
+                    TokenKind::String(title) => scopes.push(Scope::Section(title)),
+                    _ => return Err(self.unexpected(&token, "a section title")),
+                }
+            } else if self.keyword("under") {
+                let token = self.next();
+                let kind = match &token.kind {
+                    TokenKind::Ident(name) => KindTest::parse(name),
+                    _ => None,
+                };
+                match kind {
+                    Some(kind) if kind.kind == Kind::Header => scopes.push(Scope::Under(kind)),
+                    _ => return Err(self.unexpected(&token, "a heading kind")),
+                }
+            } else {
+                break;
+            }
+        }
+
+        let filter = if self.keyword("where") {
+            let start = self.peek().span.start;
+            let filter = self.expr()?;
+            no_aggregate(&filter, start..self.previous_end(), "WHERE")?;
+            Some(filter)
+        } else {
+            None
+        };
+
+        let column_names: Vec<&str> = columns.iter().map(|(_, name, _)| name.as_str()).collect();
+
+        let mut group_by = Vec::new();
+        if self.keyword("group") {
+            self.expect_keyword("by")?;
+            loop {
+                let start = self.peek().span.start;
+                let key = self.order_key(&column_names, "GROUP BY")?;
+                group_by.push((start..self.previous_end(), key));
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let mut order_by = Vec::new();
+        if self.keyword("order") {
+            self.expect_keyword("by")?;
+            loop {
+                let key = self.order_key(&column_names, "ORDER BY")?;
+                let descending = self.keyword("desc");
+                if !descending {
+                    self.keyword("asc");
+                }
+                order_by.push((key, descending));
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let mut limit = None;
+        let mut offset = 0;
+        if self.keyword("limit") {
+            limit = Some(self.count()?);
+            if self.keyword("offset") {
+                offset = self.count()?;
+            }
+        }
+
+        let token = self.next();
+        if token.kind != TokenKind::End {
+            return Err(self.unexpected(&token, "the end of the query"));
+        }
+
+        let columns = if star {
+            star_columns(from.kind)
+        } else {
+            columns
+                .into_iter()
+                .map(|(span, name, column)| resolve_column(span, name, column, from))
+                .collect::<Result<_, _>>()?
+        };
+
+        let resolve = |key: OrderKey| match key {
+            OrderKey::Column(i) => columns[i].expr.clone(),
+            OrderKey::Expr(expr) => expr,
+        };
+
+        let group_by = group_by
+            .into_iter()
+            .map(|(span, key)| {
+                let expr = resolve(key);
+                no_aggregate(&expr, span, "GROUP BY").map(|_| expr)
+            })
+            .collect::<Result<_, _>>()?;
+
+        let order_by = order_by
+            .into_iter()
+            .map(|(key, descending)| Order {
+                expr: resolve(key),
+                descending,
+            })
+            .collect();
+
+        Ok(Query {
+            columns,
+            from,
+            source,
+            scopes,
+            filter,
+            distinct,
+            group_by,
+            order_by,
+            limit,
+            offset,
+            explain,
+        })
+    }
+
+    /// A term of `clause`, `GROUP BY` or `ORDER BY`, which may refer to a
+    /// column.
+    fn order_key(&mut self, column_names: &[&str], clause: &str) -> Result<OrderKey, QueryError> {
+        let token = self.peek().clone();
+        if token.kind == TokenKind::End {
+            return Err(self.unexpected(&token, &format!("a column after {}", clause)));
+        }
+        let ends_term = match self.tokens.get(self.position + 1).map(|next| &next.kind) {
+            Some(TokenKind::Symbol(",") | TokenKind::End) | None => true,
+            Some(TokenKind::Ident(next)) => is_keyword(next),
+            Some(_) => false,
+        };
+
+        match &token.kind {
+            TokenKind::Number(n) if ends_term => {
+                let position = *n as usize;
+                if n.fract() != 0.0 || position == 0 || position > column_names.len() {
+                    return Err(QueryError::new(
+                        format!("there is no column {}", n),
+                        token.span,
+                    ));
+                }
+                self.next();
+                Ok(OrderKey::Column(position - 1))
+            }
+            TokenKind::Ident(name) if ends_term && column_names.contains(&name.as_str()) => {
+                self.next();
+                let position = column_names.iter().position(|c| c == name);
+                Ok(OrderKey::Column(position.unwrap_or_default()))
+            }
+            _ => self.expr().map(OrderKey::Expr),
+        }
+    }
+
+    fn count(&mut self) -> Result<usize, QueryError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Number(n) if n.fract() == 0.0 && n >= 0.0 => Ok(n as usize),
+            _ => Err(self.unexpected(&token, "a whole number")),
+        }
+    }
+
+    /// The end of the last token consumed.
+    fn previous_end(&self) -> usize {
+        self.tokens[..self.position]
+            .last()
+            .map_or(0, |token| token.span.end)
+    }
+
+    fn column(&mut self) -> Result<(Range<usize>, String, ColumnExpr), QueryError> {
+        let start = self.peek().span.start;
+
+        // A bare node kind selects the nodes themselves.
+        let kind = match &self.peek().kind {
+            TokenKind::Ident(name) if !is_field(name) && Axis::parse(name).is_none() => {
+                KindTest::parse(name)
+            }
+            _ => None,
+        };
+
+        let expr = match kind {
+            Some(kind) => {
+                self.next();
+                ColumnExpr::Kind(kind)
+            }
+            None => ColumnExpr::Expr(self.expr()?),
+        };
+        let span = start..self.previous_end();
+
+        let name = if self.keyword("as") {
+            let token = self.next();
+            match token.kind {
+                TokenKind::Ident(name) | TokenKind::String(name) => name,
+                _ => return Err(self.unexpected(&token, "a column name")),
+            }
+        } else {
+            self.text[span.clone()].to_string()
+        };
+
+        Ok((span, name, expr))
+    }
+
+    fn expr(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.not()?;
+        while self.keyword("and") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, QueryError> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Expr, QueryError> {
+        let start = self.peek().span.start;
+        let left = self.primary()?;
+
+        if self.keyword("is") {
+            let negated = self.keyword("not");
+            if self.keyword("null") {
+                return Ok(Expr::IsNull {
+                    expr: Box::new(left),
+                    negated,
+                });
+            }
+
+            let token = self.next();
+            let TokenKind::Ident(name) = &token.kind else {
+                return Err(self.unexpected(&token, "NULL or a node kind"));
+            };
+            let kind = kind_test(name, token.span.clone())?;
+
+            let path = match left {
+                Expr::Path(path) if path.field.is_none() => path,
+                _ => {
+                    return Err(QueryError::new(
+                        "only nodes can be tested for a kind",
+                        start..token.span.end,
+                    ))
+                }
+            };
+
+            let filter = if self.symbol("[") {
+                let start = self.peek().span.start;
+                let filter = self.expr()?;
+                no_aggregate(&filter, start..self.previous_end(), "a kind test")?;
+                self.expect_symbol("]")?;
+                Some(Box::new(filter))
+            } else {
+                None
+            };
+
+            return Ok(Expr::IsKind {
+                path,
+                kind,
+                filter,
+                negated,
+            });
+        }
+
+        let op = match self.peek().kind {
+            TokenKind::Symbol("=") => CompareOp::Eq,
+            TokenKind::Symbol("!=" | "<>") => CompareOp::Ne,
+            TokenKind::Symbol("<") => CompareOp::Lt,
+            TokenKind::Symbol("<=") => CompareOp::Le,
+            TokenKind::Symbol(">") => CompareOp::Gt,
+            TokenKind::Symbol(">=") => CompareOp::Ge,
+            _ => return Ok(left),
+        };
+        self.next();
+
+        Ok(Expr::Compare {
+            left: Box::new(left),
+            op,
+            right: Box::new(self.primary()?),
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expr, QueryError> {
+        let token = self.next();
+
+        match &token.kind {
+            TokenKind::String(s) => Ok(Expr::Literal(Value::Text(s.clone()))),
+            TokenKind::Number(n) => Ok(Expr::Literal(Value::Number(*n))),
+            TokenKind::Symbol("?") => {
+                let position = self
+                    .placeholders
+                    .iter()
+                    .filter(|(p, _)| matches!(p, Placeholder::Position(_)))
+                    .count();
+                Ok(self.placeholder(Placeholder::Position(position + 1), token.span))
+            }
+            TokenKind::Parameter(name) => {
+                Ok(self.placeholder(Placeholder::Name(name.clone()), token.span.clone()))
+            }
+            TokenKind::Symbol("(") => {
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            TokenKind::Ident(name) if name.eq_ignore_ascii_case("null") => {
+                Ok(Expr::Literal(Value::Null))
+            }
+            TokenKind::Ident(name) if name.eq_ignore_ascii_case("true") => {
+                Ok(Expr::Literal(Value::Bool(true)))
+            }
+            TokenKind::Ident(name) if name.eq_ignore_ascii_case("false") => {
+                Ok(Expr::Literal(Value::Bool(false)))
+            }
+            TokenKind::Ident(name) if self.peek().kind == TokenKind::Symbol("(") => {
+                self.call(name, token.span.clone())
+            }
+            TokenKind::Ident(name) if !is_keyword(name) => self.path(name, token.span.clone()),
+            _ => Err(self.unexpected(&token, "an expression")),
+        }
+    }
+
+    fn call(&mut self, name: &str, span: Range<usize>) -> Result<Expr, QueryError> {
+        let Some(function) = Aggregate::parse(name) else {
+            return self.function_call(name, span);
+        };
+        self.expect_symbol("(")?;
+
+        let mut distinct = false;
+        let arg = if function == Aggregate::Count && self.symbol("*") {
+            None
+        } else {
+            distinct = self.keyword("distinct");
+            let start = self.peek().span.start;
+            let arg = self.expr()?;
+            no_aggregate(&arg, start..self.previous_end(), "an aggregate")?;
+            Some(Box::new(arg))
+        };
+
+        self.expect_symbol(")")?;
+        Ok(Expr::Aggregate {
+            function,
+            arg,
+            distinct,
+        })
+    }
+
+    fn function_call(&mut self, name: &str, span: Range<usize>) -> Result<Expr, QueryError> {
+        let Some(function) = self.functions.get(name).cloned() else {
+            let names = self.functions.iter().map(Function::name);
+            let aggregates = Aggregate::ALL.iter().map(|a| a.name());
+            return Err(QueryError::unknown(
+                "function",
+                name,
+                names.chain(aggregates),
+                span,
+            ));
+        };
+        self.expect_symbol("(")?;
+
+        let mut args = Vec::new();
+        if !self.symbol(")") {
+            args.push(self.expr()?);
+            while self.symbol(",") {
+                args.push(self.expr()?);
+            }
+            self.expect_symbol(")")?;
+        }
+
+        if args.len() != function.arity() {
+            return Err(QueryError::new(
+                format!(
+                    "`{}` takes {} argument{}, found {}",
+                    function.name(),
+                    function.arity(),
+                    if function.arity() == 1 { "" } else { "s" },
+                    args.len()
+                ),
+                span.start..self.previous_end(),
+            ));
+        }
+        Ok(Expr::Call { function, args })
+    }
+
+    fn path(&mut self, name: &str, span: Range<usize>) -> Result<Expr, QueryError> {
+        let Some(axis) = Axis::parse(name) else {
+            return field(name, span).map(|field| {
+                Expr::Path(Path {
+                    axis: Axis::Current,
+                    field: Some(field),
+                })
+            });
+        };
+
+        let field = if self.symbol(".") {
+            let token = self.next();
+            match &token.kind {
+                TokenKind::Ident(name) => Some(field(name, token.span.clone())?),
+                _ => return Err(self.unexpected(&token, "a field")),
+            }
+        } else {
+            None
+        };
+
+        Ok(Expr::Path(Path { axis, field }))
+    }
+
+    /// The parameter for a placeholder. Each `?` gets a slot of its own,
+    /// while the uses of a name share one.
+    fn placeholder(&mut self, placeholder: Placeholder, span: Range<usize>) -> Expr {
+        let slot = match placeholder {
+            Placeholder::Name(_) => self
+                .placeholders
+                .iter()
+                .position(|(p, _)| *p == placeholder),
+            Placeholder::Position(_) => None,
+        };
+        Expr::Parameter(slot.unwrap_or_else(|| {
+            self.placeholders.push((placeholder, span));
+            self.placeholders.len() - 1
+        }))
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(
+            &self.peek().kind,
+            TokenKind::Ident(name) if name.eq_ignore_ascii_case(keyword)
+        );
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn symbol(&mut self, symbol: &'static str) -> bool {
+        let found = self.peek().kind == TokenKind::Symbol(symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), QueryError> {
+        if self.keyword(keyword) {
+            return Ok(());
+        }
+        let token = self.peek().clone();
+        let mut error = self.unexpected(&token, &keyword.to_uppercase());
+        if let TokenKind::Ident(name) = &token.kind {
+            if let Some(keyword) = did_you_mean(name, [keyword]) {
+                write!(
+                    error.message,
+                    ", did you mean `{}`?",
+                    keyword.to_uppercase()
+                )
+                .expect("writing to a String never fails");
+            }
+        }
+        Err(error)
+    }
+
+    fn expect_symbol(&mut self, symbol: &'static str) -> Result<(), QueryError> {
+        if self.symbol(symbol) {
+            return Ok(());
+        }
+        let token = self.peek().clone();
+        Err(self.unexpected(&token, &format!("`{}`", symbol)))
+    }
+
+    fn unexpected(&self, token: &Token, expected: &str) -> QueryError {
+        let found = match token.kind {
+            TokenKind::End => "the end of the query".to_string(),
+            _ => format!("`{}`", &self.text[token.span.clone()]),
+        };
+        QueryError::new(
+            format!("expected {}, found {}", expected, found),
+            token.span.clone(),
+        )
+    }
+}
+
+enum OrderKey {
+    Column(usize),
+    Expr(Expr),
+}
+
+fn no_aggregate(expr: &Expr, span: Range<usize>, clause: &str) -> Result<(), QueryError> {
+    if expr.contains_aggregate() {
+        return Err(QueryError::new(
+            format!("aggregates are not allowed in {}", clause),
+            span,
+        ));
+    }
+    Ok(())
+}
+
+/// A column before `FROM` is known, which may still be a node kind.
+enum ColumnExpr {
+    Kind(KindTest),
+    Expr(Expr),
+}
+
+fn resolve_column(
+    span: Range<usize>,
+    name: String,
+    column: ColumnExpr,
+    from: KindTest,
+) -> Result<Column, QueryError> {
+    let expr = match column {
+        ColumnExpr::Expr(expr) => expr,
+        ColumnExpr::Kind(kind) if kind == from => Expr::Path(Path {
+            axis: Axis::Current,
+            field: None,
+        }),
+        ColumnExpr::Kind(kind) => {
+            return Err(QueryError::new(
+                format!("cannot select `{}` nodes from `{}` nodes", kind, from),
+                span,
+            ))
+        }
+    };
+
+    Ok(Column { name, expr })
+}
+
+/// The columns of `SELECT *`: the node itself and the fields of its kind.
+fn star_columns(kind: Kind) -> Vec<Column> {
+    let mut columns = vec![Column {
+        name: kind.name().to_string(),
+        expr: Expr::Path(Path {
+            axis: Axis::Current,
+            field: None,
+        }),
+    }];
+
+    for field in kind.fields() {
+        columns.push(Column {
+            name: field.to_string(),
+            expr: Expr::Path(Path {
+                axis: Axis::Current,
+                field: Some(field.to_string()),
+            }),
+        });
+    }
+    columns
+}
+
+pub(crate) fn field(name: &str, span: Range<usize>) -> Result<String, QueryError> {
+    let name = name.to_ascii_lowercase();
+    if is_field(&name) {
+        return Ok(name);
+    }
+
+    if KindTest::parse(&name).is_some() {
+        return Err(QueryError::new(
+            format!("`{}` is a node kind, compare it with IS", name),
+            span,
+        ));
+    }
+
+    let fields = Kind::ALL.iter().flat_map(|kind| kind.fields());
+    let candidates = COMMON_FIELDS
+        .iter()
+        .chain(fields)
+        .copied()
+        .chain(Axis::ALL.iter().map(|axis| axis.name()))
+        .chain(kind_names());
+    Err(QueryError::unknown("field", &name, candidates, span))
+}
+
+pub(crate) fn kind_test(name: &str, span: Range<usize>) -> Result<KindTest, QueryError> {
+    KindTest::parse(name).ok_or_else(|| QueryError::unknown("node kind", name, kind_names(), span))
+}
+
+/// The names of node kinds, as a query writes them.
+fn kind_names() -> impl Iterator<Item = &'static str> {
+    let headings = ["h1", "h2", "h3", "h4", "h5", "h6"];
+    Kind::ALL.iter().map(|kind| kind.name()).chain(headings)
+}
+
+fn is_keyword(name: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(name))
+}
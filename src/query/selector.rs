@@ -0,0 +1,444 @@
+//! Parses selectors, a compact syntax after CSS for the queries that only
+//! pick out nodes:
+//!
+//! ```text
+//! h2 + p link                  links in a paragraph right after an h2
+//! bold > link                  links directly inside bold text
+//! image[url^="https"]          images whose URL starts with https
+//! link:not([url*="example"])   links whose URL does not contain example
+//! ```
+//!
+//! ```text
+//! selector   = [EXPLAIN] complex
+//! complex    = compound (combinator compound)*
+//! combinator = " " | ">" | "+" | "~"
+//! compound   = kind ("[" attribute "]" | ":" pseudo)*
+//!            | ("[" attribute "]" | ":" pseudo)+
+//! attribute  = field [("=" | "^=" | "$=" | "*=" | "~=") value]
+//! pseudo     = (not | has) "(" complex ")" | first-child | last-child
+//! ```
+//!
+//! A selector compiles to a `Query` for the nodes its last compound
+//! matches, so that `h2 + p link` runs as
+//! `SELECT link WHERE ancestor IS paragraph[prev IS h2]`. Besides the kinds
+//! queries know, compounds may use the HTML names `p`, `a`, `img`,
+//! `strong`, `em`, `code`, `pre`, `li`, `ul`, `ol`, `tr` and `td`.
+//!
+//! Every compound names a kind, except the last one inside `:not(...)`, as
+//! in `link:not([url^=https])`.
+
+use std::ops::Range;
+
+use super::parser::{field, kind_test, Axis, Column, CompareOp, Expr, Path, Query};
+use super::tree::{Kind, KindTest};
+use super::{Functions, QueryError, Value};
+
+impl Query {
+    /// Parses a selector such as `bold > link` into the query it stands
+    /// for.
+    pub fn parse_selector(text: &str) -> Result<Self, QueryError> {
+        Parser {
+            text,
+            position: 0,
+            functions: &Functions::default(),
+        }
+        .selector()
+    }
+
+    /// Parses a query if `text` begins with `SELECT`, or `EXPLAIN SELECT`,
+    /// and a selector otherwise.
+    pub fn parse_any(text: &str) -> Result<Self, QueryError> {
+        let mut words = text.split_whitespace();
+        let first = words.next().unwrap_or_default();
+        let word = if first.eq_ignore_ascii_case("explain") {
+            words.next().unwrap_or_default()
+        } else {
+            first
+        };
+
+        if word.eq_ignore_ascii_case("select") {
+            Query::parse(text)
+        } else {
+            Query::parse_selector(text)
+        }
+    }
+}
+
+/// The kind of node a compound matches, and the conditions on it.
+struct Compound {
+    kind: Option<KindTest>,
+    conditions: Vec<Expr>,
+    start: usize,
+}
+
+const PSEUDO_CLASSES: [&str; 4] = ["not", "has", "first-child", "last-child"];
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+    functions: &'a Functions,
+}
+
+impl<'a> Parser<'a> {
+    fn selector(&mut self) -> Result<Query, QueryError> {
+        self.skip_whitespace();
+        let start = self.position;
+        let explain = self.name().eq_ignore_ascii_case("explain") && self.skip_whitespace();
+        if !explain {
+            self.position = start;
+        }
+
+        let (kind, filter) = self.subject()?;
+        if self.peek().is_some() {
+            return Err(self.unexpected("a combinator"));
+        }
+
+        Ok(Query {
+            columns: vec![Column {
+                name: kind.to_string(),
+                expr: Expr::Path(Path {
+                    axis: Axis::Current,
+                    field: None,
+                }),
+            }],
+            from: kind,
+            source: None,
+            scopes: Vec::new(),
+            filter,
+            distinct: false,
+            group_by: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: 0,
+            explain,
+        })
+    }
+
+    /// A complex selector whose last compound names a kind, as that kind
+    /// and the conditions on it.
+    fn subject(&mut self) -> Result<(KindTest, Option<Expr>), QueryError> {
+        let compound = self.complex()?;
+        match compound.kind {
+            Some(kind) => Ok((kind, all(compound.conditions))),
+            None => {
+                self.position = compound.start;
+                Err(self.unexpected("a node kind"))
+            }
+        }
+    }
+
+    /// Compounds joined by combinators, as the last compound with the ones
+    /// before it turned into a condition on it.
+    fn complex(&mut self) -> Result<Compound, QueryError> {
+        let mut compound = self.compound()?;
+        loop {
+            let spaced = self.skip_whitespace();
+            let axis = match self.peek() {
+                None | Some(')') => return Ok(compound),
+                Some('>') => Axis::Parent,
+                Some('+') => Axis::Previous,
+                Some('~') => Axis::PrecedingSibling,
+                Some(_) if spaced => Axis::Ancestor,
+                Some(_) => return Err(self.unexpected("a combinator")),
+            };
+            let Some(kind) = compound.kind else {
+                self.position = compound.start;
+                return Err(self.unexpected("a node kind"));
+            };
+            if axis != Axis::Ancestor {
+                self.bump();
+                self.skip_whitespace();
+            }
+
+            // The compound becomes a condition on the node the combinator
+            // leads to from the next one.
+            let relation = Expr::IsKind {
+                path: Path { axis, field: None },
+                kind,
+                filter: all(compound.conditions).map(Box::new),
+                negated: false,
+            };
+            compound = self.compound()?;
+            compound.conditions.push(relation);
+        }
+    }
+
+    fn compound(&mut self) -> Result<Compound, QueryError> {
+        let start = self.position;
+        let name = self.name();
+        let (kind, mut conditions) = if name.is_empty() {
+            (None, Vec::new())
+        } else {
+            let (kind, condition) = element(name, start..self.position)?;
+            (Some(kind), condition.into_iter().collect())
+        };
+
+        loop {
+            match self.peek() {
+                Some('[') => {
+                    self.bump();
+                    conditions.push(self.attribute()?);
+                }
+                Some(':') => {
+                    self.bump();
+                    conditions.push(self.pseudo_class()?);
+                }
+                _ => break,
+            }
+        }
+
+        if kind.is_none() && conditions.is_empty() {
+            self.position = start;
+            return Err(self.unexpected("a node kind"));
+        }
+        Ok(Compound {
+            kind,
+            conditions,
+            start,
+        })
+    }
+
+    /// The inside of `[...]`, after the bracket.
+    fn attribute(&mut self) -> Result<Expr, QueryError> {
+        self.skip_whitespace();
+        let start = self.position;
+        let name = self.name();
+        if name.is_empty() {
+            return Err(self.unexpected("a field"));
+        }
+        let path = Expr::Path(Path {
+            axis: Axis::Current,
+            field: Some(field(name, start..self.position)?),
+        });
+        self.skip_whitespace();
+
+        if self.eat("]") {
+            return Ok(Expr::IsNull {
+                expr: Box::new(path),
+                negated: true,
+            });
+        }
+
+        let operator = ["=", "^=", "$=", "*=", "~="]
+            .into_iter()
+            .find(|operator| self.eat(operator))
+            .ok_or_else(|| self.unexpected("`]` or an operator such as `=` or `^=`"))?;
+        self.skip_whitespace();
+        let value = self.value()?;
+        self.skip_whitespace();
+        if !self.eat("]") {
+            return Err(self.unexpected("`]`"));
+        }
+
+        let call = |name: &str, value: Value| Expr::Call {
+            function: self
+                .functions
+                .get(name)
+                .cloned()
+                .expect("the function is built in"),
+            args: vec![path.clone(), Expr::Literal(value)],
+        };
+        let text = value.to_string();
+        Ok(match operator {
+            "=" => Expr::Compare {
+                left: Box::new(path.clone()),
+                op: CompareOp::Eq,
+                right: Box::new(Expr::Literal(value)),
+            },
+            "^=" => call("starts_with", Value::Text(text)),
+            "$=" => call("ends_with", Value::Text(text)),
+            "*=" => call("contains", Value::Text(text)),
+            _ => {
+                let word = format!(r"(^|\s){}(\s|$)", regex::escape(&text));
+                call("regex_match", Value::Text(word))
+            }
+        })
+    }
+
+    /// A quoted string, or a bare word, which is a number if it reads as
+    /// one.
+    fn value(&mut self) -> Result<Value, QueryError> {
+        let start = self.position;
+        let Some(quote) = self.peek().filter(|c| *c == '"' || *c == '\'') else {
+            let word = self.name();
+            if word.is_empty() {
+                return Err(self.unexpected("a value"));
+            }
+            return Ok(word
+                .parse()
+                .map_or_else(|_| Value::from(word), Value::Number));
+        };
+
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => match self.bump() {
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                Some(c) if c == quote => return Ok(Value::Text(value)),
+                Some(c) => value.push(c),
+                None => break,
+            }
+        }
+        Err(QueryError::new(
+            "unterminated string",
+            start..self.text.len(),
+        ))
+    }
+
+    /// A pseudo-class, after the colon.
+    fn pseudo_class(&mut self) -> Result<Expr, QueryError> {
+        let start = self.position;
+        let name = self.name().to_ascii_lowercase();
+        let span = start..self.position;
+        if name.is_empty() {
+            return Err(self.unexpected("a pseudo-class"));
+        }
+
+        let sibling = |axis| Expr::IsNull {
+            expr: Box::new(Expr::Path(Path { axis, field: None })),
+            negated: false,
+        };
+        let expr = match name.as_str() {
+            "first-child" => return Ok(sibling(Axis::Previous)),
+            "last-child" => return Ok(sibling(Axis::Next)),
+            "not" | "has" => {
+                if !self.eat("(") {
+                    return Err(self.unexpected("`(`"));
+                }
+                self.skip_whitespace();
+                let expr = if name == "not" {
+                    let compound = self.complex()?;
+                    let kind = compound.kind.map(|kind| Expr::IsKind {
+                        path: Path {
+                            axis: Axis::Current,
+                            field: None,
+                        },
+                        kind,
+                        filter: None,
+                        negated: false,
+                    });
+                    let conditions = kind.into_iter().chain(compound.conditions).collect();
+                    Expr::Not(Box::new(all(conditions).expect("a compound is not empty")))
+                } else {
+                    let (kind, filter) = self.subject()?;
+                    Expr::IsKind {
+                        path: Path {
+                            axis: Axis::Descendant,
+                            field: None,
+                        },
+                        kind,
+                        filter: filter.map(Box::new),
+                        negated: false,
+                    }
+                };
+                self.skip_whitespace();
+                expr
+            }
+            _ => {
+                return Err(QueryError::unknown(
+                    "pseudo-class",
+                    &name,
+                    PSEUDO_CLASSES,
+                    span,
+                ))
+            }
+        };
+
+        if !self.eat(")") {
+            return Err(self.unexpected("`)`"));
+        }
+        Ok(expr)
+    }
+
+    /// A name made of letters, digits, `_` and `-`, which may be empty.
+    fn name(&mut self) -> &'a str {
+        let text = self.text;
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            self.bump();
+        }
+        &text[start..self.position]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let found = self.text[self.position..].starts_with(text);
+        if found {
+            self.position += text.len();
+        }
+        found
+    }
+
+    /// Skips whitespace, returning whether there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.position;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+        self.position > start
+    }
+
+    fn unexpected(&self, expected: &str) -> QueryError {
+        match self.peek() {
+            Some(c) => QueryError::new(
+                format!("expected {}, found `{}`", expected, c),
+                self.position..self.position + c.len_utf8(),
+            ),
+            None => QueryError::new(
+                format!("expected {}, found the end of the selector", expected),
+                self.position..self.position,
+            ),
+        }
+    }
+}
+
+/// The kind a compound names, with the condition an HTML name adds to it.
+fn element(name: &str, span: Range<usize>) -> Result<(KindTest, Option<Expr>), QueryError> {
+    let kind = |kind| KindTest { kind, level: None };
+    let ordered = |ordered| Expr::Compare {
+        left: Box::new(Expr::Path(Path {
+            axis: Axis::Current,
+            field: Some("ordered".to_string()),
+        })),
+        op: CompareOp::Eq,
+        right: Box::new(Expr::Literal(Value::Bool(ordered))),
+    };
+
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "p" => (kind(Kind::Paragraph), None),
+        "a" => (kind(Kind::Link), None),
+        "img" => (kind(Kind::Image), None),
+        "strong" => (kind(Kind::Bold), None),
+        "em" => (kind(Kind::Italic), None),
+        "code" => (kind(Kind::InlineCode), None),
+        "pre" => (kind(Kind::CodeBlock), None),
+        "li" => (kind(Kind::Item), None),
+        "ul" => (kind(Kind::List), Some(ordered(false))),
+        "ol" => (kind(Kind::List), Some(ordered(true))),
+        "tr" => (kind(Kind::Row), None),
+        "td" => (kind(Kind::Cell), None),
+        _ => (kind_test(name, span)?, None),
+    })
+}
+
+/// The conditions joined by `AND`, or `None` if there are none.
+fn all(conditions: Vec<Expr>) -> Option<Expr> {
+    conditions
+        .into_iter()
+        .reduce(|left, right| Expr::And(Box::new(left), Box::new(right)))
+}
@@ -0,0 +1,466 @@
+//! A flattened view of one or more `Document`s that queries run over. Every
+//! line, list item, table row and cell, and inline token becomes a node that
+//! knows its parent and children. Nodes are numbered in document order, so a
+//! node's descendants are the nodes numbered after it up to its `end`, and
+//! the nodes of each document follow those of the one before.
+
+use std::ops::Range;
+
+use crate::{Document, Line, ListItem, Span, Token};
+
+use super::Value;
+
+/// The kinds of node a query can select.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum Kind {
+    Header,
+    Paragraph,
+    Image,
+    CodeBlock,
+    List,
+    Item,
+    Blockquote,
+    Table,
+    Row,
+    Cell,
+    Blank,
+    Bold,
+    Italic,
+    InlineCode,
+    Regular,
+    Link,
+    SoftBreak,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 17] = [
+        Kind::Header,
+        Kind::Paragraph,
+        Kind::Image,
+        Kind::CodeBlock,
+        Kind::List,
+        Kind::Item,
+        Kind::Blockquote,
+        Kind::Table,
+        Kind::Row,
+        Kind::Cell,
+        Kind::Blank,
+        Kind::Bold,
+        Kind::Italic,
+        Kind::InlineCode,
+        Kind::Regular,
+        Kind::Link,
+        Kind::SoftBreak,
+    ];
+
+    /// The name queries use for this kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Header => "header",
+            Kind::Paragraph => "paragraph",
+            Kind::Image => "image",
+            Kind::CodeBlock => "code_block",
+            Kind::List => "list",
+            Kind::Item => "item",
+            Kind::Blockquote => "blockquote",
+            Kind::Table => "table",
+            Kind::Row => "row",
+            Kind::Cell => "cell",
+            Kind::Blank => "blank",
+            Kind::Bold => "bold",
+            Kind::Italic => "italic",
+            Kind::InlineCode => "inline_code",
+            Kind::Regular => "regular",
+            Kind::Link => "link",
+            Kind::SoftBreak => "soft_break",
+        }
+    }
+
+    /// The fields a node of this kind has besides the common ones.
+    pub fn fields(self) -> &'static [&'static str] {
+        match self {
+            Kind::Header => &["level"],
+            Kind::Image | Kind::Link => &["url", "label"],
+            Kind::CodeBlock => &["info", "content"],
+            Kind::List => &["ordered", "start", "loose"],
+            Kind::Cell => &["index", "column_header"],
+            _ => &[],
+        }
+    }
+}
+
+/// The fields every node has.
+pub const COMMON_FIELDS: [&str; 6] = ["kind", "text", "line", "column", "section", "file"];
+
+/// Whether `name` is a field of any kind of node.
+pub fn is_field(name: &str) -> bool {
+    COMMON_FIELDS.contains(&name) || Kind::ALL.iter().any(|kind| kind.fields().contains(&name))
+}
+
+/// A node kind, optionally narrowed to one heading level as in `h2`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct KindTest {
+    pub kind: Kind,
+    pub level: Option<u8>,
+}
+
+impl KindTest {
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+
+        if let Some(level) = name.strip_prefix('h').and_then(|l| l.parse().ok()) {
+            return (1..=6).contains(&level).then_some(KindTest {
+                kind: Kind::Header,
+                level: Some(level),
+            });
+        }
+
+        let kind = Kind::ALL.into_iter().find(|kind| kind.name() == name)?;
+        Some(KindTest { kind, level: None })
+    }
+
+    pub(crate) fn matches(&self, tree: &Tree, id: usize) -> bool {
+        tree.nodes[id].kind == self.kind
+            && self.level.is_none_or(|level| tree.level(id) == Some(level))
+    }
+}
+
+/// The AST value a node stands for.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Ast<'a> {
+    Line(&'a Line),
+    Item(&'a ListItem),
+    Row(&'a [Vec<Token>]),
+    Cell(&'a [Token]),
+    Token(&'a Token),
+}
+
+#[derive(Debug)]
+pub(crate) struct Node<'a> {
+    pub(crate) ast: Ast<'a>,
+    pub(crate) kind: Kind,
+    pub(crate) span: Span,
+    /// The number of the document the node is in.
+    pub(crate) document: usize,
+    pub(crate) parent: Option<usize>,
+    pub(crate) children: Vec<usize>,
+    /// One past the number of the node's last descendant.
+    pub(crate) end: usize,
+}
+
+/// The nodes under a heading: those after it up to the next heading of the
+/// same or a higher level.
+#[derive(Debug)]
+pub(crate) struct Section {
+    pub(crate) heading: usize,
+    pub(crate) nodes: Range<usize>,
+}
+
+/// The nodes of one document, and the path it was read from, if any.
+#[derive(Debug)]
+pub(crate) struct Source<'a> {
+    pub(crate) file: Option<&'a str>,
+    pub(crate) roots: Vec<usize>,
+    pub(crate) nodes: Range<usize>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Tree<'a> {
+    pub(crate) nodes: Vec<Node<'a>>,
+    pub(crate) sources: Vec<Source<'a>>,
+    pub(crate) sections: Vec<Section>,
+}
+
+impl<'a> Tree<'a> {
+    pub(crate) fn new(
+        documents: impl IntoIterator<Item = (Option<&'a str>, &'a Document)>,
+    ) -> Self {
+        let mut tree = Tree::default();
+        for (file, document) in documents {
+            let start = tree.len();
+            tree.sources.push(Source {
+                file,
+                roots: Vec::new(),
+                nodes: start..start,
+            });
+
+            let roots = document
+                .iter()
+                .map(|line| tree.add(Ast::Line(line), None, line.span()))
+                .collect();
+            let end = tree.len();
+            let source = tree.sources.last_mut().expect("a source was just added");
+            source.roots = roots;
+            source.nodes.end = end;
+
+            let sections = tree.find_sections(start..end);
+            tree.sections.extend(sections);
+        }
+        tree
+    }
+
+    fn find_sections(&self, nodes: Range<usize>) -> Vec<Section> {
+        let end = nodes.end;
+        let headings: Vec<(usize, u8)> =
+            nodes.filter_map(|id| Some((id, self.level(id)?))).collect();
+
+        headings
+            .iter()
+            .enumerate()
+            .map(|(i, &(heading, level))| {
+                let end = headings[i + 1..]
+                    .iter()
+                    .find(|(_, next)| *next <= level)
+                    .map_or(end, |(next, _)| *next);
+
+                Section {
+                    heading,
+                    nodes: self.nodes[heading].end..end,
+                }
+            })
+            .collect()
+    }
+
+    /// The document a node is in.
+    pub(crate) fn source(&self, id: usize) -> &Source<'a> {
+        &self.sources[self.nodes[id].document]
+    }
+
+    /// The sections a node is in, outermost first.
+    pub(crate) fn sections(&self, id: usize) -> impl Iterator<Item = &Section> {
+        self.sections
+            .iter()
+            .filter(move |section| section.nodes.contains(&id))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Adds a node and, recursively, its children.
+    fn add(&mut self, ast: Ast<'a>, parent: Option<usize>, span: Span) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            ast,
+            kind: kind_of(ast),
+            span,
+            document: self.sources.len() - 1,
+            parent,
+            children: Vec::new(),
+            end: id + 1,
+        });
+
+        let mut children = Vec::new();
+        match ast {
+            Ast::Line(line) => match line {
+                Line::Header { tokens, .. } | Line::Paragraph(tokens, _) => {
+                    children.extend(tokens.iter().map(|t| self.add_token(t, id)))
+                }
+                Line::Image { label, .. } => {
+                    children.extend(label.iter().map(|t| self.add_token(t, id)))
+                }
+                Line::List { items, .. } => {
+                    for item in items {
+                        children.push(self.add(Ast::Item(item), Some(id), item.span));
+                    }
+                }
+                Line::Blockquote(lines, _) => {
+                    for line in lines {
+                        children.push(self.add(Ast::Line(line), Some(id), line.span()));
+                    }
+                }
+                Line::Table { header, rows, .. } => {
+                    for row in std::iter::once(header).chain(rows) {
+                        let row_span = cells_span(row).unwrap_or(span);
+                        children.push(self.add(Ast::Row(row), Some(id), row_span));
+                    }
+                }
+                Line::CodeBlock { .. } | Line::Blank(_) => {}
+            },
+            Ast::Item(item) => {
+                children.extend(item.tokens.iter().map(|t| self.add_token(t, id)));
+                for line in &item.children {
+                    children.push(self.add(Ast::Line(line), Some(id), line.span()));
+                }
+            }
+            Ast::Row(cells) => {
+                for cell in cells {
+                    let cell_span = tokens_span(cell).unwrap_or(span);
+                    children.push(self.add(Ast::Cell(cell), Some(id), cell_span));
+                }
+            }
+            Ast::Cell(tokens) => children.extend(tokens.iter().map(|t| self.add_token(t, id))),
+            Ast::Token(token) => match token {
+                Token::Bold(tokens, _)
+                | Token::Italic(tokens, _)
+                | Token::InlineCode(tokens, _)
+                | Token::Link { label: tokens, .. }
+                | Token::Image { label: tokens, .. } => {
+                    children.extend(tokens.iter().map(|t| self.add_token(t, id)))
+                }
+                Token::Regular(..) | Token::SoftBreak(_) => {}
+            },
+        }
+
+        let end = self.nodes.len();
+        let node = &mut self.nodes[id];
+        node.children = children;
+        node.end = end;
+        id
+    }
+
+    fn add_token(&mut self, token: &'a Token, parent: usize) -> usize {
+        self.add(Ast::Token(token), Some(parent), token.span())
+    }
+
+    /// The nodes sharing the parent of `id`, including `id` itself.
+    pub(crate) fn siblings(&self, id: usize) -> &[usize] {
+        match self.nodes[id].parent {
+            Some(parent) => &self.nodes[parent].children,
+            None => &self.source(id).roots,
+        }
+    }
+
+    /// Whether a node carries content. Blank lines, soft breaks and
+    /// whitespace between inline nodes only separate their neighbours.
+    pub(crate) fn is_significant(&self, id: usize) -> bool {
+        match self.nodes[id].ast {
+            Ast::Line(Line::Blank(_)) | Ast::Token(Token::SoftBreak(_)) => false,
+            Ast::Token(Token::Regular(text, _)) => !text.trim().is_empty(),
+            _ => true,
+        }
+    }
+
+    pub(crate) fn level(&self, id: usize) -> Option<u8> {
+        match self.nodes[id].ast {
+            Ast::Line(Line::Header { level, .. }) => Some(level.number()),
+            _ => None,
+        }
+    }
+
+    /// The plain text of a node: inline content is concatenated, with soft
+    /// breaks as spaces, and blocks are joined by newlines.
+    pub(crate) fn text(&self, id: usize) -> String {
+        let node = &self.nodes[id];
+        let separator = match node.ast {
+            Ast::Line(Line::CodeBlock { content, .. }) => return content.clone(),
+            Ast::Token(Token::Regular(text, _)) => return text.clone(),
+            Ast::Token(Token::SoftBreak(_)) => return " ".to_string(),
+            Ast::Line(Line::List { .. } | Line::Table { .. }) => "\n",
+            Ast::Row(_) => " | ",
+            _ => "",
+        };
+
+        let mut text = String::new();
+        for (i, &child) in node.children.iter().enumerate() {
+            if i > 0 {
+                let block = matches!(self.nodes[child].ast, Ast::Line(_));
+                text.push_str(if block { "\n" } else { separator });
+            }
+            text.push_str(&self.text(child));
+        }
+        text
+    }
+
+    /// The position of a cell in its row, from 0.
+    fn cell_index(&self, id: usize) -> Option<usize> {
+        let row = self.nodes[id].parent?;
+        self.nodes[row].children.iter().position(|&cell| cell == id)
+    }
+
+    /// The cell of the header row in the column of a cell.
+    fn cell_header(&self, id: usize) -> Option<usize> {
+        let index = self.cell_index(id)?;
+        let table = self.nodes[self.nodes[id].parent?].parent?;
+        let header = *self.nodes[table].children.first()?;
+        self.nodes[header].children.get(index).copied()
+    }
+
+    /// The value of a field of a node, or `Null` if it has no such field.
+    pub(crate) fn field(&self, id: usize, name: &str) -> Value {
+        let node = &self.nodes[id];
+
+        match name {
+            "kind" => return Value::Text(node.kind.name().to_string()),
+            "text" => return Value::Text(self.text(id)),
+            "line" => return Value::Number(node.span.line as f64),
+            "column" => return Value::Number(node.span.column as f64),
+            "section" => {
+                return self.sections(id).last().map_or(Value::Null, |section| {
+                    Value::Text(self.text(section.heading))
+                })
+            }
+            "file" => return self.source(id).file.map_or(Value::Null, Value::from),
+            "level" => {
+                return self
+                    .level(id)
+                    .map_or(Value::Null, |l| Value::Number(l.into()))
+            }
+            "label" if matches!(node.kind, Kind::Image | Kind::Link) => {
+                return Value::Text(self.text(id))
+            }
+            "index" if node.kind == Kind::Cell => {
+                return self
+                    .cell_index(id)
+                    .map_or(Value::Null, |i| Value::Number((i + 1) as f64))
+            }
+            "column_header" if node.kind == Kind::Cell => {
+                return self
+                    .cell_header(id)
+                    .map_or(Value::Null, |header| Value::Text(self.text(header)))
+            }
+            _ => {}
+        }
+
+        match (node.ast, name) {
+            (
+                Ast::Line(Line::Image { url, .. })
+                | Ast::Token(Token::Link { url, .. } | Token::Image { url, .. }),
+                "url",
+            ) => Value::Text(url.clone()),
+            (Ast::Line(Line::CodeBlock { info, .. }), "info") => Value::Text(info.clone()),
+            (Ast::Line(Line::CodeBlock { content, .. }), "content") => Value::Text(content.clone()),
+            (Ast::Line(Line::List { ordered, .. }), "ordered") => Value::Bool(*ordered),
+            (Ast::Line(Line::List { start, .. }), "start") => Value::Number(*start as f64),
+            (Ast::Line(Line::List { loose, .. }), "loose") => Value::Bool(*loose),
+            _ => Value::Null,
+        }
+    }
+}
+
+fn kind_of(ast: Ast) -> Kind {
+    match ast {
+        Ast::Line(line) => match line {
+            Line::Header { .. } => Kind::Header,
+            Line::Paragraph(..) => Kind::Paragraph,
+            Line::Image { .. } => Kind::Image,
+            Line::CodeBlock { .. } => Kind::CodeBlock,
+            Line::List { .. } => Kind::List,
+            Line::Blockquote(..) => Kind::Blockquote,
+            Line::Table { .. } => Kind::Table,
+            Line::Blank(_) => Kind::Blank,
+        },
+        Ast::Item(_) => Kind::Item,
+        Ast::Row(_) => Kind::Row,
+        Ast::Cell(_) => Kind::Cell,
+        Ast::Token(token) => match token {
+            Token::Bold(..) => Kind::Bold,
+            Token::Italic(..) => Kind::Italic,
+            Token::InlineCode(..) => Kind::InlineCode,
+            Token::Regular(..) => Kind::Regular,
+            Token::Link { .. } => Kind::Link,
+            Token::Image { .. } => Kind::Image,
+            Token::SoftBreak(_) => Kind::SoftBreak,
+        },
+    }
+}
+
+fn tokens_span(tokens: &[Token]) -> Option<Span> {
+    Some(tokens.first()?.span().to(tokens.last()?.span()))
+}
+
+fn cells_span(cells: &[Vec<Token>]) -> Option<Span> {
+    let mut spans = cells.iter().filter_map(|cell| tokens_span(cell));
+    let first = spans.next()?;
+    Some(spans.fold(first, Span::to))
+}
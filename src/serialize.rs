@@ -0,0 +1,395 @@
+//! `Serialize` and `Deserialize` for documents, with the `serde` feature.
+//!
+//! Every node is an object whose `"type"` is its kind, named as queries
+//! name it, with its fields beside it and the `"span"` of source it came
+//! from. In JSON, `See [docs](https://docs.rs)` is:
+//!
+//! ```json
+//! [
+//!   {
+//!     "type": "paragraph",
+//!     "tokens": [
+//!       { "type": "regular", "text": "See ", "span": { ... } },
+//!       {
+//!         "type": "link",
+//!         "url": "https://docs.rs",
+//!         "label": [{ "type": "regular", "text": "docs", "span": { ... } }],
+//!         "span": { ... }
+//!       }
+//!     ],
+//!     "span": { "start": 0, "end": 24, "line": 1, "column": 1 }
+//!   }
+//! ]
+//! ```
+//!
+//! The nodes are:
+//!
+//! - blocks: `header` (`level` 1 to 6, `tokens`), `paragraph` (`tokens`),
+//!   `image` (`url`, `label`), `code_block` (`info`, `content`), `list`
+//!   (`ordered`, `start`, `loose`, `items`), `blockquote` (`lines`), `table`
+//!   (`alignments`, `header`, `rows`) and `blank`;
+//! - inline: `bold`, `italic` and `inline_code` (`tokens`), `regular`
+//!   (`text`), `link` and `image` (`url`, `label`) and `soft_break`.
+//!
+//! A list item is `{"tokens": [...], "children": [...], "span": {...}}`, an
+//! alignment is `"none"`, `"left"`, `"center"` or `"right"`, and a table
+//! cell is an array of tokens. Spans may be left out when deserializing.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Alignment, HeaderLevel, Line, ListItem, Span, Token};
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TokenRef<'a> {
+    Bold {
+        tokens: &'a [Token],
+        span: Span,
+    },
+    Italic {
+        tokens: &'a [Token],
+        span: Span,
+    },
+    InlineCode {
+        tokens: &'a [Token],
+        span: Span,
+    },
+    Regular {
+        text: &'a str,
+        span: Span,
+    },
+    Link {
+        url: &'a str,
+        label: &'a [Token],
+        span: Span,
+    },
+    Image {
+        url: &'a str,
+        label: &'a [Token],
+        span: Span,
+    },
+    SoftBreak {
+        span: Span,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TokenData {
+    Bold {
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    Italic {
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    InlineCode {
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    Regular {
+        text: String,
+        #[serde(default)]
+        span: Span,
+    },
+    Link {
+        url: String,
+        label: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    Image {
+        url: String,
+        label: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    SoftBreak {
+        #[serde(default)]
+        span: Span,
+    },
+}
+
+impl Serialize for Token {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let token = match self {
+            Token::Bold(tokens, span) => TokenRef::Bold {
+                tokens,
+                span: *span,
+            },
+            Token::Italic(tokens, span) => TokenRef::Italic {
+                tokens,
+                span: *span,
+            },
+            Token::InlineCode(tokens, span) => TokenRef::InlineCode {
+                tokens,
+                span: *span,
+            },
+            Token::Regular(text, span) => TokenRef::Regular { text, span: *span },
+            Token::Link { label, url, span } => TokenRef::Link {
+                url,
+                label,
+                span: *span,
+            },
+            Token::Image { label, url, span } => TokenRef::Image {
+                url,
+                label,
+                span: *span,
+            },
+            Token::SoftBreak(span) => TokenRef::SoftBreak { span: *span },
+        };
+        token.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Token {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match TokenData::deserialize(deserializer)? {
+            TokenData::Bold { tokens, span } => Token::Bold(tokens, span),
+            TokenData::Italic { tokens, span } => Token::Italic(tokens, span),
+            TokenData::InlineCode { tokens, span } => Token::InlineCode(tokens, span),
+            TokenData::Regular { text, span } => Token::Regular(text, span),
+            TokenData::Link { url, label, span } => Token::Link { label, url, span },
+            TokenData::Image { url, label, span } => Token::Image { label, url, span },
+            TokenData::SoftBreak { span } => Token::SoftBreak(span),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LineRef<'a> {
+    Header {
+        level: &'a HeaderLevel,
+        tokens: &'a [Token],
+        span: Span,
+    },
+    Paragraph {
+        tokens: &'a [Token],
+        span: Span,
+    },
+    Image {
+        url: &'a str,
+        label: &'a [Token],
+        span: Span,
+    },
+    CodeBlock {
+        info: &'a str,
+        content: &'a str,
+        span: Span,
+    },
+    List {
+        ordered: bool,
+        start: u64,
+        loose: bool,
+        items: &'a [ListItem],
+        span: Span,
+    },
+    Blockquote {
+        lines: &'a [Line],
+        span: Span,
+    },
+    Table {
+        alignments: &'a [Alignment],
+        header: &'a [Vec<Token>],
+        rows: &'a [Vec<Vec<Token>>],
+        span: Span,
+    },
+    Blank {
+        span: Span,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LineData {
+    Header {
+        level: HeaderLevel,
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    Paragraph {
+        tokens: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    Image {
+        url: String,
+        label: Vec<Token>,
+        #[serde(default)]
+        span: Span,
+    },
+    CodeBlock {
+        info: String,
+        content: String,
+        #[serde(default)]
+        span: Span,
+    },
+    List {
+        ordered: bool,
+        start: u64,
+        #[serde(default)]
+        loose: bool,
+        items: Vec<ListItem>,
+        #[serde(default)]
+        span: Span,
+    },
+    Blockquote {
+        lines: Vec<Line>,
+        #[serde(default)]
+        span: Span,
+    },
+    Table {
+        alignments: Vec<Alignment>,
+        header: Vec<Vec<Token>>,
+        rows: Vec<Vec<Vec<Token>>>,
+        #[serde(default)]
+        span: Span,
+    },
+    Blank {
+        #[serde(default)]
+        span: Span,
+    },
+}
+
+impl Serialize for Line {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let line = match self {
+            Line::Header {
+                level,
+                tokens,
+                span,
+            } => LineRef::Header {
+                level,
+                tokens,
+                span: *span,
+            },
+            Line::Paragraph(tokens, span) => LineRef::Paragraph {
+                tokens,
+                span: *span,
+            },
+            Line::Image { label, url, span } => LineRef::Image {
+                url,
+                label,
+                span: *span,
+            },
+            Line::CodeBlock {
+                info,
+                content,
+                span,
+            } => LineRef::CodeBlock {
+                info,
+                content,
+                span: *span,
+            },
+            Line::List {
+                ordered,
+                start,
+                loose,
+                items,
+                span,
+            } => LineRef::List {
+                ordered: *ordered,
+                start: *start,
+                loose: *loose,
+                items,
+                span: *span,
+            },
+            Line::Blockquote(lines, span) => LineRef::Blockquote { lines, span: *span },
+            Line::Table {
+                alignments,
+                header,
+                rows,
+                span,
+            } => LineRef::Table {
+                alignments,
+                header,
+                rows,
+                span: *span,
+            },
+            Line::Blank(span) => LineRef::Blank { span: *span },
+        };
+        line.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Line {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match LineData::deserialize(deserializer)? {
+            LineData::Header {
+                level,
+                tokens,
+                span,
+            } => Line::Header {
+                level,
+                tokens,
+                span,
+            },
+            LineData::Paragraph { tokens, span } => Line::Paragraph(tokens, span),
+            LineData::Image { url, label, span } => Line::Image { label, url, span },
+            LineData::CodeBlock {
+                info,
+                content,
+                span,
+            } => Line::CodeBlock {
+                info,
+                content,
+                span,
+            },
+            LineData::List {
+                ordered,
+                start,
+                loose,
+                items,
+                span,
+            } => Line::List {
+                ordered,
+                start,
+                loose,
+                items,
+                span,
+            },
+            LineData::Blockquote { lines, span } => Line::Blockquote(lines, span),
+            LineData::Table {
+                alignments,
+                header,
+                rows,
+                span,
+            } => Line::Table {
+                alignments,
+                header,
+                rows,
+                span,
+            },
+            LineData::Blank { span } => Line::Blank(span),
+        })
+    }
+}
+
+impl Serialize for HeaderLevel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.number())
+    }
+}
+
+impl<'de> Deserialize<'de> for HeaderLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match u8::deserialize(deserializer)? {
+            1 => Ok(HeaderLevel::H1),
+            2 => Ok(HeaderLevel::H2),
+            3 => Ok(HeaderLevel::H3),
+            4 => Ok(HeaderLevel::H4),
+            5 => Ok(HeaderLevel::H5),
+            6 => Ok(HeaderLevel::H6),
+            level => Err(serde::de::Error::custom(format!(
+                "invalid heading level {}, expected 1 to 6",
+                level
+            ))),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Lets writers built on `fmt::Write` write to an `io::Write` as well.
+
+use std::fmt;
+use std::io;
+
+/// Runs `write` against `out`, keeping the io error that `fmt::Error`
+/// cannot carry.
+pub(crate) fn write_io(
+    out: &mut impl io::Write,
+    write: impl FnOnce(&mut dyn fmt::Write) -> fmt::Result,
+) -> io::Result<()> {
+    let mut adapter = IoAdapter { out, error: None };
+    match write(&mut adapter) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(adapter
+            .error
+            .unwrap_or_else(|| io::Error::other("formatting failed"))),
+    }
+}
+
+struct IoAdapter<'a, W: io::Write> {
+    out: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.out.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const DOCUMENT: &str = "# Links
+
+See [home](https://a.com) and [other](http://b.org).
+
+### Details
+";
+
+/// Runs `anansi` with `args` and `stdin`, returning its exit code, output
+/// and errors.
+fn anansi(args: &[&str], stdin: &str) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_anansi"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn read_standard_input() {
+    let (code, out, _) = anansi(&["parse", "-"], "# Title");
+    assert_eq!(code, 0);
+    assert!(out.contains("Header {"), "{}", out);
+
+    let (code, out, _) = anansi(&["query", "--format=csv", "SELECT url FROM link"], DOCUMENT);
+    assert_eq!(
+        (code, out.as_str()),
+        (0, "url\r\nhttps://a.com\r\nhttp://b.org\r\n")
+    );
+
+    let (code, out, _) = anansi(&["render", "--to", "html"], "## A & B\n");
+    assert_eq!(
+        (code, out.as_str()),
+        (0, "<h2 id=\"a--b\">A &amp; B</h2>\n")
+    );
+
+    let script = "[a](javascript:alert(1))\n";
+    let (code, out, _) = anansi(&["render"], script);
+    assert_eq!((code, out.as_str()), (0, "<p><a>a</a></p>\n"));
+    let (code, out, _) = anansi(&["render", "--unsafe-urls"], script);
+    assert_eq!(
+        (code, out.as_str()),
+        (0, "<p><a href=\"javascript:alert(1)\">a</a></p>\n")
+    );
+
+    let (code, out, _) = anansi(&["fmt", "-"], "Some __bold__\n* item\n");
+    assert_eq!((code, out.as_str()), (0, "Some **bold**\n- item\n"));
+
+    let (code, out, _) = anansi(&["toc"], DOCUMENT);
+    assert_eq!(
+        (code, out.as_str()),
+        (0, "- [Links](#links)\n    - [Details](#details)\n")
+    );
+}
+
+#[test]
+fn read_files() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("links.md");
+    std::fs::write(&path, DOCUMENT).unwrap();
+    let path = path.to_str().unwrap();
+
+    let (code, out, _) = anansi(&["query", "--format", "csv", "link[url^=https]", path], "");
+    assert_eq!(code, 0);
+    assert_eq!(
+        out,
+        format!("file,line,column,link\r\n{},3,5,home\r\n", path)
+    );
+
+    let (code, out, _) = anansi(&["lint", path], "");
+    assert_eq!(code, 1);
+    assert_eq!(out, format!("{}:5:1: heading jumps from h1 to h3\n", path));
+
+    for args in [
+        &["query", "link", "no/such/*.md"][..],
+        &["lint", "no/such/*.md"],
+    ] {
+        let (code, _, error) = anansi(args, "");
+        assert_eq!(
+            (code, error.as_str()),
+            (1, "anansi: no files match `no/such/*.md`\n")
+        );
+    }
+
+    let (code, _, error) = anansi(&["toc", "no/such/file.md"], "");
+    assert_eq!(code, 1);
+    assert!(
+        error.starts_with("anansi: cannot read no/such/file.md: "),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn report_errors() {
+    let (code, _, error) = anansi(&["lint"], "# Title\n\nSee [docs](https://docs.rs).\n");
+    assert_eq!((code, error.as_str()), (0, ""));
+
+    let (code, _, error) = anansi(&["query", "SELECT url FROM lnk"], "");
+    assert_eq!(code, 1);
+    assert!(
+        error.starts_with("anansi: unknown node kind `lnk`, did you mean `link`?\n --> 1:17"),
+        "{}",
+        error
+    );
+
+    for args in [
+        &[][..],
+        &["frobnicate"],
+        &["parse", "--pretty"],
+        &["toc", "a.md", "b.md"],
+        &["query"],
+        &["query", "--format"],
+        &["render", "--to", "pdf"],
+    ] {
+        let (code, _, error) = anansi(args, "");
+        assert_eq!(code, 2, "{:?}", args);
+        assert!(error.contains("usage: anansi"), "{}", error);
+    }
+}
+
+#[test]
+fn stop_quietly_when_output_is_closed() {
+    let document = "# a\n\n[](b)\n\n".repeat(10_000);
+    for args in [&["parse"][..], &["fmt"], &["toc"], &["lint"], &["help"]] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_anansi"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        drop(child.stdout.take());
+        // The command may stop before it has read all of its input.
+        let _ = child.stdin.take().unwrap().write_all(document.as_bytes());
+        let output = child.wait_with_output().unwrap();
+        assert_eq!(
+            (
+                output.status.code(),
+                String::from_utf8(output.stderr).unwrap()
+            ),
+            (Some(0), String::new()),
+            "{:?}",
+            args
+        );
+    }
+}
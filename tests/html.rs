@@ -0,0 +1,117 @@
+use std::io;
+
+use md_parser::html::{self, HeadingIds, Options};
+
+fn render(text: &str) -> String {
+    html::render(&md_parser::parse(text), &Options::default())
+}
+
+#[test]
+fn render_inline_tokens() {
+    assert_eq!(
+        render("Some **bold *and italic*** with `code` and [a **link**](https://a.com)\nsoftly"),
+        "<p>Some <strong>bold <em>and italic</em></strong> with <code>code</code> and \
+         <a href=\"https://a.com\">a <strong>link</strong></a>\nsoftly</p>\n"
+    );
+}
+
+#[test]
+fn render_inline_images() {
+    assert_eq!(
+        render("![a](a.png) then ![*b*](b.png)"),
+        "<p><img src=\"a.png\" alt=\"a\"> then <img src=\"b.png\" alt=\"b\"></p>\n"
+    );
+}
+
+#[test]
+fn render_blocks() {
+    assert_eq!(
+        render("## Title\n\n![a logo](logo.png)\n\n- one\n- two\n"),
+        "<h2>Title</h2>\n<p><img src=\"logo.png\" alt=\"a logo\"></p>\n\
+         <ul>\n<li>one</li>\n<li>two</li>\n</ul>\n"
+    );
+    assert_eq!(
+        render("```sh\necho <hi>\n```\n\n> quoted\n"),
+        "<pre><code class=\"language-sh\">echo &lt;hi&gt;\n</code></pre>\n\
+         <blockquote>\n<p>quoted</p>\n</blockquote>\n"
+    );
+}
+
+#[test]
+fn render_loose_lists() {
+    assert_eq!(
+        render("1. one\n\n2. two\n   - tight\n"),
+        "<ol>\n<li>\n<p>one</p>\n</li>\n<li>\n<p>two</p>\n<ul>\n<li>tight</li>\n</ul>\n</li>\n</ol>\n"
+    );
+}
+
+#[test]
+fn escape_text_and_attributes() {
+    assert_eq!(
+        render("Tom & \"Jerry\" <b> [it's](https://a.com/?q=\"x\"&y=<z>)"),
+        "<p>Tom &amp; &quot;Jerry&quot; &lt;b&gt; \
+         <a href=\"https://a.com/?q=&quot;x&quot;&amp;y=&lt;z&gt;\">it&#39;s</a></p>\n"
+    );
+    assert_eq!(
+        render("![\"><script>](x.png)"),
+        "<p><img src=\"x.png\" alt=\"&quot;&gt;&lt;script&gt;\"></p>\n"
+    );
+}
+
+#[test]
+fn leave_out_urls_that_run_script() {
+    let document = md_parser::parse(
+        "[a](javascript:alert(1)) [b](JavaScript:x) [c](/js:x) ![d](data:image/png) ![e](e.png)\n\n\
+         ![f](vbscript:x)",
+    );
+    let options = Options {
+        safe_urls: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        html::render(&document, &options),
+        "<p><a>a</a> <a>b</a> <a href=\"/js:x\">c</a> <img alt=\"d\"> <img src=\"e.png\" alt=\"e\"></p>\n\
+         <p><img alt=\"f\"></p>\n"
+    );
+    assert!(html::render(&document, &Options::default()).contains("href=\"javascript:alert(1)\""));
+}
+
+#[test]
+fn heading_ids() {
+    let document = md_parser::parse("# Getting *started*\n\n## FAQ & Help\n\n# Getting started\n");
+    let options = Options {
+        heading_ids: true,
+        id_prefix: "doc-".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        html::render(&document, &options),
+        "<h1 id=\"doc-getting-started\">Getting <em>started</em></h1>\n\
+         <h2 id=\"doc-faq--help\">FAQ &amp; Help</h2>\n\
+         <h1 id=\"doc-getting-started-1\">Getting started</h1>\n"
+    );
+
+    let mut ids = HeadingIds::new();
+    assert_eq!(ids.next("Über uns!"), "über-uns");
+    assert_eq!(ids.next("Über uns"), "über-uns-1");
+}
+
+#[test]
+fn write_to_io() {
+    let document = md_parser::parse("# Title\n\nText");
+    let mut out = Vec::new();
+    html::write(&document, &Options::default(), &mut out).unwrap();
+    assert_eq!(out, b"<h1>Title</h1>\n<p>Text</p>\n");
+
+    struct Full;
+    impl io::Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::StorageFull.into())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let error = html::write(&document, &Options::default(), &mut Full).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+}
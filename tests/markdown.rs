@@ -0,0 +1,347 @@
+use md_parser::{Alignment, Document, HeaderLevel, Line, ListItem, Span, Token};
+use proptest::prelude::*;
+
+const NO_SPAN: Span = Span {
+    start: 0,
+    end: 0,
+    line: 0,
+    column: 0,
+};
+
+const DOCUMENT: &str = "# Links
+
+See **docs** [home](https://a.com) and *[other](http://b.org)*,
+or `the *code*`.
+
+![logo](https://a.com/logo.png)
+
+## Details
+
+1. **bold** [item link](/relative)
+2. plain
+   - nested
+
+> quoted *text*
+>
+> - in a list
+
+| Name | Value |
+| :-- | --: |
+| `a\\|b` | 1 |
+
+```rust
+let x = `a`;
+```
+";
+
+fn without_spans(mut document: Document) -> Document {
+    document.iter_mut().for_each(Line::clear_spans);
+    document
+}
+
+fn round_trip(document: &Document) -> Document {
+    without_spans(md_parser::parse(&md_parser::to_markdown(document)))
+}
+
+#[test]
+fn write_canonical_markdown() {
+    let document = md_parser::parse(DOCUMENT);
+    assert_eq!(md_parser::to_markdown(&document), DOCUMENT);
+    assert_eq!(round_trip(&document), without_spans(document));
+
+    let document = md_parser::parse("Some __bold__ and _italic_\n\n* one\n+ two\n\n~~~\ncode\n~~~");
+    assert_eq!(
+        md_parser::to_markdown(&document),
+        "Some **bold** and *italic*\n\n- one\n- two\n\n```\ncode\n```\n"
+    );
+}
+
+#[test]
+fn escape_markup_in_text() {
+    let text = |text: &str| vec![Token::Regular(text.into(), NO_SPAN)];
+    let document = vec![
+        Line::Paragraph(text("# not a *heading* [or](link) `code` \\"), NO_SPAN),
+        Line::Blank(NO_SPAN),
+        Line::Paragraph(text("1. not a list"), NO_SPAN),
+        Line::Blank(NO_SPAN),
+        Line::Paragraph(
+            vec![
+                Token::Regular("!".into(), NO_SPAN),
+                Token::Link {
+                    label: text("not an image"),
+                    url: "x.png".into(),
+                    span: NO_SPAN,
+                },
+                Token::Regular(" nor!".into(), NO_SPAN),
+                Token::Link {
+                    label: text("this"),
+                    url: "y.png".into(),
+                    span: NO_SPAN,
+                },
+            ],
+            NO_SPAN,
+        ),
+    ];
+    assert_eq!(
+        md_parser::to_markdown(&document),
+        "\\# not a \\*heading\\* \\[or\\](link) \\`code\\` \\\\\n\n1\\. not a list\n\n\\![not an image](x.png) nor\\![this](y.png)\n"
+    );
+    assert_eq!(round_trip(&document), document);
+}
+
+#[test]
+fn escape_lines_that_would_make_a_table() {
+    let document = md_parser::parse("\\| a \\|\n\\|---\\|\n\na | b\n\\:--|--");
+    assert_eq!(
+        md_parser::to_markdown(&document),
+        "\\| a |\n\\|---|\n\na | b\n\\:--|--\n"
+    );
+    assert_eq!(round_trip(&document), without_spans(document));
+}
+
+#[test]
+fn keep_loose_lists_loose() {
+    let loose = "- one\n\n- two\n  - tight\n  - nested\n\n- three\n";
+    let document = md_parser::parse(loose);
+    assert_eq!(md_parser::to_markdown(&document), loose);
+    assert_eq!(round_trip(&document), without_spans(document));
+}
+
+#[test]
+fn edit_and_write_back() {
+    let mut document = md_parser::parse("# Title\n\nSee [docs](http://a.com/docs).\n");
+    for line in &mut document {
+        match line {
+            Line::Header { level, .. } => *level = HeaderLevel::H2,
+            Line::Paragraph(tokens, _) => {
+                for token in tokens {
+                    if let Token::Link { url, .. } = token {
+                        *url = url.replace("http://", "https://");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(
+        md_parser::to_markdown(&document),
+        "## Title\n\nSee [docs](https://a.com/docs).\n"
+    );
+}
+
+#[test]
+fn write_to_io() {
+    let document = md_parser::parse("# Title\n\n- item\n");
+    let mut out = Vec::new();
+    md_parser::markdown::write(&document, &mut out).unwrap();
+    assert_eq!(out, b"# Title\n\n- item\n");
+}
+
+/// Words with the characters markdown gives meaning to between them, or
+/// what would be the delimiter row of a table under the line before.
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => "[|:]?[a-z0-9]{1,5}([ *_`\\[\\]\\\\#!|~+.<>&-]?[a-z0-9]{1,5}){0,3}\\|?",
+        1 => "\\|?:?-{1,3}:?(\\|:?-{1,3}:?){0,2}\\|?",
+    ]
+}
+
+fn url() -> impl Strategy<Value = String> {
+    "(https?://[a-z]{1,5}\\.com)?(/([a-z0-9_*]|\\(\\)){0,5}){0,2}"
+}
+
+/// Text, with inline nodes between its words.
+fn interleave(first: String, rest: Vec<(Token, String)>) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = first;
+    for (token, next) in rest {
+        tokens.push(Token::Regular(format!("{} ", text), NO_SPAN));
+        tokens.push(token);
+        text = format!(" {}", next);
+    }
+    tokens.push(Token::Regular(text, NO_SPAN));
+    tokens
+}
+
+fn tokens() -> impl Strategy<Value = Vec<Token>> {
+    let code = "[a-z0-9*_`|](([a-z0-9 *_`|]|\\\\[a-z]){0,6}[a-z0-9*_`|])?"
+        .prop_map(|code| Token::InlineCode(vec![Token::Regular(code, NO_SPAN)], NO_SPAN));
+    let node = code.prop_recursive(3, 12, 3, |inner| {
+        let content = prop_oneof![
+            (text(), prop::collection::vec((inner.clone(), text()), 0..3))
+                .prop_map(|(first, rest)| interleave(first, rest)),
+            inner.prop_map(|token| vec![token]),
+        ];
+        prop_oneof![
+            content
+                .clone()
+                .prop_map(|tokens| Token::Bold(tokens, NO_SPAN)),
+            content
+                .clone()
+                .prop_map(|tokens| Token::Italic(tokens, NO_SPAN)),
+            (content.clone(), url()).prop_map(|(label, url)| Token::Link {
+                label,
+                url,
+                span: NO_SPAN
+            }),
+            (content, url()).prop_map(|(label, url)| Token::Image {
+                label,
+                url,
+                span: NO_SPAN
+            }),
+        ]
+    });
+    let node = node.prop_filter("emphasis stacked too deep", |node| {
+        stacked(node, <[Token]>::first) <= 2 && stacked(node, <[Token]>::last) <= 2
+    });
+    (text(), prop::collection::vec((node, text()), 0..3))
+        .prop_map(|(first, rest)| interleave(first, rest))
+}
+
+/// How many emphasis start (or end) with each other from `token` inwards,
+/// anywhere inside it.
+fn stacked(token: &Token, end: fn(&[Token]) -> Option<&Token>) -> usize {
+    match token {
+        Token::Bold(tokens, _) | Token::Italic(tokens, _) => {
+            let inner = end(tokens).map_or(0, |token| stacked_at(token, end));
+            let nested = tokens.iter().map(|token| stacked(token, end)).max();
+            (1 + inner).max(nested.unwrap_or(0))
+        }
+        Token::Link { label, .. } | Token::Image { label, .. } => label
+            .iter()
+            .map(|token| stacked(token, end))
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn stacked_at(token: &Token, end: fn(&[Token]) -> Option<&Token>) -> usize {
+    match token {
+        Token::Bold(tokens, _) | Token::Italic(tokens, _) => {
+            1 + end(tokens).map_or(0, |token| stacked_at(token, end))
+        }
+        _ => 0,
+    }
+}
+
+fn paragraph() -> impl Strategy<Value = Line> {
+    prop::collection::vec(tokens(), 1..3).prop_map(|lines| {
+        let tokens = lines.join(&Token::SoftBreak(NO_SPAN));
+        Line::Paragraph(tokens, NO_SPAN)
+    })
+}
+
+fn header() -> impl Strategy<Value = Line> {
+    use HeaderLevel::*;
+    let level = prop::sample::select(vec![H1, H2, H3, H4, H5, H6]);
+    (level, tokens()).prop_map(|(level, tokens)| Line::Header {
+        level,
+        tokens,
+        span: NO_SPAN,
+    })
+}
+
+fn list(children: impl Strategy<Value = Vec<Line>>) -> impl Strategy<Value = Line> {
+    let item = (tokens(), children).prop_map(|(tokens, children)| ListItem {
+        tokens,
+        children,
+        span: NO_SPAN,
+    });
+    let items = prop::collection::vec(item, 1..4);
+    (any::<bool>(), 0..20u64, any::<bool>(), items).prop_map(|(ordered, start, loose, items)| {
+        Line::List {
+            ordered,
+            start: if ordered { start } else { 1 },
+            // Only blank lines between items make a list loose.
+            loose: loose && items.len() > 1,
+            items,
+            span: NO_SPAN,
+        }
+    })
+}
+
+fn table() -> impl Strategy<Value = Line> {
+    use Alignment::*;
+    let alignment = prop::sample::select(vec![None, Left, Center, Right]);
+    let cell = prop_oneof![1 => Just(Vec::new()), 4 => tokens()];
+    prop::collection::vec((alignment, cell.clone()), 1..4).prop_flat_map(move |columns| {
+        let width = columns.len();
+        let (alignments, header): (Vec<_>, Vec<_>) = columns.into_iter().unzip();
+        let row = prop::collection::vec(cell.clone(), width);
+        prop::collection::vec(row, 0..3).prop_map(move |rows| Line::Table {
+            alignments: alignments.clone(),
+            header: header.clone(),
+            rows,
+            span: NO_SPAN,
+        })
+    })
+}
+
+fn code_block() -> impl Strategy<Value = Line> {
+    let lines = prop::collection::vec("[ -~]{0,12}", 0..4);
+    ("[a-z]{0,5}", lines).prop_map(|(info, lines)| Line::CodeBlock {
+        info,
+        content: lines.iter().map(|line| format!("{}\n", line)).collect(),
+        span: NO_SPAN,
+    })
+}
+
+/// Whether no list follows a list of the same kind, which would be read
+/// back as one list.
+fn lists_apart(blocks: &[Line]) -> bool {
+    blocks.windows(2).all(|pair| match pair {
+        [Line::List { ordered: a, .. }, Line::List { ordered: b, .. }] => a != b,
+        _ => true,
+    })
+}
+
+/// Blocks separated by blank lines, as they are parsed.
+fn blocks(block: impl Strategy<Value = Line>, max: usize) -> impl Strategy<Value = Vec<Line>> {
+    let blocks = prop::collection::vec(block, 1..max);
+    blocks
+        .prop_filter("lists run together", |blocks| lists_apart(blocks))
+        .prop_map(|blocks| {
+            let mut lines = Vec::new();
+            for block in blocks {
+                if !lines.is_empty() {
+                    lines.push(Line::Blank(NO_SPAN));
+                }
+                lines.push(block);
+            }
+            lines
+        })
+}
+
+fn document() -> impl Strategy<Value = Document> {
+    let image = (tokens(), url()).prop_map(|(label, url)| Line::Image {
+        label,
+        url,
+        span: NO_SPAN,
+    });
+    let child = prop_oneof![paragraph(), code_block(), list(Just(Vec::new()))];
+    let nested = prop::collection::vec(child, 0..3)
+        .prop_filter("lists run together", |children| lists_apart(children));
+    let quote = blocks(prop_oneof![paragraph(), header(), code_block(), table()], 3)
+        .prop_map(|lines| Line::Blockquote(lines, NO_SPAN));
+    blocks(
+        prop_oneof![
+            header(),
+            paragraph(),
+            image,
+            code_block(),
+            list(nested),
+            quote,
+            table(),
+        ],
+        6,
+    )
+}
+
+proptest! {
+    #[test]
+    fn parse_written_markdown(document in document()) {
+        let markdown = md_parser::to_markdown(&document);
+        prop_assert_eq!(round_trip(&document), document, "markdown:\n{}", markdown);
+    }
+}
@@ -1,4 +1,19 @@
-use md_parser::{Builder, HeaderLevel, Lexer, Line, Parser, Token};
+use md_parser::{
+    Alignment, Builder, Document, Event, HeaderLevel, Lexer, Line, ListItem, ParseError,
+    ParseOptions, Parser, Recovery, Span, State, Token, TokenCollector,
+};
+
+const NO_SPAN: Span = Span {
+    start: 0,
+    end: 0,
+    line: 0,
+    column: 0,
+};
+
+fn without_spans(mut document: Document) -> Document {
+    document.iter_mut().for_each(Line::clear_spans);
+    document
+}
 
 #[test]
 fn parse_header() {
@@ -16,31 +31,37 @@ fn parse_header() {
     use HeaderLevel::*;
     use Token::*;
     assert_eq!(
-        builder.get_document(),
+        without_spans(builder.get_document()),
         vec![
             Line::Header {
                 level: H1,
-                tokens: vec![Regular("H1".into())]
+                tokens: vec![Regular("H1".into(), NO_SPAN)],
+                span: NO_SPAN
             },
             Line::Header {
                 level: H2,
-                tokens: vec![Regular("H2".into())]
+                tokens: vec![Regular("H2".into(), NO_SPAN)],
+                span: NO_SPAN
             },
             Line::Header {
                 level: H3,
-                tokens: vec![Regular("H3".into())]
+                tokens: vec![Regular("H3".into(), NO_SPAN)],
+                span: NO_SPAN
             },
             Line::Header {
                 level: H4,
-                tokens: vec![Regular("H4".into())]
+                tokens: vec![Regular("H4".into(), NO_SPAN)],
+                span: NO_SPAN
             },
             Line::Header {
                 level: H5,
-                tokens: vec![Regular("H5".into())]
+                tokens: vec![Regular("H5".into(), NO_SPAN)],
+                span: NO_SPAN
             },
             Line::Header {
                 level: H6,
-                tokens: vec![Regular("H6".into())]
+                tokens: vec![Regular("H6".into(), NO_SPAN)],
+                span: NO_SPAN
             }
         ]
     );
@@ -52,31 +73,49 @@ fn parse_bold() {
     let mut parser = Parser::new(&mut builder);
     let mut lexer = Lexer::new(&mut parser);
 
-    lexer.lex("**bold**");
-    lexer.lex("regular **bold** word");
-    lexer.lex("and __another__ bold word");
-    lexer.lex("**bold with spaces**");
+    lexer.lex(
+        "**bold**\n\nregular **bold** word\n\nand __another__ bold word\n\n**bold with spaces**",
+    );
 
     assert_eq!(
-        builder.get_document(),
+        without_spans(builder.get_document()),
         &[
-            Line::Paragraph(vec![Token::Bold(vec![Token::Regular("bold".into())])]),
-            Line::Paragraph(vec![
-                Token::Regular("regular".into()),
-                Token::Bold(vec![Token::Regular("bold".into())]),
-                Token::Regular("word".into())
-            ]),
-            Line::Paragraph(vec![
-                Token::Regular("and".to_string()),
-                Token::Bold(vec![Token::Regular("another".to_string())]),
-                Token::Regular("bold".to_string()),
-                Token::Regular("word".to_string())
-            ]),
-            Line::Paragraph(vec![Token::Bold(vec![
-                Token::Regular("bold".to_string()),
-                Token::Regular("with".to_string()),
-                Token::Regular("spaces".to_string())
-            ])]),
+            Line::Paragraph(
+                vec![Token::Bold(
+                    vec![Token::Regular("bold".into(), NO_SPAN)],
+                    NO_SPAN
+                )],
+                NO_SPAN
+            ),
+            Line::Blank(NO_SPAN),
+            Line::Paragraph(
+                vec![
+                    Token::Regular("regular ".into(), NO_SPAN),
+                    Token::Bold(vec![Token::Regular("bold".into(), NO_SPAN)], NO_SPAN),
+                    Token::Regular(" word".into(), NO_SPAN)
+                ],
+                NO_SPAN
+            ),
+            Line::Blank(NO_SPAN),
+            Line::Paragraph(
+                vec![
+                    Token::Regular("and ".to_string(), NO_SPAN),
+                    Token::Bold(
+                        vec![Token::Regular("another".to_string(), NO_SPAN)],
+                        NO_SPAN
+                    ),
+                    Token::Regular(" bold word".to_string(), NO_SPAN)
+                ],
+                NO_SPAN
+            ),
+            Line::Blank(NO_SPAN),
+            Line::Paragraph(
+                vec![Token::Bold(
+                    vec![Token::Regular("bold with spaces".to_string(), NO_SPAN)],
+                    NO_SPAN
+                )],
+                NO_SPAN
+            ),
         ]
     );
 }
@@ -87,33 +126,49 @@ fn parse_italic() {
     let mut parser = Parser::new(&mut builder);
     let mut lexer = Lexer::new(&mut parser);
 
-    lexer.lex("*italic*");
-    lexer.lex("regular *italic* word");
-    lexer.lex("and _another_ italic word");
-    lexer.lex("*italic with spaces*");
+    lexer.lex(
+        "*italic*\n\nregular *italic* word\n\nand _another_ italic word\n\n*italic with spaces*",
+    );
 
     assert_eq!(
-        builder.get_document(),
+        without_spans(builder.get_document()),
         &[
-            Line::Paragraph(vec![Token::Italic(vec![Token::Regular(
-                "italic".to_string()
-            )])]),
-            Line::Paragraph(vec![
-                Token::Regular("regular".to_string()),
-                Token::Italic(vec![Token::Regular("italic".to_string())]),
-                Token::Regular("word".to_string())
-            ]),
-            Line::Paragraph(vec![
-                Token::Regular("and".to_string()),
-                Token::Italic(vec![Token::Regular("another".to_string())]),
-                Token::Regular("italic".to_string()),
-                Token::Regular("word".to_string())
-            ]),
-            Line::Paragraph(vec![Token::Italic(vec![
-                Token::Regular("italic".to_string()),
-                Token::Regular("with".to_string()),
-                Token::Regular("spaces".to_string())
-            ])]),
+            Line::Paragraph(
+                vec![Token::Italic(
+                    vec![Token::Regular("italic".to_string(), NO_SPAN)],
+                    NO_SPAN
+                )],
+                NO_SPAN
+            ),
+            Line::Blank(NO_SPAN),
+            Line::Paragraph(
+                vec![
+                    Token::Regular("regular ".to_string(), NO_SPAN),
+                    Token::Italic(vec![Token::Regular("italic".to_string(), NO_SPAN)], NO_SPAN),
+                    Token::Regular(" word".to_string(), NO_SPAN)
+                ],
+                NO_SPAN
+            ),
+            Line::Blank(NO_SPAN),
+            Line::Paragraph(
+                vec![
+                    Token::Regular("and ".to_string(), NO_SPAN),
+                    Token::Italic(
+                        vec![Token::Regular("another".to_string(), NO_SPAN)],
+                        NO_SPAN
+                    ),
+                    Token::Regular(" italic word".to_string(), NO_SPAN)
+                ],
+                NO_SPAN
+            ),
+            Line::Blank(NO_SPAN),
+            Line::Paragraph(
+                vec![Token::Italic(
+                    vec![Token::Regular("italic with spaces".to_string(), NO_SPAN)],
+                    NO_SPAN
+                )],
+                NO_SPAN
+            ),
         ]
     );
 }
@@ -124,32 +179,35 @@ fn parse_link() {
     let mut parser = Parser::new(&mut builder);
     let mut lexer = Lexer::new(&mut parser);
 
-    lexer.lex("a regular [Link](https://a.com)");
-    lexer.lex("and [Another Link](https://b.com) with spaces");
+    lexer.lex("a regular [Link](https://a.com)\n\nand [Another Link](https://b.com) with spaces");
 
     assert_eq!(
-        builder.get_document(),
+        without_spans(builder.get_document()),
         &[
-            Line::Paragraph(vec![
-                Token::Regular("a".to_string()),
-                Token::Regular("regular".to_string()),
-                Token::Link {
-                    label: vec![Token::Regular("Link".to_string())],
-                    url: "https://a.com".to_string()
-                }
-            ]),
-            Line::Paragraph(vec![
-                Token::Regular("and".to_string()),
-                Token::Link {
-                    label: vec![
-                        Token::Regular("Another".to_string()),
-                        Token::Regular("Link".to_string())
-                    ],
-                    url: "https://b.com".to_string()
-                },
-                Token::Regular("with".to_string()),
-                Token::Regular("spaces".to_string())
-            ])
+            Line::Paragraph(
+                vec![
+                    Token::Regular("a regular ".to_string(), NO_SPAN),
+                    Token::Link {
+                        label: vec![Token::Regular("Link".to_string(), NO_SPAN)],
+                        url: "https://a.com".to_string(),
+                        span: NO_SPAN
+                    }
+                ],
+                NO_SPAN
+            ),
+            Line::Blank(NO_SPAN),
+            Line::Paragraph(
+                vec![
+                    Token::Regular("and ".to_string(), NO_SPAN),
+                    Token::Link {
+                        label: vec![Token::Regular("Another Link".to_string(), NO_SPAN)],
+                        url: "https://b.com".to_string(),
+                        span: NO_SPAN
+                    },
+                    Token::Regular(" with spaces".to_string(), NO_SPAN)
+                ],
+                NO_SPAN
+            )
         ]
     );
 }
@@ -163,11 +221,18 @@ fn parse_bold_link() {
     lexer.lex("**[Bold](https://a.com)**");
 
     assert_eq!(
-        builder.get_document(),
-        &[Line::Paragraph(vec![Token::Bold(vec![Token::Link {
-            label: vec![Token::Regular("Bold".to_string()),],
-            url: "https://a.com".to_string()
-        }])])]
+        without_spans(builder.get_document()),
+        &[Line::Paragraph(
+            vec![Token::Bold(
+                vec![Token::Link {
+                    label: vec![Token::Regular("Bold".to_string(), NO_SPAN),],
+                    url: "https://a.com".to_string(),
+                    span: NO_SPAN
+                }],
+                NO_SPAN
+            )],
+            NO_SPAN
+        )]
     );
 }
 
@@ -179,49 +244,89 @@ fn lex_image() {
     lexer.lex("![image](https://www.a.com)");
 
     assert_eq!(
-        builder.get_document(),
+        without_spans(builder.get_document()),
         vec![Line::Image {
-            label: vec![Token::Regular("image".into())],
-            url: "https://www.a.com".to_string()
+            label: vec![Token::Regular("image".into(), NO_SPAN)],
+            url: "https://www.a.com".to_string(),
+            span: NO_SPAN
         }]
     );
 }
 
+#[test]
+fn parse_inline_images() {
+    let document =
+        md_parser::parse("![x](y.png) trailing\n\nsee ![*alt*](z.png).\n\n![a] and more");
+
+    use Line::*;
+    use Token::*;
+    let image = |label, url: &str| Token::Image {
+        label,
+        url: url.to_string(),
+        span: NO_SPAN,
+    };
+    assert_eq!(
+        without_spans(document),
+        vec![
+            Paragraph(
+                vec![
+                    image(vec![Regular("x".into(), NO_SPAN)], "y.png"),
+                    Regular(" trailing".into(), NO_SPAN)
+                ],
+                NO_SPAN
+            ),
+            Blank(NO_SPAN),
+            Paragraph(
+                vec![
+                    Regular("see ".into(), NO_SPAN),
+                    image(
+                        vec![Italic(vec![Regular("alt".into(), NO_SPAN)], NO_SPAN)],
+                        "z.png"
+                    ),
+                    Regular(".".into(), NO_SPAN)
+                ],
+                NO_SPAN
+            ),
+            Blank(NO_SPAN),
+            Paragraph(vec![Regular("![a] and more".into(), NO_SPAN)], NO_SPAN)
+        ]
+    );
+}
+
 #[test]
 fn parse_inline_code() {
     let mut builder = Builder::new();
     let mut parser = Parser::new(&mut builder);
     let mut lexer = Lexer::new(&mut parser);
 
-    lexer.lex("regular `code` word");
-    lexer.lex("a `code with spaces`.");
+    lexer.lex("regular `code` word\n\na `code with spaces`.");
 
     use Line::*;
     use Token::*;
     assert_eq!(
-        builder.get_document(),
+        without_spans(builder.get_document()),
         vec![
             Paragraph(
                 [
-                    Regular("regular".into()),
-                    InlineCode([Regular("code".into())].to_vec()),
-                    Regular("word".into())
+                    Regular("regular ".into(), NO_SPAN),
+                    InlineCode([Regular("code".into(), NO_SPAN)].to_vec(), NO_SPAN),
+                    Regular(" word".into(), NO_SPAN)
                 ]
-                .to_vec()
+                .to_vec(),
+                NO_SPAN
             ),
+            Blank(NO_SPAN),
             Paragraph(
                 [
-                    Regular("a".into()),
+                    Regular("a ".into(), NO_SPAN),
                     InlineCode(
-                        [
-                            Regular("code".into()),
-                            Regular("with".into()),
-                            Regular("spaces.".into())
-                        ]
-                        .to_vec()
-                    )
+                        [Regular("code with spaces".into(), NO_SPAN)].to_vec(),
+                        NO_SPAN
+                    ),
+                    Regular(".".into(), NO_SPAN)
                 ]
-                .to_vec()
+                .to_vec(),
+                NO_SPAN
             )
         ]
     );
@@ -238,10 +343,679 @@ fn parse_bold_italic() {
     use Line::*;
     use Token::*;
     assert_eq!(
-        builder.get_document(),
-        vec![Paragraph(vec![Bold(vec![Italic(vec![
-            Regular("strong".into()),
-            Regular("emph".into())
-        ])])])]
+        without_spans(builder.get_document()),
+        vec![Paragraph(
+            vec![Italic(
+                vec![Bold(vec![Regular("strong emph".into(), NO_SPAN)], NO_SPAN)],
+                NO_SPAN
+            )],
+            NO_SPAN
+        )]
+    );
+}
+
+#[test]
+fn parse_code_block() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex("```rust\nfn main() {\n    println!(\"**not bold**\");\n}\n```");
+    lexer.lex("after the code");
+
+    use Line::*;
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![
+            CodeBlock {
+                info: "rust".into(),
+                content: "fn main() {\n    println!(\"**not bold**\");\n}\n".into(),
+                span: NO_SPAN
+            },
+            Paragraph(vec![Regular("after the code".into(), NO_SPAN)], NO_SPAN)
+        ]
+    );
+}
+
+#[test]
+fn parse_list() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex("- a [link](https://a.com)\n- b\n  1. nested\n  2. **bold**\n- c\n\n3. three");
+
+    use Line::*;
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![
+            List {
+                ordered: false,
+                start: 1,
+                loose: false,
+                items: vec![
+                    ListItem {
+                        tokens: vec![
+                            Regular("a ".into(), NO_SPAN),
+                            Link {
+                                label: vec![Regular("link".into(), NO_SPAN)],
+                                url: "https://a.com".into(),
+                                span: NO_SPAN
+                            }
+                        ],
+                        children: vec![],
+                        span: NO_SPAN
+                    },
+                    ListItem {
+                        tokens: vec![Regular("b".into(), NO_SPAN)],
+                        children: vec![List {
+                            ordered: true,
+                            start: 1,
+                            loose: false,
+                            items: vec![
+                                ListItem {
+                                    tokens: vec![Regular("nested".into(), NO_SPAN)],
+                                    children: vec![],
+                                    span: NO_SPAN
+                                },
+                                ListItem {
+                                    tokens: vec![Bold(
+                                        vec![Regular("bold".into(), NO_SPAN)],
+                                        NO_SPAN
+                                    )],
+                                    children: vec![],
+                                    span: NO_SPAN
+                                }
+                            ],
+                            span: NO_SPAN
+                        }],
+                        span: NO_SPAN
+                    },
+                    ListItem {
+                        tokens: vec![Regular("c".into(), NO_SPAN)],
+                        children: vec![],
+                        span: NO_SPAN
+                    }
+                ],
+                span: NO_SPAN
+            },
+            Blank(NO_SPAN),
+            List {
+                ordered: true,
+                start: 3,
+                loose: false,
+                items: vec![ListItem {
+                    tokens: vec![Regular("three".into(), NO_SPAN)],
+                    children: vec![],
+                    span: NO_SPAN
+                }],
+                span: NO_SPAN
+            }
+        ]
+    );
+}
+
+#[test]
+fn parse_blockquote() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex("> # Quote\n> text\nlazy\n> > - nested\n>\n> back\n\nafter");
+
+    use HeaderLevel::*;
+    use Line::*;
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![
+            Blockquote(
+                vec![
+                    Header {
+                        level: H1,
+                        tokens: vec![Regular("Quote".into(), NO_SPAN)],
+                        span: NO_SPAN
+                    },
+                    Paragraph(
+                        vec![
+                            Regular("text".into(), NO_SPAN),
+                            SoftBreak(NO_SPAN),
+                            Regular("lazy".into(), NO_SPAN)
+                        ],
+                        NO_SPAN
+                    ),
+                    Blockquote(
+                        vec![List {
+                            ordered: false,
+                            start: 1,
+                            loose: false,
+                            items: vec![ListItem {
+                                tokens: vec![Regular("nested".into(), NO_SPAN)],
+                                children: vec![],
+                                span: NO_SPAN
+                            }],
+                            span: NO_SPAN
+                        }],
+                        NO_SPAN
+                    ),
+                    Blank(NO_SPAN),
+                    Paragraph(vec![Regular("back".into(), NO_SPAN)], NO_SPAN)
+                ],
+                NO_SPAN
+            ),
+            Blank(NO_SPAN),
+            Paragraph(vec![Regular("after".into(), NO_SPAN)], NO_SPAN)
+        ]
+    );
+}
+
+#[test]
+fn parse_table() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex(
+        "| Option | Default |\n| --- | :---: |\n| `debug` | *false* |\n| [docs](https://a.com) |",
+    );
+
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![Line::Table {
+            alignments: vec![Alignment::None, Alignment::Center],
+            header: vec![
+                vec![Regular("Option".into(), NO_SPAN)],
+                vec![Regular("Default".into(), NO_SPAN)]
+            ],
+            rows: vec![
+                vec![
+                    vec![InlineCode(vec![Regular("debug".into(), NO_SPAN)], NO_SPAN)],
+                    vec![Italic(vec![Regular("false".into(), NO_SPAN)], NO_SPAN)]
+                ],
+                vec![
+                    vec![Link {
+                        label: vec![Regular("docs".into(), NO_SPAN)],
+                        url: "https://a.com".into(),
+                        span: NO_SPAN
+                    }],
+                    vec![]
+                ]
+            ],
+            span: NO_SPAN
+        }]
+    );
+}
+
+#[test]
+fn parse_multiline_paragraph() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex("a paragraph **wrapped**\nacross [lines](https://a.com)\n\nnext\n- item\n  continued\n# Header");
+
+    use HeaderLevel::*;
+    use Line::*;
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![
+            Paragraph(
+                vec![
+                    Regular("a paragraph ".into(), NO_SPAN),
+                    Bold(vec![Regular("wrapped".into(), NO_SPAN)], NO_SPAN),
+                    SoftBreak(NO_SPAN),
+                    Regular("across ".into(), NO_SPAN),
+                    Link {
+                        label: vec![Regular("lines".into(), NO_SPAN)],
+                        url: "https://a.com".into(),
+                        span: NO_SPAN
+                    }
+                ],
+                NO_SPAN
+            ),
+            Blank(NO_SPAN),
+            Paragraph(vec![Regular("next".into(), NO_SPAN)], NO_SPAN),
+            List {
+                ordered: false,
+                start: 1,
+                loose: false,
+                items: vec![ListItem {
+                    tokens: vec![
+                        Regular("item".into(), NO_SPAN),
+                        SoftBreak(NO_SPAN),
+                        Regular("continued".into(), NO_SPAN)
+                    ],
+                    children: vec![],
+                    span: NO_SPAN
+                }],
+                span: NO_SPAN
+            },
+            Header {
+                level: H1,
+                tokens: vec![Regular("Header".into(), NO_SPAN)],
+                span: NO_SPAN
+            }
+        ]
+    );
+}
+
+#[test]
+fn parse_nested_emphasis() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex("*an **important** [link](https://a.com)*, really.");
+
+    use Line::*;
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![Paragraph(
+            vec![
+                Italic(
+                    vec![
+                        Regular("an ".into(), NO_SPAN),
+                        Bold(vec![Regular("important".into(), NO_SPAN)], NO_SPAN),
+                        Regular(" ".into(), NO_SPAN),
+                        Link {
+                            label: vec![Regular("link".into(), NO_SPAN)],
+                            url: "https://a.com".into(),
+                            span: NO_SPAN
+                        }
+                    ],
+                    NO_SPAN
+                ),
+                Regular(", really.".into(), NO_SPAN)
+            ],
+            NO_SPAN
+        )]
+    );
+}
+
+#[test]
+fn parse_spans() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex("> intro\n> see [docs](https://a.com)\n\n- one\n- **two**");
+
+    let document = builder.get_document();
+    let span = |start, end, line, column| Span {
+        start,
+        end,
+        line,
+        column,
+    };
+
+    let Line::Blockquote(quoted, quote_span) = &document[0] else {
+        panic!("expected a blockquote, got {:?}", document[0]);
+    };
+    assert_eq!(*quote_span, span(0, 35, 1, 1));
+    assert_eq!(quoted[0].span(), span(2, 35, 1, 3));
+
+    let Line::Paragraph(tokens, _) = &quoted[0] else {
+        panic!("expected a paragraph, got {:?}", quoted[0]);
+    };
+    assert_eq!(tokens[1], Token::SoftBreak(span(7, 8, 1, 8)));
+    assert_eq!(tokens[3].span(), span(14, 35, 2, 7));
+
+    assert_eq!(document[1], Line::Blank(span(36, 37, 3, 1)));
+
+    let Line::List {
+        items,
+        span: list_span,
+        ..
+    } = &document[2]
+    else {
+        panic!("expected a list, got {:?}", document[2]);
+    };
+    assert_eq!(*list_span, span(37, 52, 4, 1));
+    assert_eq!(items[1].span, span(43, 52, 5, 1));
+    assert_eq!(items[1].tokens[0].span(), span(45, 52, 5, 3));
+}
+
+#[test]
+fn parse_emphasis_in_header() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex("# **bold header**\n## `code` and *italic*\n**");
+
+    use HeaderLevel::*;
+    use Line::*;
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![
+            Header {
+                level: H1,
+                tokens: vec![Bold(vec![Regular("bold header".into(), NO_SPAN)], NO_SPAN)],
+                span: NO_SPAN
+            },
+            Header {
+                level: H2,
+                tokens: vec![
+                    InlineCode(vec![Regular("code".into(), NO_SPAN)], NO_SPAN),
+                    Regular(" and ".into(), NO_SPAN),
+                    Italic(vec![Regular("italic".into(), NO_SPAN)], NO_SPAN)
+                ],
+                span: NO_SPAN
+            },
+            Paragraph(vec![Regular("**".into(), NO_SPAN)], NO_SPAN)
+        ]
+    );
+}
+
+#[test]
+fn recover_unexpected_event_as_literal() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let span = Span {
+        start: 2,
+        end: 3,
+        line: 1,
+        column: 3,
+    };
+
+    parser.word("a ", NO_SPAN);
+    parser.image(span);
+    parser.word("b", NO_SPAN);
+    parser.line_break(NO_SPAN);
+
+    assert_eq!(
+        parser.errors(),
+        [ParseError {
+            event: Event::Image,
+            state: State::Text,
+            span
+        }]
+    );
+    assert_eq!(parser.finish(), Ok(()));
+
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![Line::Paragraph(
+            vec![
+                Regular("a ".into(), NO_SPAN),
+                Regular("!".into(), NO_SPAN),
+                Regular("b".into(), NO_SPAN)
+            ],
+            NO_SPAN
+        )]
+    );
+}
+
+#[test]
+fn recover_list_item_as_its_marker() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+
+    parser.word("a ", NO_SPAN);
+    parser.ordered_item(3, "3)  ", 0, NO_SPAN);
+    parser.unordered_item("* ", 0, NO_SPAN);
+    parser.word("b", NO_SPAN);
+    parser.line_break(NO_SPAN);
+
+    assert_eq!(parser.errors().len(), 2);
+    assert_eq!(parser.finish(), Ok(()));
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![Line::Paragraph(
+            ["a ", "3)  ", "* ", "b"]
+                .iter()
+                .map(|text| Token::Regular(text.to_string(), NO_SPAN))
+                .collect(),
+            NO_SPAN
+        )]
+    );
+}
+
+#[test]
+fn stop_at_unexpected_event_when_strict() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::with_recovery(&mut builder, Recovery::Strict);
+
+    parser.word("a", NO_SPAN);
+    parser.end_code_block(NO_SPAN);
+    parser.word("b", NO_SPAN);
+    parser.line_break(NO_SPAN);
+
+    let error = parser.finish().unwrap_err();
+    assert_eq!(error.event, Event::EndCodeBlock);
+    assert_eq!(error.state, State::Text);
+    assert_eq!(
+        error.to_string(),
+        "unexpected EndCodeBlock in Text state at line 0, column 0"
+    );
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![Line::Paragraph(
+            vec![Token::Regular("a".into(), NO_SPAN)],
+            NO_SPAN
+        )]
+    );
+}
+
+#[test]
+fn parse_link_at_line_start() {
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    let mut lexer = Lexer::new(&mut parser);
+
+    lexer.lex("[start](https://a.com) of a line\n> [quoted](https://b.com)");
+
+    use Line::*;
+    use Token::*;
+    assert_eq!(
+        without_spans(builder.get_document()),
+        vec![
+            Paragraph(
+                vec![
+                    Link {
+                        label: vec![Regular("start".into(), NO_SPAN)],
+                        url: "https://a.com".into(),
+                        span: NO_SPAN
+                    },
+                    Regular(" of a line".into(), NO_SPAN)
+                ],
+                NO_SPAN
+            ),
+            Blockquote(
+                vec![Paragraph(
+                    vec![Link {
+                        label: vec![Regular("quoted".into(), NO_SPAN)],
+                        url: "https://b.com".into(),
+                        span: NO_SPAN
+                    }],
+                    NO_SPAN
+                )],
+                NO_SPAN
+            )
+        ]
+    );
+}
+
+#[test]
+fn parse_in_one_call() {
+    let input = "# Title\n\nsome *text*";
+
+    let mut builder = Builder::new();
+    let mut parser = Parser::new(&mut builder);
+    Lexer::new(&mut parser).lex(input);
+
+    assert_eq!(md_parser::parse(input), builder.into_document());
+}
+
+#[test]
+fn parse_with_strict_recovery() {
+    let options = ParseOptions {
+        recovery: Recovery::Strict,
+    };
+
+    assert!(md_parser::parse_with_options("# **bold header**", &options).is_ok());
+    assert_eq!(
+        md_parser::parse_with_options("# Title", &options).map(|p| without_spans(p.document)),
+        Ok(vec![Line::Header {
+            level: HeaderLevel::H1,
+            tokens: vec![Token::Regular("Title".into(), NO_SPAN)],
+            span: NO_SPAN
+        }])
+    );
+}
+
+#[test]
+fn parse_with_literal_recovery() {
+    let input = "# Title\n\n- **item**";
+    let parsed = md_parser::parse_with_options(input, &ParseOptions::default()).unwrap();
+
+    assert_eq!(parsed.document, md_parser::parse(input));
+    assert_eq!(parsed.errors, []);
+}
+
+#[test]
+fn parse_loose_list() {
+    let document = md_parser::parse("- a\n\n- b\n\n\n- c\n\n1. one\n\n- d");
+
+    use Line::*;
+    use Token::*;
+    let item = |text: &str| ListItem {
+        tokens: vec![Regular(text.into(), NO_SPAN)],
+        children: vec![],
+        span: NO_SPAN,
+    };
+    assert_eq!(
+        without_spans(document),
+        vec![
+            List {
+                ordered: false,
+                start: 1,
+                loose: true,
+                items: vec![item("a"), item("b"), item("c")],
+                span: NO_SPAN
+            },
+            Blank(NO_SPAN),
+            List {
+                ordered: true,
+                start: 1,
+                loose: false,
+                items: vec![item("one")],
+                span: NO_SPAN
+            },
+            Blank(NO_SPAN),
+            List {
+                ordered: false,
+                start: 1,
+                loose: false,
+                items: vec![item("d")],
+                span: NO_SPAN
+            }
+        ]
+    );
+}
+
+#[test]
+fn parse_list_item_continuation() {
+    let document = md_parser::parse(
+        "- a\n\n  first\n  paragraph\n\n  second\n- b\n  - nested\n\n    deep\n\n  back\n\nafter",
+    );
+
+    use Line::*;
+    use Token::*;
+    let text = |text: &str| Regular(text.into(), NO_SPAN);
+    let paragraph = |tokens: Vec<Token>| Paragraph(tokens, NO_SPAN);
+    assert_eq!(
+        without_spans(document),
+        vec![
+            List {
+                ordered: false,
+                start: 1,
+                loose: false,
+                items: vec![
+                    ListItem {
+                        tokens: vec![text("a")],
+                        children: vec![
+                            paragraph(vec![text("first"), SoftBreak(NO_SPAN), text("paragraph")]),
+                            paragraph(vec![text("second")])
+                        ],
+                        span: NO_SPAN
+                    },
+                    ListItem {
+                        tokens: vec![text("b")],
+                        children: vec![
+                            List {
+                                ordered: false,
+                                start: 1,
+                                loose: false,
+                                items: vec![ListItem {
+                                    tokens: vec![text("nested")],
+                                    children: vec![paragraph(vec![text("deep")])],
+                                    span: NO_SPAN
+                                }],
+                                span: NO_SPAN
+                            },
+                            paragraph(vec![text("back")])
+                        ],
+                        span: NO_SPAN
+                    }
+                ],
+                span: NO_SPAN
+            },
+            Blank(NO_SPAN),
+            paragraph(vec![text("after")])
+        ]
+    );
+}
+
+#[test]
+fn parse_code_blocks_in_lists_and_quotes() {
+    let document = md_parser::parse(
+        "1. Install:\n\n   ```sh\n   cargo build\n   ```\n\n2. Run it\n\n> ```\n> quoted\n> ```",
+    );
+
+    use Line::*;
+    use Token::*;
+    assert_eq!(
+        without_spans(document),
+        vec![
+            List {
+                ordered: true,
+                start: 1,
+                loose: true,
+                items: vec![
+                    ListItem {
+                        tokens: vec![Regular("Install:".into(), NO_SPAN)],
+                        children: vec![CodeBlock {
+                            info: "sh".into(),
+                            content: "cargo build\n".into(),
+                            span: NO_SPAN
+                        }],
+                        span: NO_SPAN
+                    },
+                    ListItem {
+                        tokens: vec![Regular("Run it".into(), NO_SPAN)],
+                        children: vec![],
+                        span: NO_SPAN
+                    }
+                ],
+                span: NO_SPAN
+            },
+            Blank(NO_SPAN),
+            Blockquote(
+                vec![CodeBlock {
+                    info: String::new(),
+                    content: "quoted\n".into(),
+                    span: NO_SPAN
+                }],
+                NO_SPAN
+            )
+        ]
     );
 }
@@ -0,0 +1,949 @@
+use md_parser::query::{
+    query, query_corpus, Format, Functions, Kind, Params, Placeholder, Query, ResultSet, Value,
+};
+use md_parser::{Corpus, LoadError};
+
+const DOCUMENT: &str = "# Links
+
+See **docs** [home](https://a.com) and [other](http://b.org).
+
+![logo](https://a.com/logo.png)
+
+## Details
+
+- **bold** [item link](/relative)
+- plain";
+
+fn texts(rows: Vec<Vec<Value>>) -> Vec<Vec<String>> {
+    rows.into_iter()
+        .map(|row| row.into_iter().map(|value| value.to_string()).collect())
+        .collect()
+}
+
+#[test]
+fn select_field() {
+    let document = md_parser::parse(DOCUMENT);
+    let result = query(&document, "SELECT url FROM image").unwrap();
+
+    assert_eq!(result.columns, vec!["url"]);
+    assert_eq!(
+        result.rows,
+        vec![vec![Value::Text("https://a.com/logo.png".into())]]
+    );
+
+    let document = md_parser::parse("See ![a](a.png) and ![b](b.png).");
+    let result = query(&document, "SELECT url, label FROM image").unwrap();
+    assert_eq!(
+        texts(result.rows),
+        vec![vec!["a.png", "a"], vec!["b.png", "b"]]
+    );
+}
+
+#[test]
+fn select_nodes_after_bold() {
+    let document = md_parser::parse(DOCUMENT);
+    let result = query(&document, "SELECT link WHERE prev IS bold").unwrap();
+
+    assert_eq!(result.columns, vec!["link"]);
+    assert_eq!(texts(result.rows), vec![vec!["home"], vec!["item link"]]);
+
+    let result = query(&document, "select link where prev is not bold").unwrap();
+    assert_eq!(texts(result.rows), vec![vec!["other"]]);
+}
+
+#[test]
+fn select_nodes_as_values() {
+    let document = md_parser::parse(DOCUMENT);
+    let result = query(
+        &document,
+        "SELECT link FROM link WHERE url = 'https://a.com'",
+    )
+    .unwrap();
+
+    let Value::Node(node) = &result.rows[0][0] else {
+        panic!("expected a node, got {:?}", result.rows[0][0]);
+    };
+    assert_eq!(node.kind, Kind::Link);
+    assert_eq!(node.text, "home");
+    assert_eq!((node.span.line, node.span.column), (3, 14));
+}
+
+#[test]
+fn filter_with_conditions() {
+    let document = md_parser::parse(DOCUMENT);
+
+    let result = query(
+        &document,
+        "SELECT level, text AS title FROM header WHERE level >= 2 OR text = 'Links'",
+    )
+    .unwrap();
+    assert_eq!(result.columns, vec!["level", "title"]);
+    assert_eq!(
+        texts(result.rows),
+        vec![vec!["1", "Links"], vec!["2", "Details"]]
+    );
+
+    let result = query(
+        &document,
+        "SELECT text FROM item WHERE NOT (next IS NULL) AND line = 9",
+    )
+    .unwrap();
+    assert_eq!(texts(result.rows), vec![vec!["bold item link"]]);
+
+    let result = query(&document, "SELECT next.text FROM h1").unwrap();
+    assert_eq!(texts(result.rows), vec![vec!["See docs home and other."]]);
+}
+
+#[test]
+fn select_star() {
+    let document = md_parser::parse(DOCUMENT);
+    let result = query(&document, "SELECT * FROM link WHERE url = '/relative'").unwrap();
+
+    assert_eq!(result.columns, vec!["link", "url", "label"]);
+    assert_eq!(
+        texts(result.rows),
+        vec![vec!["item link", "/relative", "item link"]]
+    );
+}
+
+#[test]
+fn reject_invalid_queries() {
+    let error = |text| Query::parse(text).unwrap_err();
+
+    assert_eq!(
+        error("SELECT url").message,
+        "expected FROM, or a node kind as the first column"
+    );
+    assert_eq!(
+        error("SELECT url FROM lnk").message,
+        "unknown node kind `lnk`, did you mean `link`?"
+    );
+    assert_eq!(error("SELECT url FROM lnk").span, 16..19);
+    assert_eq!(
+        error("SELECT uri FROM link").message,
+        "unknown field `uri`, did you mean `url`?"
+    );
+    assert_eq!(
+        error("SELECT link WHERE url IS bold").message,
+        "only nodes can be tested for a kind"
+    );
+    assert_eq!(
+        error("SELECT link WHERE").message,
+        "expected an expression, found the end of the query"
+    );
+    assert_eq!(
+        error("SELECT bold FROM link").message,
+        "cannot select `bold` nodes from `link` nodes"
+    );
+}
+
+const NESTED: &str = "[first](a) then **bold *nested [deep](b)***
+
+> [quoted](c)
+
+- item [x](d)
+  - [nested](e)";
+
+fn urls(text: &str) -> Vec<String> {
+    let document = md_parser::parse(NESTED);
+    let result = query(&document, &format!("SELECT url FROM link WHERE {}", text)).unwrap();
+    result.rows.iter().map(|row| row[0].to_string()).collect()
+}
+
+#[test]
+fn document_order_axes() {
+    assert_eq!(urls("preceding IS bold"), vec!["c", "d", "e"]);
+    assert_eq!(urls("following IS blockquote"), vec!["a", "b"]);
+    assert_eq!(urls("following_sibling IS bold"), vec!["a"]);
+    assert_eq!(urls("preceding_sibling IS regular"), vec!["b", "d"]);
+    assert_eq!(urls("next IS NULL"), vec!["b", "c", "e"]);
+}
+
+#[test]
+fn nesting_axes() {
+    assert_eq!(urls("ancestor IS bold"), vec!["b"]);
+    assert_eq!(urls("parent IS italic"), vec!["b"]);
+    assert_eq!(urls("ancestor IS blockquote"), vec!["c"]);
+    assert_eq!(urls("ancestor IS item"), vec!["d", "e"]);
+    assert_eq!(
+        urls("ancestor IS list AND parent.kind = 'item'"),
+        vec!["d", "e"]
+    );
+
+    let document = md_parser::parse(NESTED);
+    let result = query(
+        &document,
+        "SELECT text FROM paragraph WHERE descendant IS link AND child IS NOT bold",
+    )
+    .unwrap();
+    assert_eq!(texts(result.rows), vec![vec!["quoted"]]);
+
+    let result = query(&document, "SELECT child.kind, descendant.text FROM bold").unwrap();
+    assert_eq!(texts(result.rows), vec![vec!["regular", "bold "]]);
+}
+
+const SECTIONS: &str = "# Project
+
+[home](https://home)
+
+## Installation
+
+Run [the installer](https://install).
+
+### From source
+
+Clone [the repo](https://repo).
+
+## Changelog
+
+- [v2](https://v2)
+
+# Appendix
+
+[faq](https://faq)";
+
+#[test]
+fn section_scopes() {
+    let document = md_parser::parse(SECTIONS);
+    let urls = |text: &str| -> Vec<String> {
+        let result = query(&document, text).unwrap();
+        result.rows.iter().map(|row| row[0].to_string()).collect()
+    };
+
+    assert_eq!(
+        urls("SELECT url FROM link WITHIN SECTION 'Installation'"),
+        vec!["https://install", "https://repo"]
+    );
+    assert_eq!(
+        urls("SELECT url FROM link WITHIN SECTION \"from source\""),
+        vec!["https://repo"]
+    );
+    assert_eq!(
+        urls("SELECT url FROM link UNDER h2"),
+        vec!["https://install", "https://repo", "https://v2"]
+    );
+    assert_eq!(
+        urls("SELECT url FROM link UNDER h1 WHERE NOT url = 'https://home'"),
+        vec![
+            "https://install",
+            "https://repo",
+            "https://v2",
+            "https://faq"
+        ]
+    );
+    assert_eq!(
+        urls("SELECT link UNDER h2 WITHIN SECTION 'Project'"),
+        vec!["the installer", "the repo", "v2"]
+    );
+    assert_eq!(urls("SELECT text FROM h3 UNDER h2"), vec!["From source"]);
+
+    let result = query(&document, "SELECT url, section FROM link").unwrap();
+    assert_eq!(
+        texts(result.rows),
+        vec![
+            vec!["https://home", "Project"],
+            vec!["https://install", "Installation"],
+            vec!["https://repo", "From source"],
+            vec!["https://v2", "Changelog"],
+            vec!["https://faq", "Appendix"],
+        ]
+    );
+
+    assert_eq!(
+        Query::parse("SELECT link UNDER bold").unwrap_err().message,
+        "expected a heading kind, found `bold`"
+    );
+}
+
+const REPORT: &str = "# A
+
+[x](https://a.com/1) [y](https://a.com/2) [x](https://b.com)
+
+## B
+
+![i](https://img/1)
+![j](https://img/1)
+![k](https://img/2)
+
+[z](https://a.com/1)";
+
+fn report(text: &str) -> Vec<Vec<String>> {
+    let document = md_parser::parse(REPORT);
+    texts(query(&document, text).unwrap().rows)
+}
+
+#[test]
+fn aggregates() {
+    assert_eq!(
+        report("SELECT COUNT(*), COUNT(DISTINCT url), count(url) FROM link"),
+        vec![vec!["4", "3", "4"]]
+    );
+    assert_eq!(
+        report("SELECT MIN(level), MAX(level), SUM(level), AVG(level) FROM header"),
+        vec![vec!["1", "2", "3", "1.5"]]
+    );
+    assert_eq!(
+        report("SELECT COUNT(*), MAX(url) FROM link WHERE url = 'none'"),
+        vec![vec!["0", ""]]
+    );
+}
+
+#[test]
+fn grouping() {
+    assert_eq!(
+        report("SELECT section, COUNT(*) AS n FROM link GROUP BY section ORDER BY n DESC"),
+        vec![vec!["A", "3"], vec!["B", "1"]]
+    );
+    assert_eq!(
+        report("SELECT url, COUNT(*) FROM link GROUP BY 1 ORDER BY 2 DESC, url LIMIT 2"),
+        vec![vec!["https://a.com/1", "2"], vec!["https://a.com/2", "1"]]
+    );
+    assert_eq!(
+        report("SELECT DISTINCT url FROM image"),
+        vec![vec!["https://img/1"], vec!["https://img/2"]]
+    );
+    assert_eq!(
+        report("SELECT DISTINCT text FROM link ORDER BY text DESC"),
+        vec![vec!["z"], vec!["y"], vec!["x"]]
+    );
+}
+
+#[test]
+fn ordering_and_limits() {
+    assert_eq!(
+        report("SELECT text FROM link ORDER BY url DESC LIMIT 2 OFFSET 1"),
+        vec![vec!["y"], vec!["x"]]
+    );
+    assert_eq!(
+        report("SELECT label FROM image ORDER BY url, label DESC"),
+        vec![vec!["j"], vec!["i"], vec!["k"]]
+    );
+    assert_eq!(
+        report("SELECT text FROM link LIMIT 0"),
+        Vec::<Vec<String>>::new()
+    );
+}
+
+#[test]
+fn reject_misplaced_aggregates() {
+    let error = |text| Query::parse(text).unwrap_err().message;
+
+    assert_eq!(
+        error("SELECT link WHERE COUNT(*) > 1"),
+        "aggregates are not allowed in WHERE"
+    );
+    assert_eq!(
+        error("SELECT COUNT(*) FROM link GROUP BY COUNT(*)"),
+        "aggregates are not allowed in GROUP BY"
+    );
+    assert_eq!(
+        error("SELECT url FROM link ORDER BY 3"),
+        "there is no column 3"
+    );
+    assert_eq!(
+        error("SELECT lenx(url) FROM link"),
+        "unknown function `lenx`, did you mean `len`?"
+    );
+    assert_eq!(
+        error("SELECT url FROM link LIMIT 'ten'"),
+        "expected a whole number, found `'ten'`"
+    );
+
+    assert_eq!(
+        error("SELECT link ORDER BY"),
+        "expected a column after ORDER BY, found the end of the query"
+    );
+    assert_eq!(
+        error("SELECT link GROUP BY"),
+        "expected a column after GROUP BY, found the end of the query"
+    );
+}
+
+const LINKS: &str = "See [Docs](https://user@Docs.rs:443/a/b?q=1#top), \
+[mail](mailto:me@x.org), [up](../README.md#intro), [cdn](//cdn.net/lib.js) \
+and **[Bold *link*](HTTP://x.org)**.";
+
+fn links(text: &str) -> Vec<Vec<String>> {
+    let document = md_parser::parse(LINKS);
+    texts(query(&document, text).unwrap().rows)
+}
+
+#[test]
+fn url_functions() {
+    assert_eq!(
+        links("SELECT scheme(url), host(url), path(url), is_relative(url) FROM link"),
+        vec![
+            vec!["https", "Docs.rs", "/a/b", "false"],
+            vec!["mailto", "", "me@x.org", "false"],
+            vec!["", "", "../README.md", "true"],
+            vec!["", "cdn.net", "/lib.js", "false"],
+            vec!["http", "x.org", "", "false"],
+        ]
+    );
+    assert_eq!(
+        links("SELECT text FROM link WHERE lower(host(url)) = 'docs.rs' OR is_relative(url)"),
+        vec![vec!["Docs"], vec!["up"]]
+    );
+}
+
+#[test]
+fn text_functions() {
+    assert_eq!(
+        links("SELECT text(self), len(text), lower(label) FROM link WHERE contains(url, 'x.org')"),
+        vec![
+            vec!["mail", "4", "mail"],
+            vec!["Bold link", "9", "bold link"]
+        ]
+    );
+    assert_eq!(
+        links("SELECT text(parent) FROM italic"),
+        vec![vec!["Bold link"]]
+    );
+    assert_eq!(
+        links("SELECT url FROM link WHERE starts_with(url, '//') OR regex_match(url, '^[a-z]+:[a-z]')"),
+        vec![vec!["mailto:me@x.org"], vec!["//cdn.net/lib.js"]]
+    );
+    assert_eq!(
+        links("SELECT len(NULL), regex_match(url, '(') FROM link LIMIT 1"),
+        vec![vec!["", ""]]
+    );
+    assert_eq!(
+        links("SELECT MAX(len(url)), scheme(MAX(url)) FROM link"),
+        vec![vec!["36", "mailto"]]
+    );
+}
+
+#[test]
+fn custom_functions() {
+    let mut functions = Functions::default();
+    functions.register("word_count", 1, |args| match &args[0] {
+        Value::Text(text) => Value::Number(text.split_whitespace().count() as f64),
+        _ => Value::Null,
+    });
+
+    let document = md_parser::parse(DOCUMENT);
+    let query = Query::parse_with(
+        "SELECT text, Word_Count(text) FROM paragraph WHERE word_count(text) > 3",
+        &functions,
+    )
+    .unwrap();
+    assert_eq!(
+        texts(query.execute(&document).rows),
+        vec![vec!["See docs home and other.", "5"]]
+    );
+
+    assert_eq!(
+        Query::parse("SELECT word_count(text) FROM paragraph")
+            .unwrap_err()
+            .message,
+        "unknown function `word_count`"
+    );
+    let error = Query::parse_with("SELECT link WHERE word_count(text, 2)", &functions).unwrap_err();
+    assert_eq!(error.message, "`word_count` takes 1 argument, found 2");
+    assert_eq!(error.span, 18..37);
+}
+
+#[test]
+#[should_panic(expected = "cannot register a function named `max`, the name of an aggregate")]
+fn reject_functions_named_like_aggregates() {
+    Functions::default().register("MAX", 1, |args| args[0].clone());
+}
+
+fn corpus() -> Corpus {
+    let mut corpus = Corpus::new();
+    corpus.add(
+        "README.md",
+        md_parser::parse("# Intro\n\n[home](https://home)"),
+    );
+    corpus.add(
+        "docs/guide.md",
+        md_parser::parse("**Setup** [install](https://install)"),
+    );
+    corpus.add(
+        "./docs/api/query.md",
+        md_parser::parse("Text\n\n## API\n\n[ref](https://ref) [b](https://b)"),
+    );
+    corpus
+}
+
+#[test]
+fn query_many_documents() {
+    let corpus = corpus();
+
+    let result = query_corpus(&corpus, "SELECT url FROM link").unwrap();
+    assert_eq!(result.columns, vec!["file", "line", "column", "url"]);
+    assert_eq!(
+        texts(result.rows),
+        vec![
+            vec!["README.md", "3", "1", "https://home"],
+            vec!["docs/guide.md", "1", "11", "https://install"],
+            vec!["./docs/api/query.md", "5", "1", "https://ref"],
+            vec!["./docs/api/query.md", "5", "20", "https://b"],
+        ]
+    );
+
+    let urls = |text: &str| -> Vec<String> {
+        let result = query_corpus(&corpus, text).unwrap();
+        result.rows.iter().map(|row| row[3].to_string()).collect()
+    };
+    assert_eq!(
+        urls("SELECT url FROM link IN 'docs/**/*.md'"),
+        vec!["https://install", "https://ref", "https://b"]
+    );
+    assert_eq!(
+        urls("SELECT url FROM link IN 'docs/*.md'"),
+        vec!["https://install"]
+    );
+    assert_eq!(
+        urls("SELECT url FROM link IN '*.md' WHERE file = 'README.md'"),
+        vec!["https://home"]
+    );
+
+    // Axes and sections stay within a document.
+    assert_eq!(
+        urls("SELECT url FROM link WHERE preceding IS bold"),
+        vec!["https://install"]
+    );
+    assert_eq!(urls("SELECT url FROM link UNDER h1"), vec!["https://home"]);
+}
+
+#[test]
+fn group_many_documents() {
+    let result = query_corpus(
+        &corpus(),
+        "SELECT file AS path, COUNT(*) AS links FROM link GROUP BY file ORDER BY links DESC, path",
+    )
+    .unwrap();
+    assert_eq!(
+        texts(result.rows),
+        vec![
+            vec!["./docs/api/query.md", "", "", "./docs/api/query.md", "2"],
+            vec!["README.md", "", "", "README.md", "1"],
+            vec!["docs/guide.md", "", "", "docs/guide.md", "1"],
+        ]
+    );
+
+    let result = query_corpus(&corpus(), "SELECT COUNT(*) FROM link").unwrap();
+    assert_eq!(
+        result.rows,
+        vec![vec![
+            Value::Null,
+            Value::Null,
+            Value::Null,
+            Value::Number(4.0)
+        ]]
+    );
+
+    let document = md_parser::parse("[a](b)");
+    assert_eq!(
+        texts(query(&document, "SELECT file FROM link").unwrap().rows),
+        vec![vec![""]]
+    );
+    assert_eq!(
+        Query::parse("SELECT link IN 'docs/***'")
+            .unwrap_err()
+            .message,
+        "invalid file pattern: wildcards are either regular `*` or recursive `**`"
+    );
+}
+
+#[test]
+fn load_files() {
+    let root = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("load_files");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("docs/nested")).unwrap();
+    std::fs::write(root.join("docs/a.md"), "[a](https://a)").unwrap();
+    std::fs::write(root.join("docs/nested/b.md"), "[b](https://b)").unwrap();
+    std::fs::write(root.join("docs/nested/c.txt"), "[c](https://c)").unwrap();
+
+    let pattern = format!("{}/docs/**/*.md", root.display());
+    let corpus = Corpus::load(&pattern).unwrap();
+    let files: Vec<&str> = corpus.iter().map(|(path, _)| path).collect();
+    assert_eq!(
+        files,
+        vec![
+            root.join("docs/a.md").to_str().unwrap(),
+            root.join("docs/nested/b.md").to_str().unwrap(),
+        ]
+    );
+
+    let missing = root.join("missing.md");
+    let error = Corpus::load(missing.to_str().unwrap()).unwrap_err();
+    assert!(matches!(error, LoadError::Io { .. }), "{:?}", error);
+    assert!(matches!(
+        Corpus::load("[").unwrap_err(),
+        LoadError::Pattern { .. }
+    ));
+}
+
+#[test]
+fn compiled_queries() {
+    let query = Query::compile(
+        "SELECT text FROM link WHERE host(url) = :host AND text <> ? OR url = :host",
+    )
+    .unwrap();
+    assert_eq!(
+        query.placeholders().collect::<Vec<_>>(),
+        vec![&Placeholder::Name("host".into()), &Placeholder::Position(1)]
+    );
+
+    let params = Params::new().named("host", "a.com").value("home");
+    let first = md_parser::parse(DOCUMENT);
+    let second = md_parser::parse("[a](https://a.com/x) [home](https://a.com) [c](a.com)");
+    assert_eq!(
+        texts(query.execute(&first, &params).unwrap().rows),
+        Vec::<Vec<String>>::new()
+    );
+    assert_eq!(
+        texts(query.execute(&second, &params).unwrap().rows),
+        vec![vec!["a"], vec!["c"]]
+    );
+
+    // A value is never read as query text.
+    let params = Params::new().named("host", "x' OR '1' = '1").value("home");
+    assert!(query.execute(&second, &params).unwrap().rows.is_empty());
+
+    let query = Query::compile("SELECT level FROM header WHERE level = ? LIMIT 1").unwrap();
+    assert_eq!(
+        texts(
+            query
+                .execute(&first, &Params::new().value(2.0))
+                .unwrap()
+                .rows
+        ),
+        vec![vec!["2"]]
+    );
+    let result = query.execute_corpus(&corpus(), &Params::new().value(1.0));
+    assert_eq!(
+        texts(result.unwrap().rows),
+        vec![vec!["README.md", "1", "1", "1"]]
+    );
+}
+
+#[test]
+fn reject_unbound_placeholders() {
+    let query = Query::compile("SELECT link WHERE url = ? OR text = :label").unwrap();
+    let document = md_parser::parse(DOCUMENT);
+    let error = |params: Params| query.execute(&document, &params).unwrap_err();
+
+    let unbound = error(Params::new().value("x"));
+    assert_eq!(unbound.message, "no value for `:label`");
+    assert_eq!(unbound.span, 36..42);
+    assert_eq!(
+        error(Params::new().named("label", "x")).message,
+        "no value for placeholder 1"
+    );
+    assert_eq!(
+        error(Params::new().value("x").value("y").named("label", "z")).message,
+        "2 values given for 1 `?` placeholder"
+    );
+    assert_eq!(
+        error(Params::new().value("x").named("lable", "z")).message,
+        "there is no placeholder `:lable`"
+    );
+
+    let error = Query::parse("SELECT link WHERE url = ?").unwrap_err();
+    assert_eq!(
+        error.message,
+        "placeholders are only allowed in compiled queries"
+    );
+    assert_eq!(error.span, 24..25);
+}
+
+#[test]
+fn explain_queries() {
+    let plan = Query::parse(
+        "EXPLAIN SELECT DISTINCT section, COUNT(*) AS links FROM link IN 'docs/*.md' \
+         WITHIN SECTION 'It''s' UNDER h2 \
+         WHERE NOT (host(url) = 'a.com' OR url IS NULL) AND prev IS NOT bold \
+         GROUP BY section ORDER BY links DESC, MAX(len(url)) LIMIT 5 OFFSET 1",
+    )
+    .unwrap();
+    assert!(plan.explain);
+    assert_eq!(
+        plan.explain(),
+        "Limit 5 offset 1
+  Sort links DESC, MAX(len(url)) ASC
+    Distinct
+      Project section, COUNT(*) AS links
+        Aggregate by section
+          Filter NOT (host(url) = 'a.com' OR url IS NULL) AND prev IS NOT bold
+            Scope under h2
+              Scope section 'It''s'
+                Scan link in 'docs/*.md'
+"
+    );
+
+    let document = md_parser::parse(DOCUMENT);
+    let result = query(&document, "explain select text from h1 where level = 1").unwrap();
+    assert_eq!(result.columns, vec!["plan"]);
+    assert_eq!(
+        texts(result.rows),
+        vec![
+            vec!["Project text"],
+            vec!["  Filter level = 1"],
+            vec!["    Scan h1"],
+        ]
+    );
+}
+
+#[test]
+fn render_errors() {
+    let text = "SELECT url\nFROM lnk";
+    let error = Query::parse(text).unwrap_err();
+    assert_eq!(
+        error.render(text),
+        "unknown node kind `lnk`, did you mean `link`?
+ --> 2:6
+  |
+2 | FROM lnk
+  |      ^^^"
+    );
+
+    assert_eq!(
+        Query::parse("SELCT link").unwrap_err().message,
+        "expected SELECT, found `SELCT`, did you mean `SELECT`?"
+    );
+    assert_eq!(
+        Query::parse("SELECT link WHERE prv.text = 'a'")
+            .unwrap_err()
+            .message,
+        "unknown field `prv`, did you mean `prev`?"
+    );
+    assert_eq!(
+        Query::parse("SELECT COUNT(*) FROM hedaer")
+            .unwrap_err()
+            .message,
+        "unknown node kind `hedaer`, did you mean `header`?"
+    );
+    assert_eq!(
+        Query::parse("SELECT link WHERE frobnicate(url)")
+            .unwrap_err()
+            .message,
+        "unknown function `frobnicate`"
+    );
+}
+
+const PAGE: &str = "## Install
+
+Get [it](https://example.com/get) and **[docs](https://docs.rs) [x](http://x)**.
+
+Then [more](https://more.org).
+
+# Other
+
+![logo](https://img.org/a.png)
+![icon](http://img.org/b.png)
+
+- [one](https://one) *two*
+  1. [nested](https://nested)";
+
+fn select(selector: &str) -> Vec<String> {
+    let document = md_parser::parse(PAGE);
+    let query = Query::parse_selector(selector).unwrap();
+    let result = query.execute(&document);
+    result.rows.iter().map(|row| row[0].to_string()).collect()
+}
+
+#[test]
+fn selectors() {
+    assert_eq!(select("h2 + p link"), vec!["it", "docs", "x"]);
+    assert_eq!(select("bold > link"), vec!["docs", "x"]);
+    assert_eq!(select("strong>a:first-child"), vec!["docs"]);
+    assert_eq!(select("image[url^=\"https\"]"), vec!["logo"]);
+    assert_eq!(
+        select("link:not([url*=\"example\"]):not(bold link)"),
+        vec!["more", "one", "nested"]
+    );
+    assert_eq!(select("h2 ~ p link[url$='.org']"), vec!["more"]);
+    assert_eq!(
+        select("p:has(bold link[url^=http])"),
+        vec!["Get it and docs x."]
+    );
+    assert_eq!(select("ol a"), vec!["nested"]);
+    assert_eq!(select("li:last-child > list link"), vec!["nested"]);
+    assert_eq!(select("li > link:first-child"), vec!["one", "nested"]);
+    assert_eq!(select("item > italic:last-child"), Vec::<String>::new());
+    assert_eq!(select("h1[level=1]"), vec!["Other"]);
+    assert_eq!(select("link[label~=one]"), vec!["one"]);
+
+    let document = md_parser::parse(PAGE);
+    let selector = Query::parse_selector("h2 + p link").unwrap();
+    let query = Query::parse("SELECT link WHERE ancestor IS paragraph[prev IS h2]").unwrap();
+    assert_eq!(selector.execute(&document), query.execute(&document));
+    assert_eq!(
+        Query::parse_any("explain h2 + p link").unwrap().explain(),
+        "Project link\n  Filter ancestor IS paragraph[prev IS h2]\n    Scan link\n"
+    );
+    assert_eq!(
+        Query::parse_any("  select text FROM h1").unwrap().columns[0].name,
+        "text"
+    );
+}
+
+#[test]
+fn reject_invalid_selectors() {
+    let error = |text| Query::parse_selector(text).unwrap_err();
+
+    assert_eq!(
+        error("lnk").message,
+        "unknown node kind `lnk`, did you mean `link`?"
+    );
+    assert_eq!(
+        error("link[uri^=x]").message,
+        "unknown field `uri`, did you mean `url`?"
+    );
+    assert_eq!(error("link[uri^=x]").span, 5..8);
+    assert_eq!(
+        error("link:frist-child").message,
+        "unknown pseudo-class `frist-child`, did you mean `first-child`?"
+    );
+    assert_eq!(
+        error("bold >").message,
+        "expected a node kind, found the end of the selector"
+    );
+    assert_eq!(error("[url]").message, "expected a node kind, found `[`");
+    assert_eq!(
+        error("link:not([url] > bold)").message,
+        "expected a node kind, found `[`"
+    );
+    assert_eq!(
+        error("link:not(bold))").message,
+        "expected a combinator, found `)`"
+    );
+    assert_eq!(
+        error("link[url!=x]").message,
+        "expected `]` or an operator such as `=` or `^=`, found `!`"
+    );
+    assert_eq!(error("link[url='x]").message, "unterminated string");
+}
+
+fn sample() -> ResultSet {
+    ResultSet {
+        columns: vec!["name".into(), "count".into()],
+        rows: vec![
+            vec![
+                Value::Text("a, \"quoted\"\nline".into()),
+                Value::Number(12.0),
+            ],
+            vec![Value::Null, Value::Number(1.5)],
+        ],
+    }
+}
+
+#[test]
+fn format_json() {
+    assert_eq!(
+        sample().render(Format::Json),
+        "[\n  {\"name\":\"a, \\\"quoted\\\"\\nline\",\"count\":12},\n  {\"name\":null,\"count\":1.5}\n]\n"
+    );
+    assert_eq!(
+        sample().render(Format::Ndjson),
+        "{\"name\":\"a, \\\"quoted\\\"\\nline\",\"count\":12}\n{\"name\":null,\"count\":1.5}\n"
+    );
+
+    let empty = ResultSet {
+        columns: vec!["x".into()],
+        rows: vec![],
+    };
+    assert_eq!(empty.render(Format::Json), "[]\n");
+    assert_eq!(empty.render(Format::Ndjson), "");
+}
+
+#[test]
+fn format_nodes_as_json() {
+    let document = md_parser::parse("a [link](https://a.com)");
+    let result = query(&document, "SELECT link").unwrap();
+
+    assert_eq!(
+        result.render(Format::Ndjson),
+        "{\"link\":{\"kind\":\"link\",\"text\":\"link\",\"line\":1,\"column\":3}}\n"
+    );
+}
+
+#[test]
+fn format_csv() {
+    assert_eq!(
+        sample().render(Format::Csv),
+        "name,count\r\n\"a, \"\"quoted\"\"\nline\",12\r\n,1.5\r\n"
+    );
+}
+
+#[test]
+fn format_table() {
+    assert_eq!(
+        sample().render(Format::Table),
+        "name               count\n\
+         -----------------  -----\n\
+         a, \"quoted\"\\nline     12\n\
+         \x20                    1.5\n"
+    );
+}
+
+#[test]
+fn write_formats_to_io() {
+    let mut out = Vec::new();
+    sample().write(Format::Csv, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        sample().render(Format::Csv)
+    );
+
+    assert_eq!("NDJSON".parse(), Ok(Format::Ndjson));
+    assert_eq!(
+        "xml".parse::<Format>(),
+        Err("unknown format `xml`, expected one of json, ndjson, csv or table".into())
+    );
+}
+
+#[test]
+fn select_cells_by_column() {
+    let document = md_parser::parse(
+        "| Crate | Version |\n| --- | --: |\n| serde | 1.0 |\n| regex | 1.11 |\n\n\
+         > | Quoted | Version |\n> | --- | --- |\n> | glob | 0.3 |",
+    );
+    let result = query(
+        &document,
+        "SELECT text, index, column_header FROM cell WHERE index = 2",
+    )
+    .unwrap();
+    assert_eq!(
+        texts(result.rows),
+        vec![
+            vec!["Version", "2", "Version"],
+            vec!["1.0", "2", "Version"],
+            vec!["1.11", "2", "Version"],
+            vec!["Version", "2", "Version"],
+            vec!["0.3", "2", "Version"],
+        ]
+    );
+
+    let result = query(
+        &document,
+        "SELECT text FROM cell WHERE column_header = 'Crate'",
+    )
+    .unwrap();
+    assert_eq!(
+        texts(result.rows),
+        vec![vec!["Crate"], vec!["serde"], vec!["regex"]]
+    );
+}
+
+#[test]
+fn cell_fields_do_not_hide_header_nodes() {
+    let document = md_parser::parse(
+        "# Crates
+
+| Crate |
+| --- |
+| serde |",
+    );
+
+    let result = query(&document, "SELECT header").unwrap();
+    assert_eq!(result.columns, vec!["header"]);
+    assert_eq!(texts(result.rows), vec![vec!["Crates"]]);
+    assert_eq!(
+        texts(query(&document, "SELECT header FROM header").unwrap().rows),
+        vec![vec!["Crates"]]
+    );
+}
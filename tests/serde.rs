@@ -0,0 +1,84 @@
+use md_parser::{Document, Line, Span, Token};
+use serde_json::json;
+
+/// Every kind of node, to lock the shape of all of them.
+const DOCUMENT: &str = "# Title
+
+A **bold**, *italic* and `code` [link](https://a.com)
+on two lines.
+
+![logo](logo.png)
+
+```rust
+let x = 1;
+```
+
+3. first
+   - nested
+
+> quoted
+
+| a | b |
+| :-- | --: |
+| 1 | 2 |
+";
+
+const SNAPSHOT: &str = include_str!("snapshots/document.json");
+
+#[test]
+fn serialize_snapshot() {
+    let document = md_parser::parse(DOCUMENT);
+    let json = serde_json::to_string_pretty(&document).unwrap();
+    assert_eq!(json, SNAPSHOT.trim_end());
+
+    let parsed: Document = serde_json::from_str(SNAPSHOT).unwrap();
+    assert_eq!(parsed, document);
+}
+
+#[test]
+fn serialize_tagged_nodes() {
+    let link = Token::Link {
+        label: vec![Token::Regular("docs".into(), Span::default())],
+        url: "https://docs.rs".into(),
+        span: Span::default(),
+    };
+    let span = json!({ "start": 0, "end": 0, "line": 0, "column": 0 });
+    assert_eq!(
+        serde_json::to_value(&link).unwrap(),
+        json!({
+            "type": "link",
+            "url": "https://docs.rs",
+            "label": [{ "type": "regular", "text": "docs", "span": span }],
+            "span": span,
+        })
+    );
+
+    // Spans may be left out.
+    let line: Line = serde_json::from_value(json!({
+        "type": "header",
+        "level": 2,
+        "tokens": [{ "type": "regular", "text": "Title" }],
+    }))
+    .unwrap();
+    assert_eq!(
+        line,
+        Line::Header {
+            level: md_parser::HeaderLevel::H2,
+            tokens: vec![Token::Regular("Title".into(), Span::default())],
+            span: Span::default(),
+        }
+    );
+}
+
+#[test]
+fn reject_invalid_nodes() {
+    let error = |value| {
+        serde_json::from_value::<Line>(value)
+            .unwrap_err()
+            .to_string()
+    };
+    assert!(error(json!({ "type": "header", "level": 7, "tokens": [] }))
+        .contains("invalid heading level 7, expected 1 to 6"));
+    assert!(error(json!({ "type": "heading", "tokens": [] })).contains("unknown variant `heading`"));
+    assert!(error(json!({ "type": "blank", "extra": 1 })).contains("unknown field `extra`"));
+}
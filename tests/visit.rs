@@ -0,0 +1,108 @@
+use md_parser::{ListItem, Span, Token, Visit, VisitMut};
+
+const DOCUMENT: &str = "# [Title](/title)
+
+![logo](logo.png)
+
+- an *[item](/item)*
+
+> [quoted](/quoted)
+
+| [cell](/cell) |
+| --- |
+| `code` |
+";
+
+/// The URLs of links and images, in document order.
+#[derive(Default)]
+struct Urls(Vec<String>);
+
+impl Visit for Urls {
+    fn visit_image(&mut self, label: &[Token], url: &str, _: Span) {
+        self.0.push(format!("image {}", url));
+        md_parser::walk_tokens(self, label);
+    }
+
+    fn visit_link(&mut self, label: &[Token], url: &str, _: Span) {
+        self.0.push(format!("link {}", url));
+        md_parser::walk_tokens(self, label);
+    }
+}
+
+#[test]
+fn visit_nested_nodes() {
+    let document = md_parser::parse(DOCUMENT);
+    let mut urls = Urls::default();
+    urls.visit_lines(&document);
+    assert_eq!(
+        urls.0,
+        [
+            "link /title",
+            "image logo.png",
+            "link /item",
+            "link /quoted",
+            "link /cell",
+        ]
+    );
+}
+
+#[test]
+fn visit_spans_before_their_nodes() {
+    #[derive(Default)]
+    struct Kinds(Vec<String>);
+
+    impl Visit for Kinds {
+        fn visit_span(&mut self, span: Span) {
+            self.0.push(format!("{}:{}", span.line, span.column));
+        }
+
+        fn visit_list_item(&mut self, item: &ListItem) {
+            self.0.push("item".to_string());
+            md_parser::walk_list_item(self, item);
+        }
+
+        fn visit_regular(&mut self, text: &str, _: Span) {
+            self.0.push(text.to_string());
+        }
+
+        // Code is left out by not walking into it.
+        fn visit_inline_code(&mut self, _: &[Token], _: Span) {}
+    }
+
+    let mut kinds = Kinds::default();
+    kinds.visit_lines(&md_parser::parse("- a `b`\n"));
+    assert_eq!(kinds.0, ["1:1", "item", "1:1", "1:3", "a ", "1:5"]);
+}
+
+#[test]
+fn change_nodes_in_place() {
+    struct Edit;
+
+    impl VisitMut for Edit {
+        fn visit_link_mut(&mut self, label: &mut Vec<Token>, url: &mut String, _: &mut Span) {
+            url.insert_str(0, "https://a.com");
+            md_parser::walk_tokens_mut(self, label);
+        }
+
+        fn visit_regular_mut(&mut self, text: &mut String, _: &mut Span) {
+            *text = text.to_uppercase();
+        }
+
+        fn visit_code_block_mut(&mut self, _: &mut String, content: &mut String, _: &mut Span) {
+            content.clear();
+        }
+    }
+
+    let mut document = md_parser::parse(DOCUMENT);
+    Edit.visit_lines_mut(&mut document);
+    assert_eq!(
+        md_parser::to_markdown(&document),
+        "# [TITLE](https://a.com/title)\n\n![LOGO](logo.png)\n\n\
+         - AN *[ITEM](https://a.com/item)*\n\n> [QUOTED](https://a.com/quoted)\n\n\
+         | [CELL](https://a.com/cell) |\n| --- |\n| `CODE` |\n"
+    );
+
+    let mut code = md_parser::parse("```\nx\n```\n");
+    Edit.visit_lines_mut(&mut code);
+    assert_eq!(md_parser::to_markdown(&code), "```\n```\n");
+}